@@ -0,0 +1,91 @@
+//! Time checking Schnorr proofs one by one and in a batch, under one key and under distinct
+//! keys.
+//!
+//! Run with `cargo bench --bench batch_verification`.
+
+use std::time::{Duration, Instant};
+
+use num::BigInt;
+use sigma::{
+    applications::signatures::generate_key,
+    batch::SchnorrBatch,
+    fiat_shamir::NonInteractiveProof,
+    schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol},
+};
+
+const ITERATIONS: u32 = 5;
+
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+type Proof = NonInteractiveProof<BigInt, BigInt>;
+
+fn make_proofs(
+    params: &SchnorrDiscreteLogInstance,
+    n: usize,
+    shared_key: bool,
+) -> Vec<(SchnorrDiscreteLogInstance, Proof)> {
+    let group = params.group();
+    let (x, h) = generate_key(&group);
+    (0..n)
+        .map(|_| {
+            let (x, h) = if shared_key {
+                (x.clone(), h.clone())
+            } else {
+                generate_key(&group)
+            };
+            let instance = SchnorrDiscreteLogInstance::new(
+                params.p().clone(),
+                params.q().clone(),
+                params.g().clone(),
+                h,
+            );
+            let bits = instance.challenge_bits();
+            let proof = Proof::prove::<SchnorrDiscreteLogProtocol, _, _>(
+                b"bench",
+                instance.clone(),
+                x.expose().clone(),
+                bits,
+            );
+            (instance, proof)
+        })
+        .collect()
+}
+
+fn main() {
+    let (params, _) = SchnorrDiscreteLogInstance::generate(1024, 160);
+
+    for shared_key in [true, false] {
+        for n in [16, 64, 256] {
+            let proofs = make_proofs(&params, n, shared_key);
+
+            let individual = time(|| {
+                for (instance, proof) in &proofs {
+                    let bits = instance.challenge_bits();
+                    proof
+                        .verify::<SchnorrDiscreteLogProtocol, _, BigInt>(
+                            b"bench",
+                            instance.clone(),
+                            bits,
+                        )
+                        .unwrap();
+                }
+            });
+            let batch = time(|| {
+                let mut batch = SchnorrBatch::new(params.group());
+                for (instance, proof) in &proofs {
+                    batch.add_proof(instance, proof, b"bench").unwrap();
+                }
+                batch.verify().unwrap();
+            });
+
+            let keys = if shared_key { "one key" } else { "n keys" };
+            println!("n = {n}, {keys}: individual {individual:?}, batch {batch:?}");
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Time the Montgomery arithmetic behind `SchnorrGroup` against plain `BigInt` arithmetic, which
+//! divides by the modulus after every product, at 2048 and 3072 bits.
+//!
+//! Run with `cargo bench --bench montgomery`.
+
+use std::time::{Duration, Instant};
+
+use num::{bigint::RandBigInt, BigInt, Integer};
+use sigma::{
+    montgomery::Modulus,
+    multiexp::{self, Multiply},
+};
+
+const ITERATIONS: u32 = 20;
+
+/// The number of bits of the exponents, as for a subgroup of order about `2^256`.
+const EXPONENT_BITS: usize = 256;
+
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+/// Arithmetic modulo `p` with `BigInt` products and `mod_floor`.
+struct BigIntModulus(BigInt);
+
+impl Multiply for BigIntModulus {
+    type Element = BigInt;
+
+    fn identity(&self) -> BigInt {
+        BigInt::from(1)
+    }
+
+    fn mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        (a * b).mod_floor(&self.0)
+    }
+}
+
+fn main() {
+    let mut rng = rand::thread_rng();
+
+    for bits in [2048, 3072] {
+        let p = (BigInt::from(1) << (bits - 1)) + BigInt::from(rng.gen_biguint(bits - 2)) * 2 + 1;
+        let modulus = Modulus::new(&p);
+        let plain = BigIntModulus(p.clone());
+
+        let (a, b) = (
+            rng.gen_bigint_range(&1.into(), &p),
+            rng.gen_bigint_range(&1.into(), &p),
+        );
+        let (ra, rb) = (modulus.residue(&a), modulus.residue(&b));
+        let e = BigInt::from(rng.gen_biguint(EXPONENT_BITS));
+        let bases = (0..16)
+            .map(|_| rng.gen_bigint_range(&1.into(), &p))
+            .collect::<Vec<_>>();
+        let residues = bases.iter().map(|b| modulus.residue(b)).collect::<Vec<_>>();
+        let exponents = (0..16)
+            .map(|_| BigInt::from(rng.gen_biguint(EXPONENT_BITS)))
+            .collect::<Vec<_>>();
+
+        let mul = (time(|| plain.mul(&a, &b)), time(|| modulus.mul(&ra, &rb)));
+        let exp = (time(|| a.modpow(&e, &p)), time(|| modulus.pow(&ra, &e)));
+        let multi_exp = (
+            time(|| multiexp::multi_exp(&plain, &bases, &exponents)),
+            time(|| multiexp::multi_exp(&modulus, &residues, &exponents)),
+        );
+
+        println!("{bits} bits:");
+        println!("  mul: BigInt {:?}, Montgomery {:?}", mul.0, mul.1);
+        println!("  exp: BigInt::modpow {:?}, Montgomery {:?}", exp.0, exp.1);
+        println!(
+            "  multi_exp of 16: BigInt {:?}, Montgomery {:?}",
+            multi_exp.0, multi_exp.1
+        );
+    }
+}
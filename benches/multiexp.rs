@@ -0,0 +1,55 @@
+//! Time multi-exponentiations and fixed-base exponentiations against separate
+//! exponentiations.
+//!
+//! Run with `cargo bench --bench multiexp`.
+
+use std::time::{Duration, Instant};
+
+use num::BigInt;
+use sigma::{
+    group::{PrimeOrderGroup, SchnorrGroup},
+    multiexp::{pippenger, straus, FixedBaseTable},
+    schnorr::SchnorrDiscreteLogInstance,
+};
+
+const ITERATIONS: u32 = 10;
+
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+/// The product of `bases[i]^exponents[i]`, with one exponentiation each.
+fn naive(group: &SchnorrGroup, bases: &[BigInt], exponents: &[BigInt]) -> BigInt {
+    let p = group.modulus();
+    bases
+        .iter()
+        .zip(exponents)
+        .fold(BigInt::from(1), |acc, (b, e)| acc * b.modpow(e, p) % p)
+}
+
+fn main() {
+    let group = SchnorrDiscreteLogInstance::generate(1024, 160).0.group();
+    let g = group.generator();
+
+    for n in [2, 4, 8, 16, 128] {
+        let bases = (0..n)
+            .map(|_| group.exp(&g, &group.random_scalar()))
+            .collect::<Vec<_>>();
+        let exponents = (0..n).map(|_| group.random_scalar()).collect::<Vec<_>>();
+
+        let separate = time(|| naive(&group, &bases, &exponents));
+        let straus = time(|| straus(&group, &bases, &exponents));
+        let pippenger = time(|| pippenger(&group, &bases, &exponents));
+        println!("n = {n}: separate {separate:?}, straus {straus:?}, pippenger {pippenger:?}");
+    }
+
+    let table = FixedBaseTable::new(&group, &g, group.order().bits());
+    let e = group.random_scalar();
+    let modpow = time(|| g.modpow(&e, group.modulus()));
+    let fixed_base = time(|| table.exp(&group, &e));
+    println!("fixed base: modpow {modpow:?}, table {fixed_base:?}");
+}
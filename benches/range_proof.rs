@@ -0,0 +1,48 @@
+//! Time range proofs for 32 and 64 bit values, bit by bit and as Bulletproofs.
+//!
+//! Run with `cargo bench --bench range_proof`.
+
+use std::time::{Duration, Instant};
+
+use num::BigInt;
+use sigma::applications::{
+    bulletproofs::{Bulletproof, BulletproofGens},
+    commitments::{CommitmentScheme, PedersenScheme},
+    range_proof::RangeProof,
+};
+
+const ITERATIONS: u32 = 10;
+
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let scheme = PedersenScheme::new(CommitmentScheme::gen_params(1024, 160));
+    scheme.precompute();
+    let gens = BulletproofGens::for_pedersen(&scheme, 64);
+
+    for bits in [32, 64] {
+        let v = (BigInt::from(1) << (bits - 1)) + 12345;
+        let (c, r) = scheme.commit(&v);
+        let r = r.expose();
+        let proof = RangeProof::prove(&scheme, &c, &v, r, bits).unwrap();
+
+        let prove = time(|| RangeProof::prove(&scheme, &c, &v, r, bits).unwrap());
+        let verify = time(|| proof.verify(&scheme, &c, bits).unwrap());
+        println!("range proof, n = {bits}: prove {prove:?}, verify {verify:?}");
+
+        let values = [v.clone()];
+        let blindings = [r.clone()];
+        let proof = Bulletproof::prove(&gens, &values, &blindings, bits).unwrap();
+        let commitments = [c.clone()];
+
+        let prove = time(|| Bulletproof::prove(&gens, &values, &blindings, bits).unwrap());
+        let verify = time(|| proof.verify(&gens, &commitments, bits).unwrap());
+        println!("bulletproof, n = {bits}: prove {prove:?}, verify {verify:?}");
+    }
+}
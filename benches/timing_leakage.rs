@@ -0,0 +1,129 @@
+//! A dudect-style check that operations on secrets run in constant time.
+//!
+//! Each operation runs on secrets from two classes, one fixed secret and fresh random ones, in
+//! random order. The running times of the two classes are compared with Welch's t-test, on all
+//! measurements and again without the slowest ones above a few percentiles, as in dudect. A `|t|`
+//! above 4.5 means that the time most likely depends on the secret. The variable-time paths run
+//! alongside the constant-time ones, to show that the test catches them.
+//!
+//! The fixed secret has a single bit set, and the random ones have the same number of bits, so
+//! that only the arithmetic, not the length of the `BigInt`s, tells them apart.
+//!
+//! Run with `cargo bench --bench timing_leakage`, preferably on an idle machine.
+
+use std::{hint::black_box, time::Instant};
+
+use num::{bigint::RandBigInt, BigInt, Integer};
+use rand::Rng;
+use sigma::{
+    group::{PrimeOrderGroup, SchnorrGroup},
+    schnorr::SchnorrDiscreteLogInstance,
+};
+
+const MEASUREMENTS: usize = 10_000;
+
+/// The `|t|` above which the two classes are taken to have different running times.
+const THRESHOLD: f64 = 4.5;
+
+/// The percentiles above which measurements are dropped, besides keeping them all.
+const PERCENTILES: [f64; 4] = [0.5, 0.75, 0.9, 0.99];
+
+/// The mean and the variance of `samples`.
+fn moments(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance)
+}
+
+/// Welch's t statistic of the two classes of measurements.
+fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+    let ((mean_a, var_a), (mean_b, var_b)) = (moments(a), moments(b));
+    (mean_a - mean_b) / (var_a / a.len() as f64 + var_b / b.len() as f64).sqrt()
+}
+
+/// Time `f` on secrets of both classes, and return the largest `|t|` over the croppings.
+fn largest_t(q: &BigInt, f: impl Fn(&BigInt) -> BigInt) -> f64 {
+    let mut rng = rand::thread_rng();
+    let low = BigInt::from(1) << (q.bits() - 1);
+    let inputs = (0..MEASUREMENTS)
+        .map(|_| {
+            let fixed = rng.gen::<bool>();
+            let secret = if fixed {
+                low.clone()
+            } else {
+                rng.gen_bigint_range(&low, q)
+            };
+            (fixed, secret)
+        })
+        .collect::<Vec<_>>();
+
+    let times = inputs
+        .iter()
+        .map(|(fixed, secret)| {
+            let start = Instant::now();
+            black_box(f(black_box(secret)));
+            (*fixed, start.elapsed().as_nanos() as f64)
+        })
+        .collect::<Vec<_>>();
+
+    let mut sorted = times.iter().map(|(_, t)| *t).collect::<Vec<_>>();
+    sorted.sort_by(f64::total_cmp);
+    let cutoffs = PERCENTILES
+        .iter()
+        .map(|p| sorted[(p * sorted.len() as f64) as usize])
+        .chain([f64::INFINITY]);
+
+    cutoffs
+        .map(|cutoff| {
+            let class = |fixed: bool| {
+                times
+                    .iter()
+                    .filter(|(f, t)| *f == fixed && *t <= cutoff)
+                    .map(|(_, t)| *t)
+                    .collect::<Vec<_>>()
+            };
+            welch_t(&class(true), &class(false)).abs()
+        })
+        .fold(0.0, f64::max)
+}
+
+fn report(name: &str, t: f64) {
+    let verdict = if t > THRESHOLD {
+        "depends on the secret"
+    } else {
+        "no dependence found"
+    };
+    println!("{name}: max |t| = {t:.1}, {verdict}");
+}
+
+fn main() {
+    let (instance, _) = SchnorrDiscreteLogInstance::generate(1024, 160);
+    let group: SchnorrGroup = instance.group();
+    let (p, q, g) = (group.modulus(), group.order(), group.generator());
+    let w = group.random_scalar();
+    let e = group.random_scalar();
+
+    report("BigInt::modpow", largest_t(q, |x| g.modpow(x, p)));
+    report(
+        "SchnorrGroup::exp_secret",
+        largest_t(q, |x| group.exp_secret(&g, x)),
+    );
+
+    instance.precompute();
+    report("fixed-base pow_g", largest_t(q, |x| instance.pow_g(x)));
+    report(
+        "fixed-base pow_g_secret",
+        largest_t(q, |x| instance.pow_g_secret(x)),
+    );
+
+    // The secret is the witness here, and the nonce a fixed random one.
+    report(
+        "BigInt response",
+        largest_t(q, |x| (&w + &e * x).mod_floor(q)),
+    );
+    report(
+        "SchnorrGroup::response",
+        largest_t(q, |x| group.response(&w, &e, x)),
+    );
+}
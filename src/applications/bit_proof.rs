@@ -0,0 +1,333 @@
+//! Proofs that a Pedersen commitment hides a bit.
+//!
+//! A commitment `C = g^m h^r` hides 0 exactly when `C = h^r`, and 1 exactly when `C / g = h^r`.
+//! Proving knowledge of `r` for one of the two, without revealing which, is an OR of two
+//! Schnorr statements with `h` as the base.
+
+use std::io;
+
+use num::{BigInt, Signed};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    netutil::BinaryEncode,
+    or::{NaryOrProtocol, OrProtocolVerifierError},
+    schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol, SchnorrVerifierError},
+    transcript::Transcript,
+    SigmaProtocol,
+};
+
+/// A Pedersen commitment, together with its parameters.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BitInstance {
+    params: SchnorrDiscreteLogInstance,
+    commitment: BigInt,
+}
+
+impl BitInstance {
+    pub fn new(params: SchnorrDiscreteLogInstance, commitment: BigInt) -> Self {
+        BitInstance { params, commitment }
+    }
+
+    pub fn params(&self) -> &SchnorrDiscreteLogInstance {
+        &self.params
+    }
+
+    pub fn commitment(&self) -> &BigInt {
+        &self.commitment
+    }
+
+    /// Whether the commitment is in the subgroup, so that the statements are meaningful.
+    fn is_valid(&self) -> bool {
+        self.params.is_element(&self.commitment)
+    }
+
+    /// The statements `C = h^r` and `C / g = h^r`.
+    fn statements(&self) -> [SchnorrDiscreteLogInstance; 2] {
+        let (p, q) = (self.params.p(), self.params.q());
+        let g_inv = self.params.g().modpow(&(q - 1), p);
+        let statement = |target| {
+            SchnorrDiscreteLogInstance::new(p.clone(), q.clone(), self.params.h().clone(), target)
+        };
+        [
+            statement(self.commitment.clone()),
+            statement((&self.commitment * g_inv) % p),
+        ]
+    }
+}
+
+pub struct BitProtocol {
+    instance: BitInstance,
+    inner: NaryOrProtocol<SchnorrDiscreteLogProtocol, BigInt, BigInt>,
+}
+
+#[derive(Debug)]
+pub enum BitVerifierError {
+    /// The commitment is not an element of the group.
+    InvalidCommitment,
+    /// A challenge or response of a proof is negative or too large.
+    ScalarOutOfRange,
+    SubProtocolError(OrProtocolVerifierError<SchnorrVerifierError>),
+}
+
+/// The witness is the bit and the randomness of the commitment.
+impl SigmaProtocol<BitInstance, (bool, BigInt), Vec<BigInt>, BigInt, Vec<(BigInt, BigInt)>>
+    for BitProtocol
+{
+    type VerifierError = BitVerifierError;
+
+    fn new(instance: BitInstance, witness: Option<(bool, BigInt)>) -> Self {
+        let inner = NaryOrProtocol::new(
+            instance.statements().to_vec(),
+            witness.map(|(bit, r)| (bit as usize, r)),
+        );
+        BitProtocol { instance, inner }
+    }
+
+    fn initial_message(&mut self) -> Vec<BigInt> {
+        self.inner.initial_message()
+    }
+
+    fn challenge(&mut self) -> BigInt {
+        self.inner.challenge()
+    }
+
+    fn challenge_response(&mut self, challenge: &BigInt) -> Vec<(BigInt, BigInt)> {
+        self.inner.challenge_response(challenge)
+    }
+
+    fn check(
+        &mut self,
+        initial_msg: Vec<BigInt>,
+        challenge: BigInt,
+        response: Vec<(BigInt, BigInt)>,
+    ) -> Result<(), Self::VerifierError> {
+        if !self.instance.is_valid() {
+            return Err(BitVerifierError::InvalidCommitment);
+        }
+        self.inner
+            .check(initial_msg, challenge, response)
+            .map_err(BitVerifierError::SubProtocolError)
+    }
+
+    fn simulate(&mut self, challenge: &BigInt) -> (Vec<BigInt>, Vec<(BigInt, BigInt)>) {
+        self.inner.simulate(challenge)
+    }
+}
+
+/// A non-interactive bit proof, made with the Fiat-Shamir transform.
+///
+/// Only the challenges and responses are kept, as the initial messages follow from them. This
+/// makes a proof four scalars long.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BitProof {
+    e0: BigInt,
+    z0: BigInt,
+    e1: BigInt,
+    z1: BigInt,
+}
+
+impl BitProof {
+    /// Prove that `instance` hides `bit`, given the randomness `r` of the commitment.
+    pub fn prove(instance: &BitInstance, bit: bool, r: &BigInt) -> Self {
+        let mut protocol = BitProtocol::new(instance.clone(), Some((bit, r.clone())));
+        let a = protocol.initial_message();
+        let e = fiat_shamir_challenge(instance, &a);
+        let mut response = protocol.challenge_response(&e).into_iter();
+        let (e0, z0) = response.next().unwrap();
+        let (e1, z1) = response.next().unwrap();
+        BitProof { e0, z0, e1, z1 }
+    }
+
+    pub fn verify(&self, instance: &BitInstance) -> Result<(), BitVerifierError> {
+        if !instance.is_valid() {
+            return Err(BitVerifierError::InvalidCommitment);
+        }
+
+        // Challenges are in `[0, 2^bits)` and responses in `[0, q)`, which also keeps the
+        // exponents below from being negative.
+        let params = instance.params();
+        let (p, q) = (params.p(), params.q());
+        let challenge_bound = BigInt::from(1) << params.challenge_bits();
+        let in_range = |x: &BigInt, bound: &BigInt| !x.is_negative() && x < bound;
+        if ![&self.e0, &self.e1]
+            .iter()
+            .all(|e| in_range(e, &challenge_bound))
+            || ![&self.z0, &self.z1].iter().all(|z| in_range(z, q))
+        {
+            return Err(BitVerifierError::ScalarOutOfRange);
+        }
+
+        // Recover the initial messages as `a = h^z X^-e`.
+        let [x0, x1] = instance.statements();
+        let initial_msg = |x: &SchnorrDiscreteLogInstance, e: &BigInt, z: &BigInt| {
+            let x_inv_e = x.h().modpow(&(q - e % q), p);
+            (params.h().modpow(&(z % q), p) * x_inv_e) % p
+        };
+        let a = vec![
+            initial_msg(&x0, &self.e0, &self.z0),
+            initial_msg(&x1, &self.e1, &self.z1),
+        ];
+
+        let e = fiat_shamir_challenge(instance, &a);
+        let response = vec![
+            (self.e0.clone(), self.z0.clone()),
+            (self.e1.clone(), self.z1.clone()),
+        ];
+        BitProtocol::new(instance.clone(), None).check(a, e, response)
+    }
+}
+
+impl BinaryEncode for BitProof {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        let values = (
+            self.e0.clone(),
+            self.z0.clone(),
+            self.e1.clone(),
+            self.z1.clone(),
+        );
+        values.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let (e0, z0, e1, z1) = BinaryEncode::decode(int_len, input)?;
+        Ok(BitProof { e0, z0, e1, z1 })
+    }
+}
+
+fn fiat_shamir_challenge(instance: &BitInstance, initial_msg: &[BigInt]) -> BigInt {
+    let mut transcript = Transcript::new(b"bit proof");
+    transcript.append_value(b"instance", instance);
+    transcript.append_value(b"initial message", &initial_msg);
+    transcript.challenge(b"challenge", instance.params().challenge_bits())
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::commitments::{CommitmentScheme, PedersenScheme},
+        netutil::BinaryEncode,
+        SigmaProtocol,
+    };
+
+    use super::{BitInstance, BitProof, BitProtocol, BitVerifierError};
+
+    // A cheating prover succeeds with probability `2^-challenge_bits`, so the rejection tests
+    // need a larger group than the usual test group.
+    fn make_scheme() -> PedersenScheme {
+        PedersenScheme::new(CommitmentScheme::gen_params(256, 64))
+    }
+
+    fn commit(scheme: &PedersenScheme, m: i32) -> (BitInstance, BigInt) {
+        let (c, r) = scheme.commit(&BigInt::from(m));
+        (
+            BitInstance::new(scheme.params().clone(), c),
+            r.expose().clone(),
+        )
+    }
+
+    #[test]
+    fn honest_run_is_accepted() {
+        let scheme = make_scheme();
+        for bit in [false, true] {
+            let (instance, r) = commit(&scheme, bit as i32);
+            let mut protocol = BitProtocol::new(instance, Some((bit, r)));
+            let res = protocol.run_protocol();
+            assert!(res.is_ok(), "Honest run not accepted: {:?}", res)
+        }
+    }
+
+    #[test]
+    fn simulator_is_accepted() {
+        let scheme = make_scheme();
+        let (instance, _) = commit(&scheme, 7);
+        let mut protocol = BitProtocol::new(instance, None);
+
+        let e = protocol.challenge();
+        let (a, z) = protocol.simulate(&e);
+
+        let res = protocol.check(a, e, z);
+        assert!(res.is_ok(), "Simulator not accepted: {:?}", res)
+    }
+
+    #[test]
+    fn non_bits_are_rejected() {
+        let scheme = make_scheme();
+        for m in [2, -1] {
+            let (instance, r) = commit(&scheme, m);
+            for bit in [false, true] {
+                let mut protocol = BitProtocol::new(instance.clone(), Some((bit, r.clone())));
+                assert!(protocol.run_protocol().is_err());
+
+                let proof = BitProof::prove(&instance, bit, &r);
+                assert!(proof.verify(&instance).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn non_interactive_proof_is_accepted() {
+        let scheme = make_scheme();
+        for bit in [false, true] {
+            let (instance, r) = commit(&scheme, bit as i32);
+            let proof = BitProof::prove(&instance, bit, &r);
+            let res = proof.verify(&instance);
+            assert!(res.is_ok(), "Proof not accepted: {:?}", res);
+
+            // The proof is bound to its commitment.
+            let (other, _) = commit(&scheme, bit as i32);
+            assert!(proof.verify(&other).is_err());
+        }
+    }
+
+    #[test]
+    fn non_interactive_proof_is_compact() {
+        let scheme = make_scheme();
+        let (instance, r) = commit(&scheme, 1);
+        let proof = BitProof::prove(&instance, true, &r);
+
+        let mut bytes = vec![];
+        proof.encode(32, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4 * 32);
+        assert_eq!(BitProof::decode(32, &mut &bytes[..]).unwrap(), proof);
+    }
+
+    #[test]
+    fn commitment_outside_group_is_rejected() {
+        let scheme = make_scheme();
+        let (instance, r) = commit(&scheme, 1);
+        let proof = BitProof::prove(&instance, true, &r);
+
+        // -1 is not in the subgroup of order q, so neither is -c.
+        let outside = BitInstance::new(
+            scheme.params().clone(),
+            scheme.params().p() - instance.commitment(),
+        );
+        assert!(matches!(
+            proof.verify(&outside),
+            Err(BitVerifierError::InvalidCommitment)
+        ));
+    }
+
+    #[test]
+    fn scalars_out_of_range_are_rejected() {
+        let scheme = make_scheme();
+        let (instance, r) = commit(&scheme, 0);
+        let proof = BitProof::prove(&instance, false, &r);
+        let q = scheme.params().q();
+
+        let mut tampered = [proof.clone(), proof.clone(), proof.clone(), proof];
+        tampered[0].z0 = -tampered[0].z0.clone() - 1;
+        tampered[1].e1 = -tampered[1].e1.clone() - 1;
+        tampered[2].z1 = &tampered[2].z1 + q;
+        tampered[3].e0 = BigInt::from(1) << scheme.params().challenge_bits();
+        for proof in tampered {
+            assert!(matches!(
+                proof.verify(&instance),
+                Err(BitVerifierError::ScalarOutOfRange)
+            ));
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//! Blind Schnorr signatures, and anonymous tokens made from them.
+//!
+//! The signer runs the prover of [`SchnorrDiscreteLogProtocol`] for its key `X = g^x`, and the
+//! user plays the verifier, but blinds what it sees and sends:
+//!
+//! 1. The signer sends `a = g^k`.
+//! 2. The user picks random `alpha` and `beta`, computes `R = a g^alpha X^beta` and the
+//!    challenge `c` of the message `m` for `R`, and sends `e = c + beta`.
+//! 3. The signer sends `z = k + e x`, and the user checks it and outputs the signature
+//!    `(R, z + alpha)` on `m`, which is an ordinary [`Signature`].
+//!
+//! `R` and `e` are uniformly random and independent of `a` and `z`, so the signer learns
+//! nothing about which signature came from which session.
+//!
+//! # Concurrent sessions
+//!
+//! Blind Schnorr signatures are only secure if the sessions of the signer do not overlap. With
+//! `l` sessions open at the same time, a user can make `l + 1` valid signatures by solving the
+//! ROS problem: with Wagner's algorithm in subexponential time for moderate `l`, and in
+//! polynomial time once `l` is larger than the bit length of the order
+//! ([Benhamouda et al.](https://eprint.iacr.org/2020/945)). [`BlindSigner`] therefore refuses
+//! to open more than a fixed number of sessions at once. The cap only makes the attack more
+//! expensive, so it should be kept small, and set to 1 where the signer can afford to serve
+//! users one at a time.
+
+use std::collections::{HashMap, HashSet};
+
+use num::{BigInt, Integer};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::signatures::{self, challenge, Signature, SignatureError},
+    group::PrimeOrderGroup,
+    schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol, SchnorrVerifierError},
+    secret::Secret,
+    session::random_contribution,
+    SigmaProtocol,
+};
+
+/// The default cap on concurrent sessions of a [`BlindSigner`].
+pub const DEFAULT_MAX_SESSIONS: usize = 4;
+
+#[derive(Debug)]
+pub enum BlindSignatureError {
+    /// The signer already has the maximum number of sessions open.
+    TooManySessions { max: usize },
+    /// There is no open session with this id, as it never existed or is already finished.
+    UnknownSession(u64),
+    /// The initial message of the signer is not an element of the group.
+    InvalidInitialMessage,
+    /// The response of the signer does not match its initial message and the challenge.
+    InvalidResponse(SchnorrVerifierError),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenError {
+    InvalidSignature(SignatureError),
+    /// The token was already redeemed.
+    DoubleSpend,
+}
+
+/// The signer, which holds the secret key and the sessions it has started.
+pub struct BlindSigner {
+    key: SchnorrDiscreteLogInstance,
+    secret_key: Secret<BigInt>,
+    max_sessions: usize,
+    sessions: HashMap<u64, SchnorrDiscreteLogProtocol>,
+    next_session: u64,
+}
+
+impl BlindSigner {
+    /// A signer for the key `key`, whose discrete log is `secret_key`, which allows at most
+    /// [`DEFAULT_MAX_SESSIONS`] concurrent sessions.
+    pub fn new(key: SchnorrDiscreteLogInstance, secret_key: Secret<BigInt>) -> Self {
+        Self::with_max_sessions(key, secret_key, DEFAULT_MAX_SESSIONS)
+    }
+
+    pub fn with_max_sessions(
+        key: SchnorrDiscreteLogInstance,
+        secret_key: Secret<BigInt>,
+        max_sessions: usize,
+    ) -> Self {
+        BlindSigner {
+            key,
+            secret_key,
+            max_sessions,
+            sessions: HashMap::new(),
+            next_session: 0,
+        }
+    }
+
+    pub fn public_key(&self) -> &SchnorrDiscreteLogInstance {
+        &self.key
+    }
+
+    /// The number of sessions that are started but not finished.
+    pub fn open_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Start a session, returning its id and the initial message `a` for the user.
+    pub fn start_session(&mut self) -> Result<(u64, BigInt), BlindSignatureError> {
+        if self.sessions.len() >= self.max_sessions {
+            return Err(BlindSignatureError::TooManySessions {
+                max: self.max_sessions,
+            });
+        }
+        let mut protocol = SchnorrDiscreteLogProtocol::new(
+            self.key.clone(),
+            Some(self.secret_key.expose().clone()),
+        );
+        let a = protocol.initial_message();
+        let id = self.next_session;
+        self.next_session += 1;
+        self.sessions.insert(id, protocol);
+        Ok((id, a))
+    }
+
+    /// Finish the session `id` by answering the blinded challenge `e` of the user.
+    pub fn respond(&mut self, id: u64, e: &BigInt) -> Result<BigInt, BlindSignatureError> {
+        let mut protocol = self
+            .sessions
+            .remove(&id)
+            .ok_or(BlindSignatureError::UnknownSession(id))?;
+        Ok(protocol.challenge_response(&e.mod_floor(self.key.q())))
+    }
+
+    /// Give up on the session `id`, for example because the user went away, so it no longer
+    /// counts towards the cap.
+    pub fn abort(&mut self, id: u64) -> Result<(), BlindSignatureError> {
+        self.sessions
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(BlindSignatureError::UnknownSession(id))
+    }
+}
+
+/// The user's side of one session, which gets a signature on `message` without the signer
+/// seeing it.
+pub struct BlindUser {
+    key: SchnorrDiscreteLogInstance,
+    message: Vec<u8>,
+    state: Option<Blinding>,
+}
+
+struct Blinding {
+    a: BigInt,
+    e: BigInt,
+    alpha: Secret<BigInt>,
+    r: BigInt,
+}
+
+impl BlindUser {
+    pub fn new(key: SchnorrDiscreteLogInstance, message: Vec<u8>) -> Self {
+        BlindUser {
+            key,
+            message,
+            state: None,
+        }
+    }
+
+    /// The blinded challenge for the initial message `a` of the signer.
+    pub fn blind(&mut self, a: BigInt) -> Result<BigInt, BlindSignatureError> {
+        // An `a` outside the group could tag the signature and link it to the session.
+        if !self.key.is_element(&a) {
+            return Err(BlindSignatureError::InvalidInitialMessage);
+        }
+        let group = self.key.group();
+        let alpha = Secret::new(group.random_scalar());
+        let beta = Secret::new(group.random_scalar());
+        let exponents = Secret::new(vec![
+            BigInt::from(1),
+            alpha.expose().clone(),
+            beta.expose().clone(),
+        ]);
+        let r = group.multi_exp_secret(
+            &[a.clone(), group.generator(), self.key.h().clone()],
+            exponents.expose(),
+        );
+        let c = challenge(&group, &r, self.key.h(), &self.message);
+        let e = (c + beta.expose()).mod_floor(group.order());
+        self.state = Some(Blinding {
+            a,
+            e: e.clone(),
+            alpha,
+            r,
+        });
+        Ok(e)
+    }
+
+    /// Check the response `z` of the signer, and unblind it into a signature on our message.
+    ///
+    /// # Panics
+    ///
+    /// If called before [`BlindUser::blind`].
+    pub fn unblind(self, z: BigInt) -> Result<Signature<BigInt>, BlindSignatureError> {
+        let Blinding { a, e, alpha, r } = self.state.expect("The challenge was not blinded yet.");
+        SchnorrDiscreteLogProtocol::new(self.key.clone(), None)
+            .check(a, e, z.clone())
+            .map_err(BlindSignatureError::InvalidResponse)?;
+        Ok(Signature::new(
+            r,
+            (z + alpha.expose()).mod_floor(self.key.q()),
+        ))
+    }
+}
+
+/// An anonymous token: a random serial number with a signature of the issuer on it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Token {
+    serial: [u8; 32],
+    signature: Signature<BigInt>,
+}
+
+/// The user's side of a session with the issuer for a new token, which has a fresh serial
+/// number as its message.
+pub struct TokenRequest {
+    serial: [u8; 32],
+    user: BlindUser,
+}
+
+impl TokenRequest {
+    /// The blinded challenge for the initial message `a` of the issuer.
+    pub fn blind(&mut self, a: BigInt) -> Result<BigInt, BlindSignatureError> {
+        self.user.blind(a)
+    }
+}
+
+impl Token {
+    /// A request for a new token with a fresh serial number, to run a session with the issuer.
+    pub fn request(key: &SchnorrDiscreteLogInstance) -> TokenRequest {
+        let serial = random_contribution();
+        TokenRequest {
+            serial,
+            user: BlindUser::new(key.clone(), serial.to_vec()),
+        }
+    }
+
+    /// The token from the finished session of `request`.
+    pub fn issue(request: TokenRequest, z: BigInt) -> Result<Self, BlindSignatureError> {
+        let signature = request.user.unblind(z)?;
+        Ok(Token {
+            serial: request.serial,
+            signature,
+        })
+    }
+
+    pub fn serial(&self) -> &[u8; 32] {
+        &self.serial
+    }
+
+    pub fn verify(&self, key: &SchnorrDiscreteLogInstance) -> Result<(), SignatureError> {
+        signatures::verify(&key.group(), key.h(), &self.serial, &self.signature)
+    }
+}
+
+/// The serial numbers of the tokens that have been redeemed, so that each token is accepted
+/// only once.
+pub struct TokenRegistry {
+    key: SchnorrDiscreteLogInstance,
+    spent: HashSet<[u8; 32]>,
+}
+
+impl TokenRegistry {
+    pub fn new(key: SchnorrDiscreteLogInstance) -> Self {
+        TokenRegistry {
+            key,
+            spent: HashSet::new(),
+        }
+    }
+
+    /// Accept `token` if it is valid and was not redeemed before.
+    pub fn redeem(&mut self, token: &Token) -> Result<(), TokenError> {
+        token
+            .verify(&self.key)
+            .map_err(TokenError::InvalidSignature)?;
+        if !self.spent.insert(token.serial) {
+            return Err(TokenError::DoubleSpend);
+        }
+        Ok(())
+    }
+
+    pub fn spent(&self) -> usize {
+        self.spent.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::signatures::{verify, SignatureError},
+        schnorr::SchnorrDiscreteLogInstance,
+        secret::Secret,
+    };
+
+    use super::{BlindSignatureError, BlindSigner, BlindUser, Token, TokenError, TokenRegistry};
+
+    fn make_signer(max_sessions: usize) -> BlindSigner {
+        let (key, x) = SchnorrDiscreteLogInstance::generate(256, 64);
+        BlindSigner::with_max_sessions(key, Secret::new(x), max_sessions)
+    }
+
+    #[test]
+    fn unblinded_signature_is_accepted() {
+        let mut signer = make_signer(1);
+        let key = signer.public_key().clone();
+        let mut user = BlindUser::new(key.clone(), b"message".to_vec());
+
+        let (id, a) = signer.start_session().unwrap();
+        let e = user.blind(a.clone()).unwrap();
+        let z = signer.respond(id, &e).unwrap();
+        let signature = user.unblind(z).unwrap();
+
+        assert_eq!(
+            verify(&key.group(), key.h(), b"message", &signature),
+            Ok(())
+        );
+        // The signer never saw the nonce of the signature.
+        assert_ne!(signature.r(), &a);
+    }
+
+    #[test]
+    fn wrong_response_is_rejected() {
+        let mut signer = make_signer(1);
+        let mut user = BlindUser::new(signer.public_key().clone(), b"message".to_vec());
+        let (id, a) = signer.start_session().unwrap();
+        let e = user.blind(a).unwrap();
+        let z = signer.respond(id, &e).unwrap();
+        assert!(matches!(
+            user.unblind(z + 1),
+            Err(BlindSignatureError::InvalidResponse(_))
+        ));
+
+        let mut user = BlindUser::new(signer.public_key().clone(), b"message".to_vec());
+        assert!(matches!(
+            user.blind(BigInt::from(0)),
+            Err(BlindSignatureError::InvalidInitialMessage)
+        ));
+    }
+
+    #[test]
+    fn concurrent_sessions_are_capped() {
+        let mut signer = make_signer(2);
+        let (first, _) = signer.start_session().unwrap();
+        let (second, _) = signer.start_session().unwrap();
+        assert!(matches!(
+            signer.start_session(),
+            Err(BlindSignatureError::TooManySessions { max: 2 })
+        ));
+
+        signer.abort(first).unwrap();
+        signer.start_session().unwrap();
+        signer.respond(second, &BigInt::from(5)).unwrap();
+        assert!(matches!(
+            signer.respond(second, &BigInt::from(5)),
+            Err(BlindSignatureError::UnknownSession(_))
+        ));
+        assert_eq!(signer.open_sessions(), 1);
+    }
+
+    #[test]
+    fn token_is_redeemed_once() {
+        let mut signer = make_signer(1);
+        let key = signer.public_key().clone();
+        let mut registry = TokenRegistry::new(key.clone());
+
+        let mut request = Token::request(&key);
+        let (id, a) = signer.start_session().unwrap();
+        let e = request.blind(a).unwrap();
+        let z = signer.respond(id, &e).unwrap();
+        let token = Token::issue(request, z).unwrap();
+
+        assert_eq!(registry.redeem(&token), Ok(()));
+        assert_eq!(registry.redeem(&token), Err(TokenError::DoubleSpend));
+        assert_eq!(registry.spent(), 1);
+
+        let mut forged = token;
+        forged.serial[0] ^= 1;
+        assert_eq!(
+            registry.redeem(&forged),
+            Err(TokenError::InvalidSignature(
+                SignatureError::InvalidSignature
+            ))
+        );
+    }
+}
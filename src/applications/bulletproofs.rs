@@ -0,0 +1,819 @@
+//! Range proofs of logarithmic size, following
+//! [Bulletproofs](https://eprint.iacr.org/2017/1066).
+//!
+//! A proof shows that each of `m` Pedersen commitments `V_j = g^v_j h^gamma_j` hides a value in
+//! `[0, 2^n)`. Instead of one proof per bit as in
+//! [`range_proof`](crate::applications::range_proof), the bits are committed to as vectors,
+//! and an inner product argument shows that they are bits and add up to the values. A proof
+//! has `2 log2(n m) + 4` elements and 5 scalars.
+//!
+//! The verifier's checks are a single product of powers that must be the identity, so many
+//! proofs can be checked at once with [`Bulletproof::verify_batch`], by combining these products
+//! with random weights.
+//!
+//! With `g` and `h` from a [`PedersenScheme`], the proofs are about the commitments it makes.
+
+use num::{BigInt, Integer, One, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::commitments::PedersenScheme,
+    group::{PrimeOrderGroup, SchnorrGroup},
+    secret::Secret,
+    transcript::Transcript,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum BulletproofError {
+    /// `n m` is not a power of two.
+    SizeNotPowerOfTwo { size: usize },
+    /// The generators only allow proofs for `n m` up to `capacity`.
+    NotEnoughGenerators { size: usize, capacity: usize },
+    /// The ranges have `bits` bits, but the group only allows ranges of up to `max` bits.
+    TooManyBits { bits: usize, max: usize },
+    /// There is not one blinding factor for every value.
+    WrongLength { expected: usize, actual: usize },
+    /// The prover's value at `index` is not in the range.
+    ValueOutOfRange { index: usize },
+    /// A commitment or a part of the proof is not an element of the group.
+    InvalidElement,
+    /// The proof, or at least one proof in the batch, was rejected.
+    VerificationFailed,
+}
+
+/// The public generators of the proofs: `g` and `h` of the commitments, `n m` vector
+/// generators `G_i` and `H_i`, and `u` for the inner product.
+#[derive(Clone, Debug)]
+pub struct BulletproofGens<G: PrimeOrderGroup> {
+    group: G,
+    g: G::Element,
+    h: G::Element,
+    u: G::Element,
+    gs: Vec<G::Element>,
+    hs: Vec<G::Element>,
+}
+
+impl<G: PrimeOrderGroup> BulletproofGens<G> {
+    /// Generators for proofs of up to `capacity` bits in total, about commitments
+    /// `g^v h^gamma` where `g` is the generator of `group`.
+    pub fn new(group: G, h: G::Element, capacity: usize) -> Self {
+        let hashed =
+            |label: &str, i: usize| group.hash_to_element(format!("{label} {i}").as_bytes());
+        let gs = (0..capacity).map(|i| hashed("bulletproofs G", i)).collect();
+        let hs = (0..capacity).map(|i| hashed("bulletproofs H", i)).collect();
+        let u = group.hash_to_element(b"bulletproofs u");
+        BulletproofGens {
+            g: group.generator(),
+            group,
+            h,
+            u,
+            gs,
+            hs,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.gs.len()
+    }
+
+    /// The Pedersen commitment `g^v h^gamma`, for a secret `v` and `gamma`.
+    pub fn commit(&self, v: &BigInt, gamma: &BigInt) -> G::Element {
+        let exponents = Secret::new(vec![v.clone(), gamma.clone()]);
+        self.group
+            .multi_exp_secret(&[self.g.clone(), self.h.clone()], exponents.expose())
+    }
+}
+
+impl BulletproofGens<SchnorrGroup> {
+    /// Generators for proofs about the commitments of `scheme`.
+    pub fn for_pedersen(scheme: &PedersenScheme, capacity: usize) -> Self {
+        let params = scheme.params();
+        Self::new(params.group(), params.h().clone(), capacity)
+    }
+}
+
+/// An aggregated range proof for one or more commitments.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bulletproof<E> {
+    a: E,
+    s: E,
+    t1: E,
+    t2: E,
+    tau_x: BigInt,
+    mu: BigInt,
+    t_hat: BigInt,
+    ipp: InnerProductProof<E>,
+}
+
+/// Shows that `P = G^a H^b u^<a, b>`, by halving the vectors in each round.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct InnerProductProof<E> {
+    ls: Vec<E>,
+    rs: Vec<E>,
+    a: BigInt,
+    b: BigInt,
+}
+
+/// A product of powers that must be the identity, with the exponents of the fixed generators
+/// kept apart so that equations of several proofs can be added up.
+struct Equation<E> {
+    g: BigInt,
+    h: BigInt,
+    u: BigInt,
+    gs: Vec<BigInt>,
+    hs: Vec<BigInt>,
+    others: Vec<(E, BigInt)>,
+}
+
+impl<E: Clone + PartialEq + Serialize> Bulletproof<E> {
+    /// Prove that every `values[j]` is in `[0, 2^bits)`, for the commitments
+    /// `g^values[j] h^blindings[j]`.
+    pub fn prove<G>(
+        gens: &BulletproofGens<G>,
+        values: &[BigInt],
+        blindings: &[BigInt],
+        bits: usize,
+    ) -> Result<Self, BulletproofError>
+    where
+        G: PrimeOrderGroup<Element = E>,
+    {
+        let group = &gens.group;
+        let q = group.order();
+        check_size(gens, values.len(), bits)?;
+        if blindings.len() != values.len() {
+            return Err(BulletproofError::WrongLength {
+                expected: values.len(),
+                actual: blindings.len(),
+            });
+        }
+        if let Some(index) = values
+            .iter()
+            .position(|v| v < &BigInt::zero() || v.bits() > bits)
+        {
+            return Err(BulletproofError::ValueOutOfRange { index });
+        }
+
+        let size = bits * values.len();
+        let commitments = values
+            .iter()
+            .zip(blindings)
+            .map(|(v, gamma)| gens.commit(v, gamma))
+            .collect::<Vec<_>>();
+        let mut transcript = start_transcript(gens, &commitments, bits);
+
+        // Commit to the bits `a_L` and to `a_R = a_L - 1`, and to blinding vectors for them.
+        let a_l = Secret::new(
+            values
+                .iter()
+                .flat_map(|v| (0..bits).map(move |i| (v >> i) & BigInt::one()))
+                .collect::<Vec<_>>(),
+        );
+        let a_r = Secret::new(a_l.expose().iter().map(|a| a - 1).collect::<Vec<_>>());
+        let alpha = Secret::new(group.random_scalar());
+        let a = vector_commit(gens, alpha.expose(), a_l.expose(), a_r.expose());
+
+        let random_vector = || Secret::new((0..size).map(|_| group.random_scalar()).collect());
+        let (s_l, s_r): (Secret<Vec<_>>, Secret<Vec<_>>) = (random_vector(), random_vector());
+        let rho = Secret::new(group.random_scalar());
+        let s = vector_commit(gens, rho.expose(), s_l.expose(), s_r.expose());
+        let (a_l, a_r, s_l, s_r) = (a_l.expose(), a_r.expose(), s_l.expose(), s_r.expose());
+
+        transcript.append_value(b"A", &a);
+        transcript.append_value(b"S", &s);
+        let y = scalar_challenge(&transcript, b"y", q);
+        let z = scalar_challenge(&transcript, b"z", q);
+
+        // `l(X) = l0 + l1 X` and `r(X) = r0 + r1 X`, whose inner product `t(X)` has a constant
+        // term that only depends on the values.
+        let y_powers = powers(&y, size, q);
+        let zz = weighted_powers_of_two(&z, values.len(), bits, q);
+        let l0 = Secret::new(a_l.iter().map(|a| a - &z).collect::<Vec<_>>());
+        let l1 = s_l;
+        let r0 = Secret::new(
+            (0..size)
+                .map(|i| (&y_powers[i] * (&a_r[i] + &z) + &zz[i]).mod_floor(q))
+                .collect::<Vec<_>>(),
+        );
+        let r1 = Secret::new(
+            (0..size)
+                .map(|i| (&y_powers[i] * &s_r[i]).mod_floor(q))
+                .collect::<Vec<_>>(),
+        );
+        let (l0, r0, r1) = (l0.expose(), r0.expose(), r1.expose());
+
+        let t1 = Secret::new((inner_product(l0, r1, q) + inner_product(l1, r0, q)).mod_floor(q));
+        let t2 = Secret::new(inner_product(l1, r1, q));
+        let tau1 = Secret::new(group.random_scalar());
+        let tau2 = Secret::new(group.random_scalar());
+        let t1_commitment = gens.commit(t1.expose(), tau1.expose());
+        let t2_commitment = gens.commit(t2.expose(), tau2.expose());
+
+        transcript.append_value(b"T1", &t1_commitment);
+        transcript.append_value(b"T2", &t2_commitment);
+        let x = scalar_challenge(&transcript, b"x", q);
+
+        let l = Secret::new(
+            (0..size)
+                .map(|i| (&l0[i] + &l1[i] * &x).mod_floor(q))
+                .collect::<Vec<_>>(),
+        );
+        let r = Secret::new(
+            (0..size)
+                .map(|i| (&r0[i] + &r1[i] * &x).mod_floor(q))
+                .collect::<Vec<_>>(),
+        );
+        let t_hat = inner_product(l.expose(), r.expose(), q);
+
+        let z_squared = &z * &z;
+        let gamma_sum = blindings
+            .iter()
+            .zip(powers(&z, values.len(), q))
+            .fold(BigInt::zero(), |acc, (gamma, z_j)| {
+                acc + gamma * z_j * &z_squared
+            });
+        let tau_x = (tau2.expose() * &x * &x + tau1.expose() * &x + gamma_sum).mod_floor(q);
+        let mu = (alpha.expose() + rho.expose() * &x).mod_floor(q);
+
+        transcript.append_value(b"tau_x", &tau_x);
+        transcript.append_value(b"mu", &mu);
+        transcript.append_value(b"t_hat", &t_hat);
+        let w = scalar_challenge(&transcript, b"w", q);
+
+        // Prove `<l, r>` with the generators `H'_i = H_i^(y^-i)`, under which `r` is committed.
+        let y_inv = group.scalar_inverse(&y);
+        let hs_prime = gens
+            .hs
+            .iter()
+            .zip(powers(&y_inv, size, q))
+            .map(|(h, y_inv_i)| group.exp(h, &y_inv_i))
+            .collect();
+        let u = group.exp(&gens.u, &w);
+        let ipp = InnerProductProof::prove(
+            group,
+            &mut transcript,
+            gens.gs[..size].to_vec(),
+            hs_prime,
+            &u,
+            l,
+            r,
+        );
+
+        Ok(Bulletproof {
+            a,
+            s,
+            t1: t1_commitment,
+            t2: t2_commitment,
+            tau_x,
+            mu,
+            t_hat,
+            ipp,
+        })
+    }
+
+    /// Verify that every commitment hides a value in `[0, 2^bits)`.
+    pub fn verify<G>(
+        &self,
+        gens: &BulletproofGens<G>,
+        commitments: &[E],
+        bits: usize,
+    ) -> Result<(), BulletproofError>
+    where
+        G: PrimeOrderGroup<Element = E>,
+    {
+        Self::verify_batch(gens, &[(self, commitments)], bits)
+    }
+
+    /// Verify many proofs at once, which is much faster than verifying them one by one.
+    ///
+    /// If the batch is rejected, at least one of the proofs is invalid, but the batch does not
+    /// tell which.
+    pub fn verify_batch<G>(
+        gens: &BulletproofGens<G>,
+        proofs: &[(&Self, &[E])],
+        bits: usize,
+    ) -> Result<(), BulletproofError>
+    where
+        G: PrimeOrderGroup<Element = E>,
+    {
+        let group = &gens.group;
+        let q = group.order();
+        let mut total = Equation {
+            g: BigInt::zero(),
+            h: BigInt::zero(),
+            u: BigInt::zero(),
+            gs: vec![BigInt::zero(); gens.capacity()],
+            hs: vec![BigInt::zero(); gens.capacity()],
+            others: vec![],
+        };
+
+        for (proof, commitments) in proofs {
+            let equation = proof.equation(gens, commitments, bits)?;
+            // Weigh each proof randomly, so that errors in different proofs cannot cancel.
+            let weight = group.random_scalar();
+            let add = |acc: &mut BigInt, e: BigInt| *acc = (&*acc + e * &weight).mod_floor(q);
+            add(&mut total.g, equation.g);
+            add(&mut total.h, equation.h);
+            add(&mut total.u, equation.u);
+            for (acc, e) in total.gs.iter_mut().zip(equation.gs) {
+                add(acc, e);
+            }
+            for (acc, e) in total.hs.iter_mut().zip(equation.hs) {
+                add(acc, e);
+            }
+            total.others.extend(
+                equation
+                    .others
+                    .into_iter()
+                    .map(|(base, e)| (base, (e * &weight).mod_floor(q))),
+            );
+        }
+
+        let mut bases = vec![gens.g.clone(), gens.h.clone(), gens.u.clone()];
+        let mut exponents = vec![total.g, total.h, total.u];
+        bases.extend(gens.gs.iter().cloned());
+        exponents.extend(total.gs);
+        bases.extend(gens.hs.iter().cloned());
+        exponents.extend(total.hs);
+        for (base, e) in total.others {
+            bases.push(base);
+            exponents.push(e);
+        }
+
+        if group.multi_exp(&bases, &exponents) == group.identity() {
+            Ok(())
+        } else {
+            Err(BulletproofError::VerificationFailed)
+        }
+    }
+
+    /// The checks of this proof, as one product that must be the identity.
+    ///
+    /// The first check is that `t_hat` is committed to as `t(x)` should be, and the second one
+    /// that the inner product proof holds for the commitments to `l` and `r`. They are combined
+    /// with a random weight.
+    fn equation<G>(
+        &self,
+        gens: &BulletproofGens<G>,
+        commitments: &[E],
+        bits: usize,
+    ) -> Result<Equation<E>, BulletproofError>
+    where
+        G: PrimeOrderGroup<Element = E>,
+    {
+        let group = &gens.group;
+        let q = group.order();
+        let m = commitments.len();
+        check_size(gens, m, bits)?;
+        let size = bits * m;
+        let rounds = size.trailing_zeros() as usize;
+        if self.ipp.ls.len() != rounds || self.ipp.rs.len() != rounds {
+            return Err(BulletproofError::WrongLength {
+                expected: rounds,
+                actual: self.ipp.ls.len().min(self.ipp.rs.len()),
+            });
+        }
+        let elements = commitments
+            .iter()
+            .chain([&self.a, &self.s, &self.t1, &self.t2])
+            .chain(&self.ipp.ls)
+            .chain(&self.ipp.rs);
+        if !elements.into_iter().all(|e| group.is_element(e)) {
+            return Err(BulletproofError::InvalidElement);
+        }
+
+        let mut transcript = start_transcript(gens, commitments, bits);
+        transcript.append_value(b"A", &self.a);
+        transcript.append_value(b"S", &self.s);
+        let y = scalar_challenge(&transcript, b"y", q);
+        let z = scalar_challenge(&transcript, b"z", q);
+        transcript.append_value(b"T1", &self.t1);
+        transcript.append_value(b"T2", &self.t2);
+        let x = scalar_challenge(&transcript, b"x", q);
+        transcript.append_value(b"tau_x", &self.tau_x);
+        transcript.append_value(b"mu", &self.mu);
+        transcript.append_value(b"t_hat", &self.t_hat);
+        let w = scalar_challenge(&transcript, b"w", q);
+        let round_challenges = self
+            .ipp
+            .ls
+            .iter()
+            .zip(&self.ipp.rs)
+            .map(|(l, r)| {
+                transcript.append_value(b"L", l);
+                transcript.append_value(b"R", r);
+                scalar_challenge(&transcript, b"round", q)
+            })
+            .collect::<Vec<_>>();
+
+        let c = group.random_scalar();
+        let z_squared = (&z * &z).mod_floor(q);
+        let y_powers = powers(&y, size, q);
+        let y_inv_powers = powers(&group.scalar_inverse(&y), size, q);
+        let zz = weighted_powers_of_two(&z, m, bits, q);
+        let z_powers = powers(&z, m, q);
+
+        // First check: `g^t_hat h^tau_x = V^(z^2 z^m) g^delta T1^x T2^(x^2)`, weighted by `c`.
+        let sum_y = y_powers.iter().fold(BigInt::zero(), |acc, y_i| acc + y_i);
+        let sum_two = (BigInt::one() << bits) - 1;
+        let sum_z = z_powers.iter().fold(BigInt::zero(), |acc, z_j| acc + z_j);
+        let delta: BigInt = (&z - &z_squared) * sum_y - &z_squared * &z * sum_z * sum_two;
+
+        let mut others = commitments
+            .iter()
+            .zip(&z_powers)
+            .map(|(v, z_j)| (v.clone(), -(&c * &z_squared * z_j)))
+            .collect::<Vec<_>>();
+        others.push((self.t1.clone(), -(&c * &x)));
+        others.push((self.t2.clone(), -(&c * &x * &x)));
+
+        // Second check: the unrolled inner product proof,
+        // `A S^x G^-z H'^(z y^n + zz) h^-mu u'^t_hat prod(L^(x_j^2) R^(x_j^-2))
+        //  = G^(a s) H'^(b s^-1) u'^(a b)`.
+        let s = round_products(group, &round_challenges, size);
+        let (a, b) = (&self.ipp.a, &self.ipp.b);
+        let gs = (0..size)
+            .map(|i| (-&z - a * &s[i]).mod_floor(q))
+            .collect::<Vec<_>>();
+        let hs = (0..size)
+            .map(|i| {
+                let s_inv = &s[size - 1 - i];
+                (&z + &y_inv_powers[i] * (&zz[i] - b * s_inv)).mod_floor(q)
+            })
+            .collect::<Vec<_>>();
+        others.push((self.a.clone(), BigInt::one()));
+        others.push((self.s.clone(), x.clone()));
+        for ((l, r), x_j) in self.ipp.ls.iter().zip(&self.ipp.rs).zip(&round_challenges) {
+            let x_j_squared = (x_j * x_j).mod_floor(q);
+            others.push((l.clone(), x_j_squared.clone()));
+            others.push((r.clone(), group.scalar_inverse(&x_j_squared)));
+        }
+
+        Ok(Equation {
+            g: (&c * (&self.t_hat - delta)).mod_floor(q),
+            h: (&c * &self.tau_x - &self.mu).mod_floor(q),
+            u: (&w * (&self.t_hat - a * b)).mod_floor(q),
+            gs,
+            hs,
+            others,
+        })
+    }
+}
+
+impl<E: Clone + Serialize> InnerProductProof<E> {
+    fn prove<G>(
+        group: &G,
+        transcript: &mut Transcript,
+        mut gs: Vec<E>,
+        mut hs: Vec<E>,
+        u: &E,
+        mut a: Secret<Vec<BigInt>>,
+        mut b: Secret<Vec<BigInt>>,
+    ) -> Self
+    where
+        G: PrimeOrderGroup<Element = E>,
+    {
+        let q = group.order();
+        let (mut ls, mut rs) = (vec![], vec![]);
+
+        while a.expose().len() > 1 {
+            let half = a.expose().len() / 2;
+            let (a_lo, a_hi) = a.expose().split_at(half);
+            let (b_lo, b_hi) = b.expose().split_at(half);
+            let (g_lo, g_hi) = gs.split_at(half);
+            let (h_lo, h_hi) = hs.split_at(half);
+
+            let c_l = inner_product(a_lo, b_hi, q);
+            let c_r = inner_product(a_hi, b_lo, q);
+            let l = cross_commit(group, g_hi, h_lo, u, a_lo, b_hi, c_l);
+            let r = cross_commit(group, g_lo, h_hi, u, a_hi, b_lo, c_r);
+
+            transcript.append_value(b"L", &l);
+            transcript.append_value(b"R", &r);
+            let x = scalar_challenge(transcript, b"round", q);
+            let x_inv = group.scalar_inverse(&x);
+            ls.push(l);
+            rs.push(r);
+
+            let fold = |lo: &[BigInt], hi: &[BigInt], x_lo: &BigInt, x_hi: &BigInt| {
+                lo.iter()
+                    .zip(hi)
+                    .map(|(lo, hi)| (lo * x_lo + hi * x_hi).mod_floor(q))
+                    .collect::<Vec<_>>()
+            };
+            let fold_bases = |lo: &[E], hi: &[E], x_lo: &BigInt, x_hi: &BigInt| {
+                lo.iter()
+                    .zip(hi)
+                    .map(|(lo, hi)| {
+                        group.multi_exp(&[lo.clone(), hi.clone()], &[x_lo.clone(), x_hi.clone()])
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let new_a = Secret::new(fold(a_lo, a_hi, &x, &x_inv));
+            let new_b = Secret::new(fold(b_lo, b_hi, &x_inv, &x));
+            let new_gs = fold_bases(g_lo, g_hi, &x_inv, &x);
+            let new_hs = fold_bases(h_lo, h_hi, &x, &x_inv);
+            (a, b, gs, hs) = (new_a, new_b, new_gs, new_hs);
+        }
+
+        // The last `a` and `b` are part of the proof.
+        InnerProductProof {
+            ls,
+            rs,
+            a: a.expose_mut().remove(0),
+            b: b.expose_mut().remove(0),
+        }
+    }
+}
+
+fn check_size<G: PrimeOrderGroup>(
+    gens: &BulletproofGens<G>,
+    m: usize,
+    bits: usize,
+) -> Result<(), BulletproofError> {
+    let max = gens.group.order().bits() - 1;
+    if bits > max {
+        return Err(BulletproofError::TooManyBits { bits, max });
+    }
+    let size = bits * m;
+    if !size.is_power_of_two() {
+        return Err(BulletproofError::SizeNotPowerOfTwo { size });
+    }
+    if size > gens.capacity() {
+        return Err(BulletproofError::NotEnoughGenerators {
+            size,
+            capacity: gens.capacity(),
+        });
+    }
+    Ok(())
+}
+
+fn start_transcript<G: PrimeOrderGroup>(
+    gens: &BulletproofGens<G>,
+    commitments: &[G::Element],
+    bits: usize,
+) -> Transcript {
+    let mut transcript = Transcript::new(b"bulletproof");
+    transcript.append_value(b"g", &gens.g);
+    transcript.append_value(b"h", &gens.h);
+    transcript.append(b"bits", &(bits as u64).to_be_bytes());
+    transcript.append_value(b"commitments", &commitments);
+    transcript
+}
+
+fn scalar_challenge(transcript: &Transcript, label: &[u8], q: &BigInt) -> BigInt {
+    transcript.challenge(label, q.bits() - 1)
+}
+
+/// `h^blinding G^l H^r`, for secret exponents.
+fn vector_commit<G: PrimeOrderGroup>(
+    gens: &BulletproofGens<G>,
+    blinding: &BigInt,
+    l: &[BigInt],
+    r: &[BigInt],
+) -> G::Element {
+    let bases = [gens.h.clone()]
+        .into_iter()
+        .chain(gens.gs[..l.len()].iter().cloned())
+        .chain(gens.hs[..r.len()].iter().cloned())
+        .collect::<Vec<_>>();
+    let exponents = Secret::new(
+        [blinding.clone()]
+            .into_iter()
+            .chain(l.iter().cloned())
+            .chain(r.iter().cloned())
+            .collect::<Vec<_>>(),
+    );
+    gens.group.multi_exp_secret(&bases, exponents.expose())
+}
+
+/// `G^a H^b u^c`, for the secret vectors of a round of the inner product proof.
+fn cross_commit<G: PrimeOrderGroup>(
+    group: &G,
+    gs: &[G::Element],
+    hs: &[G::Element],
+    u: &G::Element,
+    a: &[BigInt],
+    b: &[BigInt],
+    c: BigInt,
+) -> G::Element {
+    let bases = [gs, hs, std::slice::from_ref(u)].concat();
+    let exponents = Secret::new([a, b, &[c]].concat());
+    group.multi_exp_secret(&bases, exponents.expose())
+}
+
+fn inner_product(a: &[BigInt], b: &[BigInt], q: &BigInt) -> BigInt {
+    a.iter()
+        .zip(b)
+        .fold(BigInt::zero(), |acc, (a, b)| acc + a * b)
+        .mod_floor(q)
+}
+
+/// `1, x, x^2, ..., x^(n-1)`.
+fn powers(x: &BigInt, n: usize, q: &BigInt) -> Vec<BigInt> {
+    let mut powers = Vec::with_capacity(n);
+    let mut power = BigInt::one();
+    for _ in 0..n {
+        let next = (&power * x).mod_floor(q);
+        powers.push(power);
+        power = next;
+    }
+    powers
+}
+
+/// The vector that is `z^(2+j) 2^i` at position `j n + i`, which moves the bits of the `j`-th
+/// value into the constant term of `t(X)`.
+fn weighted_powers_of_two(z: &BigInt, m: usize, bits: usize, q: &BigInt) -> Vec<BigInt> {
+    let z_squared = (z * z).mod_floor(q);
+    powers(z, m, q)
+        .iter()
+        .flat_map(|z_j| {
+            let weight = (z_j * &z_squared).mod_floor(q);
+            (0..bits).map(move |i| ((&weight) << i).mod_floor(q))
+        })
+        .collect()
+}
+
+/// The scalars `s_i` by which the inner product proof folds `G_i` into its final generator.
+///
+/// Round `j` multiplies the upper half of the generators by `x_j` and the lower half by
+/// `x_j^-1`, so `s_i` is a product of `x_j` or `x_j^-1`, according to the bits of `i`. The
+/// generators `H_i` are folded by `s_i^-1 = s_(n-1-i)`.
+fn round_products<G: PrimeOrderGroup>(
+    group: &G,
+    challenges: &[BigInt],
+    size: usize,
+) -> Vec<BigInt> {
+    let q = group.order();
+    let rounds = challenges.len();
+    let inverses = challenges
+        .iter()
+        .map(|x| group.scalar_inverse(x))
+        .collect::<Vec<_>>();
+    (0..size)
+        .map(|i| {
+            (0..rounds).fold(BigInt::one(), |acc, j| {
+                let upper = (i >> (rounds - 1 - j)) & 1 == 1;
+                let x = if upper { &challenges[j] } else { &inverses[j] };
+                (acc * x).mod_floor(q)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::commitments::{CommitmentScheme, PedersenScheme},
+        group::SchnorrGroup,
+    };
+
+    use super::{Bulletproof, BulletproofError, BulletproofGens};
+
+    fn make_gens(capacity: usize) -> (PedersenScheme, BulletproofGens<SchnorrGroup>) {
+        let scheme = PedersenScheme::new(CommitmentScheme::gen_params(256, 128));
+        let gens = BulletproofGens::for_pedersen(&scheme, capacity);
+        (scheme, gens)
+    }
+
+    fn commit_all(
+        scheme: &PedersenScheme,
+        values: &[u64],
+    ) -> (Vec<BigInt>, Vec<BigInt>, Vec<BigInt>) {
+        let values = values.iter().map(|&v| BigInt::from(v)).collect::<Vec<_>>();
+        let (commitments, blindings) = values
+            .iter()
+            .map(|v| {
+                let (c, r) = scheme.commit(v);
+                (c, r.expose().clone())
+            })
+            .unzip();
+        (values, commitments, blindings)
+    }
+
+    #[test]
+    fn single_proof_is_accepted() {
+        let (scheme, gens) = make_gens(32);
+        for v in [0, 1, 12345, u32::MAX as u64] {
+            let (values, commitments, blindings) = commit_all(&scheme, &[v]);
+            let proof = Bulletproof::prove(&gens, &values, &blindings, 32).unwrap();
+            assert_eq!(proof.verify(&gens, &commitments, 32), Ok(()));
+        }
+    }
+
+    #[test]
+    fn aggregated_proof_is_accepted() {
+        let (scheme, gens) = make_gens(64);
+        let (values, commitments, blindings) = commit_all(&scheme, &[3, 0, 65535, 1000]);
+        let proof = Bulletproof::prove(&gens, &values, &blindings, 16).unwrap();
+        assert_eq!(proof.verify(&gens, &commitments, 16), Ok(()));
+
+        // The proof is logarithmic in the number of bits.
+        assert_eq!(proof.ipp.ls.len(), 6);
+
+        // It only holds for the commitments in their order.
+        let swapped = [commitments[1].clone(), commitments[0].clone()]
+            .into_iter()
+            .chain(commitments[2..].iter().cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            proof.verify(&gens, &swapped, 16),
+            Err(BulletproofError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn values_out_of_range_cannot_be_proven() {
+        let (scheme, gens) = make_gens(16);
+        let (values, _, blindings) = commit_all(&scheme, &[1, 1 << 8]);
+        assert_eq!(
+            Bulletproof::prove(&gens, &values, &blindings, 8),
+            Err(BulletproofError::ValueOutOfRange { index: 1 })
+        );
+        assert_eq!(
+            Bulletproof::prove(&gens, &values[..1], &blindings[..1], 6),
+            Err(BulletproofError::SizeNotPowerOfTwo { size: 6 })
+        );
+        assert_eq!(
+            Bulletproof::prove(&gens, &values, &blindings, 16),
+            Err(BulletproofError::NotEnoughGenerators {
+                size: 32,
+                capacity: 16
+            })
+        );
+    }
+
+    #[test]
+    fn tampered_proof_is_rejected() {
+        let (scheme, gens) = make_gens(16);
+        let (values, commitments, blindings) = commit_all(&scheme, &[200]);
+        let proof = Bulletproof::prove(&gens, &values, &blindings, 16).unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.t_hat += 1;
+        assert_eq!(
+            tampered.verify(&gens, &commitments, 16),
+            Err(BulletproofError::VerificationFailed)
+        );
+
+        let mut tampered = proof.clone();
+        tampered.ipp.a += 1;
+        assert_eq!(
+            tampered.verify(&gens, &commitments, 16),
+            Err(BulletproofError::VerificationFailed)
+        );
+
+        let (other, _) = scheme.commit(&BigInt::from(200));
+        assert_eq!(
+            proof.verify(&gens, &[other], 16),
+            Err(BulletproofError::VerificationFailed)
+        );
+        assert_eq!(
+            proof.verify(&gens, &commitments, 8),
+            Err(BulletproofError::WrongLength {
+                expected: 3,
+                actual: 4
+            })
+        );
+    }
+
+    #[test]
+    fn batch_verification() {
+        let (scheme, gens) = make_gens(32);
+        let batch = [vec![5u64], vec![7, 9], vec![u16::MAX as u64]]
+            .iter()
+            .map(|values| {
+                let (values, commitments, blindings) = commit_all(&scheme, values);
+                let proof = Bulletproof::prove(&gens, &values, &blindings, 16).unwrap();
+                (proof, commitments)
+            })
+            .collect::<Vec<_>>();
+
+        let items = batch
+            .iter()
+            .map(|(proof, commitments)| (proof, &commitments[..]))
+            .collect::<Vec<_>>();
+        assert_eq!(Bulletproof::verify_batch(&gens, &items, 16), Ok(()));
+
+        let mut bad = batch[1].0.clone();
+        bad.tau_x += 1;
+        let mut items = items;
+        items[1].0 = &bad;
+        assert_eq!(
+            Bulletproof::verify_batch(&gens, &items, 16),
+            Err(BulletproofError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn serialization_round_trips() {
+        let (scheme, gens) = make_gens(8);
+        let (values, commitments, blindings) = commit_all(&scheme, &[42]);
+        let proof = Bulletproof::prove(&gens, &values, &blindings, 8).unwrap();
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let decoded: Bulletproof<BigInt> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.verify(&gens, &commitments, 8), Ok(()));
+    }
+}
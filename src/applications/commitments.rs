@@ -1,9 +1,12 @@
 //! Create and verify commitments.
 
-use num::BigInt;
+use num::{bigint::RandBigInt, BigInt, Integer, Zero};
 
 use crate::{
     schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol},
+    secret::Secret,
+    session::SessionId,
+    transcript::Transcript,
     SigmaProtocol,
 };
 
@@ -26,9 +29,17 @@ impl CommitmentScheme {
         CommitmentScheme { instance }
     }
 
-    pub fn commit(&self, e: &BigInt) -> (BigInt, BigInt) {
+    /// Build the fixed-base tables of the parameters, for a scheme that makes many commitments.
+    /// See [`SchnorrDiscreteLogInstance::precompute`].
+    pub fn precompute(&self) {
+        self.instance.precompute();
+    }
+
+    /// Commit to `e`, returning the commitment and its opening.
+    pub fn commit(&self, e: &BigInt) -> (BigInt, Secret<BigInt>) {
         let mut protocol = SchnorrDiscreteLogProtocol::new(self.instance.clone(), None);
-        protocol.simulate(e)
+        let (a, z) = protocol.simulate(e);
+        (a, Secret::new(z))
     }
 
     pub fn verify(&self, a: &BigInt, e: &BigInt, z: &BigInt) -> bool {
@@ -37,9 +48,91 @@ impl CommitmentScheme {
             .check(a.to_owned(), e.to_owned(), z.to_owned())
             .is_ok()
     }
+
+    /// Commit to `e` within a session, so the commitment cannot be replayed in another one.
+    pub fn commit_in_session(&self, e: &BigInt, session: &SessionId) -> (BigInt, Secret<BigInt>) {
+        self.commit(&tag_with_session(&self.instance, e, session))
+    }
+
+    pub fn verify_in_session(
+        &self,
+        a: &BigInt,
+        e: &BigInt,
+        z: &BigInt,
+        session: &SessionId,
+    ) -> bool {
+        self.verify(a, &tag_with_session(&self.instance, e, session), z)
+    }
+}
+
+/// Hash the session id and the value into an exponent modulo `q`.
+///
+/// The value is used as an exponent, so anything that is only reduced modulo `q` later, such
+/// as `e * 2^256 + id`, would lose `e` for a `q` of 256 bits or less.
+fn tag_with_session(
+    instance: &SchnorrDiscreteLogInstance,
+    e: &BigInt,
+    session: &SessionId,
+) -> BigInt {
+    let mut transcript = Transcript::new(b"session commitment");
+    transcript.append(b"session", session.as_bytes());
+    transcript.append_value(b"value", e);
+    // Extra bits make the reduction modulo `q` close to uniform.
+    let q = instance.q();
+    transcript.challenge(b"tag", q.bits() + 128) % q
+}
+
+/// Pedersen commitments `g^m h^r`, which hide `m` perfectly and can be added together by
+/// multiplying them.
+///
+/// Nobody may know the discrete log of `h` to the base `g`, as it would let them open a
+/// commitment to any value.
+#[derive(Clone, Debug)]
+pub struct PedersenScheme {
+    params: SchnorrDiscreteLogInstance,
 }
 
-pub fn encode(s: String) -> BigInt {
+impl PedersenScheme {
+    pub fn new(params: SchnorrDiscreteLogInstance) -> Self {
+        assert!(CommitmentScheme::check_params(&params));
+        PedersenScheme { params }
+    }
+
+    /// Build the fixed-base tables of `g` and `h`, for a scheme that makes many commitments.
+    /// The tables are shared with clones of the scheme and of its parameters.
+    pub fn precompute(&self) {
+        self.params.precompute();
+    }
+
+    pub fn params(&self) -> &SchnorrDiscreteLogInstance {
+        &self.params
+    }
+
+    /// Commit to `m` with fresh randomness, returning the commitment and its randomness.
+    pub fn commit(&self, m: &BigInt) -> (BigInt, Secret<BigInt>) {
+        let r = Secret::new(rand::thread_rng().gen_bigint_range(&BigInt::zero(), self.params.q()));
+        (self.commit_with(m, r.expose()), r)
+    }
+
+    /// Commit to `m` with the randomness `r`. Both may be negative.
+    pub fn commit_with(&self, m: &BigInt, r: &BigInt) -> BigInt {
+        let (p, q) = (self.params.p(), self.params.q());
+        let gm = self.params.pow_g_secret(&m.mod_floor(q));
+        let hr = self.params.pow_h_secret(&r.mod_floor(q));
+        (gm * hr) % p
+    }
+
+    pub fn verify(&self, c: &BigInt, m: &BigInt, r: &BigInt) -> bool {
+        self.is_element(c) && *c == self.commit_with(m, r)
+    }
+
+    /// Whether `c` could be a commitment at all, i.e. whether it is in the subgroup.
+    pub fn is_element(&self, c: &BigInt) -> bool {
+        self.params.is_element(c)
+    }
+}
+
+pub fn encode(s: &str) -> BigInt {
     BigInt::from_signed_bytes_be(s.as_bytes())
 }
 
@@ -51,14 +144,17 @@ pub fn decode(i: BigInt) -> String {
 mod tests {
     use num::BigInt;
 
-    use crate::schnorr::SchnorrDiscreteLogInstance;
+    use crate::{
+        schnorr::SchnorrDiscreteLogInstance,
+        session::{random_contribution, SessionId},
+    };
 
-    use super::{decode, encode, CommitmentScheme};
+    use super::{decode, encode, CommitmentScheme, PedersenScheme};
 
     #[test]
     fn encode_decode_equals_identity() {
         let s = String::from("Hello, World!");
-        assert_eq!(s, decode(encode(s.clone())));
+        assert_eq!(s, decode(encode(&s)));
     }
 
     #[test]
@@ -85,7 +181,7 @@ mod tests {
         let scheme = CommitmentScheme::new(instance);
         let e = BigInt::from(10);
         let (a, z) = scheme.commit(&e);
-        assert!(scheme.verify(&a, &e, &z))
+        assert!(scheme.verify(&a, &e, z.expose()))
     }
 
     #[test]
@@ -96,4 +192,38 @@ mod tests {
         let (a, z) = (BigInt::from(20), BigInt::from(30));
         assert!(!scheme.verify(&a, &e, &z))
     }
+
+    #[test]
+    fn reject_commitment_from_other_session() {
+        let instance = CommitmentScheme::gen_params(256, 32);
+        let scheme = CommitmentScheme::new(instance);
+        let first = SessionId::from_contributions(&random_contribution(), &random_contribution());
+        let second = SessionId::from_contributions(&random_contribution(), &random_contribution());
+
+        let e = BigInt::from(10);
+        let (a, z) = scheme.commit_in_session(&e, &first);
+        assert!(scheme.verify_in_session(&a, &e, z.expose(), &first));
+        assert!(!scheme.verify_in_session(&a, &e, z.expose(), &second));
+        // With `q` much shorter than the session id, the value must still be bound.
+        assert!(!scheme.verify_in_session(&a, &(e + 1), z.expose(), &first));
+    }
+
+    #[test]
+    fn pedersen_commitments_add_up() {
+        let scheme = PedersenScheme::new(CommitmentScheme::gen_params(256, 32));
+        let (c1, r1) = scheme.commit(&BigInt::from(5));
+        let (c2, r2) = scheme.commit(&BigInt::from(-3));
+        assert!(scheme.verify(&c1, &BigInt::from(5), r1.expose()));
+        assert!(!scheme.verify(&c1, &BigInt::from(6), r1.expose()));
+
+        let sum = (c1 * c2) % scheme.params().p();
+        assert!(scheme.verify(&sum, &BigInt::from(2), &(r1.expose() + r2.expose())));
+
+        // Precomputing changes nothing but the speed.
+        let (m, r) = (BigInt::from(7), BigInt::from(11));
+        let c = scheme.commit_with(&m, &r);
+        scheme.precompute();
+        assert_eq!(scheme.commit_with(&m, &r), c);
+        assert!(scheme.verify(&sum, &BigInt::from(2), &(r1.expose() + r2.expose())));
+    }
 }
@@ -0,0 +1,714 @@
+//! Anonymous credentials with selective disclosure, in any Schnorr group.
+//!
+//! This is the keyed-verification scheme of [Chase, Meiklejohn and
+//! Zaverucha](https://eprint.iacr.org/2013/516), built on the algebraic MAC `MAC_GGM`. It needs
+//! no pairings, but only the issuer, or whoever holds its secret key, can verify a credential.
+//!
+//! The issuer has the secret key `x_0, x~_0, x_1, ..., x_n` and publishes `C_x0 = g^x_0 h^x~_0`
+//! and `X_i = h^x_i`, where nobody knows the discrete log of `h`. A credential on the attributes
+//! `m_1, ..., m_n` is a MAC `(u, u^(x_0 + x_1 m_1 + ... + x_n m_n))` for a random `u`, with a
+//! proof that it was made with the published key, so that the issuer cannot tag credentials.
+//!
+//! To present the credential, the holder rerandomizes `(u, u')` by raising both to a random
+//! power, reveals the disclosed attributes, and commits to the hidden ones as `C_i = u^m_i
+//! h^z_i`. It then proves that it knows the openings of the commitments, and that the MAC is
+//! valid on them, which is the AND of one representation statement per commitment and one for
+//! the MAC, all sharing the exponents `z_i`. A range predicate on a hidden attribute is an
+//! [`IntervalProof`] for its commitment. All proofs are made non-interactive with the
+//! Fiat-Shamir transform, and bound to a context given by the verifier, such as a nonce, so
+//! that presentations cannot be replayed.
+
+use num::{BigInt, One, Signed, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::{
+        commitments::PedersenScheme,
+        range_proof::{IntervalProof, RangeProofError},
+    },
+    fiat_shamir::NonInteractiveProof,
+    group::{PrimeOrderGroup, SchnorrGroup},
+    representation::{
+        Equation, RepresentationInstance, RepresentationProtocol, RepresentationVerifierError,
+    },
+    schnorr::SchnorrDiscreteLogInstance,
+    secret::Secret,
+    transcript::Transcript,
+};
+
+type RepresentationProof = NonInteractiveProof<Vec<BigInt>, Vec<BigInt>>;
+
+#[derive(Debug)]
+pub enum CredentialError {
+    /// The key is for `expected` attributes, but `actual` were given.
+    WrongAttributeCount { expected: usize, actual: usize },
+    /// The attribute at this index is not in `[0, q)`.
+    InvalidAttribute(usize),
+    /// The attribute at this index does not exist, is disclosed twice, or is disclosed and has a
+    /// predicate.
+    InvalidDisclosure(usize),
+    /// A value in the credential or the presentation is not an element of the group, or the
+    /// MAC is on the identity.
+    InvalidElement,
+    /// The issuer did not prove that it used its public key.
+    InvalidIssuanceProof(RepresentationVerifierError),
+    /// The presentation does not show a valid credential on its attributes.
+    InvalidProof(RepresentationVerifierError),
+    /// The attribute at this index could not be shown to satisfy its predicate.
+    PredicateFailed {
+        attribute: usize,
+        error: RangeProofError,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IssuerPublicKey {
+    group: SchnorrGroup,
+    c_x0: BigInt,
+    x: Vec<BigInt>,
+}
+
+impl IssuerPublicKey {
+    pub fn group(&self) -> &SchnorrGroup {
+        &self.group
+    }
+
+    /// The number of attributes in each credential.
+    pub fn attributes(&self) -> usize {
+        self.x.len()
+    }
+
+    /// The second base `h`, whose discrete log to `g` nobody knows.
+    fn h(&self) -> BigInt {
+        self.group.hash_to_element(b"credential base")
+    }
+
+    fn check_attributes(&self, attributes: &[BigInt]) -> Result<(), CredentialError> {
+        if attributes.len() != self.attributes() {
+            return Err(CredentialError::WrongAttributeCount {
+                expected: self.attributes(),
+                actual: attributes.len(),
+            });
+        }
+        match attributes
+            .iter()
+            .position(|m| m.is_negative() || m >= self.group.order())
+        {
+            Some(index) => Err(CredentialError::InvalidAttribute(index)),
+            None => Ok(()),
+        }
+    }
+
+    /// The statement that `u_prime` is the MAC on `attributes` under the secret key of
+    /// `C_x0` and `X_i`, with the witness `x_0, x~_0, x_1, ..., x_n`.
+    fn issuance_instance(
+        &self,
+        attributes: &[BigInt],
+        u: &BigInt,
+        u_prime: &BigInt,
+    ) -> RepresentationInstance {
+        let group = &self.group;
+        let h = self.h();
+        let mut equations = vec![Equation::new(
+            self.c_x0.clone(),
+            vec![(group.generator(), 0), (h.clone(), 1)],
+        )];
+        equations.extend(
+            self.x
+                .iter()
+                .enumerate()
+                .map(|(i, x_i)| Equation::new(x_i.clone(), vec![(h.clone(), i + 2)])),
+        );
+        let mac_terms = std::iter::once((u.clone(), 0))
+            .chain(
+                attributes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, m_i)| (group.exp(u, m_i), i + 2)),
+            )
+            .collect();
+        equations.push(Equation::new(u_prime.clone(), mac_terms));
+        RepresentationInstance::new(group.clone(), self.attributes() + 2, equations)
+    }
+
+    /// The statement of a presentation, that `v` is `X_i^z_i ... g^-r` for the exponents
+    /// `z_i` of the commitments to the hidden attributes.
+    ///
+    /// The witness has `m_i` and `z_i` for each hidden attribute in turn, and `-r` last.
+    fn presentation_instance(
+        &self,
+        u: &BigInt,
+        attributes: &[AttributeValue],
+        v: &BigInt,
+    ) -> RepresentationInstance {
+        let group = &self.group;
+        let h = self.h();
+        let hidden = attributes
+            .iter()
+            .zip(&self.x)
+            .filter_map(|(attribute, x_i)| match attribute {
+                AttributeValue::Hidden(c_i) => Some((c_i, x_i)),
+                AttributeValue::Disclosed(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut equations = hidden
+            .iter()
+            .enumerate()
+            .map(|(k, (c_i, _))| {
+                Equation::new(
+                    (*c_i).clone(),
+                    vec![(u.clone(), 2 * k), (h.clone(), 2 * k + 1)],
+                )
+            })
+            .collect::<Vec<_>>();
+        let v_terms = hidden
+            .iter()
+            .enumerate()
+            .map(|(k, (_, x_i))| ((*x_i).clone(), 2 * k + 1))
+            .chain(std::iter::once((group.generator(), 2 * hidden.len())))
+            .collect();
+        equations.push(Equation::new(v.clone(), v_terms));
+        RepresentationInstance::new(group.clone(), 2 * hidden.len() + 1, equations)
+    }
+
+    /// The Pedersen commitments `u^m h^z` that hidden attributes are committed with.
+    fn attribute_scheme(&self, u: &BigInt) -> PedersenScheme {
+        let group = &self.group;
+        PedersenScheme::new(SchnorrDiscreteLogInstance::new(
+            group.modulus().clone(),
+            group.order().clone(),
+            u.clone(),
+            self.h(),
+        ))
+    }
+}
+
+/// The issuer, which also verifies presentations, as that needs its secret key.
+pub struct Issuer {
+    public_key: IssuerPublicKey,
+    x0: Secret<BigInt>,
+    x0_blinding: Secret<BigInt>,
+    x: Secret<Vec<BigInt>>,
+}
+
+impl Issuer {
+    /// An issuer with a fresh key for credentials on `attributes` attributes.
+    pub fn new(group: SchnorrGroup, attributes: usize) -> Self {
+        let x0 = Secret::new(group.random_scalar());
+        let x0_blinding = Secret::new(group.random_scalar());
+        let x = Secret::new(
+            (0..attributes)
+                .map(|_| group.random_scalar())
+                .collect::<Vec<_>>(),
+        );
+
+        let mut public_key = IssuerPublicKey {
+            group,
+            c_x0: BigInt::zero(),
+            x: Vec::new(),
+        };
+        let (group, h) = (&public_key.group, public_key.h());
+        let exponents = Secret::new(vec![x0.expose().clone(), x0_blinding.expose().clone()]);
+        public_key.c_x0 =
+            group.multi_exp_secret(&[group.generator(), h.clone()], exponents.expose());
+        public_key.x = x
+            .expose()
+            .iter()
+            .map(|x_i| group.exp_secret(&h, x_i))
+            .collect();
+
+        Issuer {
+            public_key,
+            x0,
+            x0_blinding,
+            x,
+        }
+    }
+
+    pub fn public_key(&self) -> &IssuerPublicKey {
+        &self.public_key
+    }
+
+    /// Issue a credential on `attributes`, which the issuer has checked in some other way.
+    pub fn issue(&self, attributes: &[BigInt]) -> Result<IssuanceResponse, CredentialError> {
+        self.public_key.check_attributes(attributes)?;
+        let group = &self.public_key.group;
+        let u = loop {
+            let u = group.exp_secret(&group.generator(), &group.random_scalar());
+            if u != group.identity() {
+                break u;
+            }
+        };
+        let u_prime = group.exp_secret(&u, self.mac_exponent(attributes).expose());
+
+        let instance = self.public_key.issuance_instance(attributes, &u, &u_prime);
+        let witness = [self.x0.expose().clone(), self.x0_blinding.expose().clone()]
+            .into_iter()
+            .chain(self.x.expose().iter().cloned())
+            .collect();
+        let bits = instance.challenge_bits();
+        let proof = NonInteractiveProof::prove::<RepresentationProtocol, _, _>(
+            ISSUANCE_CONTEXT,
+            instance,
+            witness,
+            bits,
+        );
+        Ok(IssuanceResponse { u, u_prime, proof })
+    }
+
+    /// Check `presentation` in `context`, which must be the one the holder was given.
+    ///
+    /// This only shows that the holder has a credential with the disclosed attributes, and
+    /// hidden ones that satisfy the predicates. The verifier must still check that these are
+    /// the attributes and predicates it asked for.
+    pub fn verify(
+        &self,
+        presentation: &Presentation,
+        context: &[u8],
+    ) -> Result<(), CredentialError> {
+        let public_key = &self.public_key;
+        let group = &public_key.group;
+        let Presentation {
+            u,
+            c_u_prime,
+            attributes,
+            proof,
+            predicates,
+        } = presentation;
+        if attributes.len() != public_key.attributes() {
+            return Err(CredentialError::WrongAttributeCount {
+                expected: public_key.attributes(),
+                actual: attributes.len(),
+            });
+        }
+        if !group.is_element(u) || u == &group.identity() || !group.is_element(c_u_prime) {
+            return Err(CredentialError::InvalidElement);
+        }
+        for (index, attribute) in attributes.iter().enumerate() {
+            match attribute {
+                AttributeValue::Disclosed(m_i) if m_i.is_negative() || m_i >= group.order() => {
+                    return Err(CredentialError::InvalidAttribute(index))
+                }
+                AttributeValue::Hidden(c_i) if !group.is_element(c_i) => {
+                    return Err(CredentialError::InvalidElement)
+                }
+                _ => {}
+            }
+        }
+
+        // V = u^x_0 C_i^x_i ... u^(x_j m_j) ... / C_u', which is X_i^z_i ... g^-r if the MAC
+        // is valid.
+        let mut bases = vec![u.clone()];
+        let mut exponents = Secret::new(vec![self.x0.expose().clone()]);
+        for (attribute, x_i) in attributes.iter().zip(self.x.expose()) {
+            match attribute {
+                AttributeValue::Disclosed(m_i) => {
+                    bases.push(u.clone());
+                    exponents.expose_mut().push(x_i * m_i);
+                }
+                AttributeValue::Hidden(c_i) => {
+                    bases.push(c_i.clone());
+                    exponents.expose_mut().push(x_i.clone());
+                }
+            }
+        }
+        bases.push(c_u_prime.clone());
+        exponents.expose_mut().push(-BigInt::one());
+        // The exponents are the secret keys of the issuer.
+        let v = group.multi_exp_secret(&bases, exponents.expose());
+
+        let instance = public_key.presentation_instance(u, attributes, &v);
+        let bits = instance.challenge_bits();
+        proof
+            .verify::<RepresentationProtocol, _, _>(
+                &presentation_context(context, u, c_u_prime, attributes),
+                instance,
+                bits,
+            )
+            .map_err(CredentialError::InvalidProof)?;
+
+        let scheme = public_key.attribute_scheme(u);
+        for (predicate, proof) in predicates {
+            let attribute = predicate.attribute;
+            let Some(AttributeValue::Hidden(c_i)) = attributes.get(attribute) else {
+                return Err(CredentialError::InvalidDisclosure(attribute));
+            };
+            proof
+                .verify(&scheme, c_i, &predicate.min, &predicate.max)
+                .map_err(|error| CredentialError::PredicateFailed { attribute, error })?;
+        }
+
+        Ok(())
+    }
+
+    /// The exponent `x_0 + x_1 m_1 + ... + x_n m_n` of the MAC.
+    fn mac_exponent(&self, attributes: &[BigInt]) -> Secret<BigInt> {
+        let exponent = self
+            .x
+            .expose()
+            .iter()
+            .zip(attributes)
+            .fold(self.x0.expose().clone(), |acc, (x_i, m_i)| acc + x_i * m_i);
+        Secret::new(exponent % self.public_key.group.order())
+    }
+}
+
+const ISSUANCE_CONTEXT: &[u8] = b"credential issuance";
+
+/// The issuer's answer to a request for a credential, from which the holder makes a
+/// [`Credential`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IssuanceResponse {
+    u: BigInt,
+    u_prime: BigInt,
+    proof: RepresentationProof,
+}
+
+/// A credential, kept by its holder.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Credential {
+    attributes: Vec<BigInt>,
+    u: BigInt,
+    u_prime: BigInt,
+}
+
+impl Credential {
+    /// The credential on `attributes` from the issuer's `response`, after checking that the
+    /// issuer used its public key.
+    pub fn new(
+        public_key: &IssuerPublicKey,
+        attributes: Vec<BigInt>,
+        response: IssuanceResponse,
+    ) -> Result<Self, CredentialError> {
+        public_key.check_attributes(&attributes)?;
+        let group = &public_key.group;
+        let IssuanceResponse { u, u_prime, proof } = response;
+        if !group.is_element(&u) || u == group.identity() || !group.is_element(&u_prime) {
+            return Err(CredentialError::InvalidElement);
+        }
+
+        let instance = public_key.issuance_instance(&attributes, &u, &u_prime);
+        let bits = instance.challenge_bits();
+        proof
+            .verify::<RepresentationProtocol, _, _>(ISSUANCE_CONTEXT, instance, bits)
+            .map_err(CredentialError::InvalidIssuanceProof)?;
+
+        Ok(Credential {
+            attributes,
+            u,
+            u_prime,
+        })
+    }
+
+    pub fn attributes(&self) -> &[BigInt] {
+        &self.attributes
+    }
+
+    /// Present the credential in `context`, disclosing the attributes at the indices in
+    /// `disclosed` and proving that the hidden ones satisfy `predicates`.
+    pub fn present(
+        &self,
+        public_key: &IssuerPublicKey,
+        disclosed: &[usize],
+        predicates: &[RangePredicate],
+        context: &[u8],
+    ) -> Result<Presentation, CredentialError> {
+        let group = &public_key.group;
+        let n = self.attributes.len();
+        let mut is_disclosed = vec![false; n];
+        for &index in disclosed {
+            match is_disclosed.get_mut(index) {
+                Some(flag) if !*flag => *flag = true,
+                _ => return Err(CredentialError::InvalidDisclosure(index)),
+            }
+        }
+        if let Some(predicate) = predicates
+            .iter()
+            .find(|predicate| *is_disclosed.get(predicate.attribute).unwrap_or(&true))
+        {
+            return Err(CredentialError::InvalidDisclosure(predicate.attribute));
+        }
+
+        // Rerandomize the MAC, so that presentations cannot be linked to each other.
+        let a = loop {
+            let a = group.random_scalar();
+            if !a.is_zero() {
+                break a;
+            }
+        };
+        let u = group.exp_secret(&self.u, &a);
+        let u_prime = group.exp_secret(&self.u_prime, &a);
+
+        let scheme = public_key.attribute_scheme(&u);
+        let mut witness = Vec::new();
+        let mut blindings = vec![BigInt::zero(); n];
+        let attributes = self
+            .attributes
+            .iter()
+            .enumerate()
+            .map(|(i, m_i)| {
+                if is_disclosed[i] {
+                    AttributeValue::Disclosed(m_i.clone())
+                } else {
+                    let (c_i, z_i) = scheme.commit(m_i);
+                    witness.extend([m_i.clone(), z_i.expose().clone()]);
+                    blindings[i] = z_i.expose().clone();
+                    AttributeValue::Hidden(c_i)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let r = group.random_scalar();
+        let c_u_prime = group.mul(&u_prime, &group.exp_secret(&group.generator(), &r));
+        let (bases, exponents): (Vec<_>, Vec<_>) = public_key
+            .x
+            .iter()
+            .zip(&blindings)
+            .zip(&is_disclosed)
+            .filter(|(_, is_disclosed)| !**is_disclosed)
+            .map(|((x_i, z_i), _)| (x_i.clone(), z_i.clone()))
+            .chain(std::iter::once((group.generator(), -&r)))
+            .unzip();
+        let v = group.multi_exp_secret(&bases, &exponents);
+        witness.push(-r);
+
+        let instance = public_key.presentation_instance(&u, &attributes, &v);
+        debug_assert!(instance.is_satisfied_by(&witness));
+        let bits = instance.challenge_bits();
+        let proof = NonInteractiveProof::prove::<RepresentationProtocol, _, _>(
+            &presentation_context(context, &u, &c_u_prime, &attributes),
+            instance,
+            witness,
+            bits,
+        );
+
+        let predicates = predicates
+            .iter()
+            .map(|predicate| {
+                let attribute = predicate.attribute;
+                let AttributeValue::Hidden(c_i) = &attributes[attribute] else {
+                    unreachable!("Predicates on disclosed attributes were rejected above.");
+                };
+                IntervalProof::prove(
+                    &scheme,
+                    c_i,
+                    &self.attributes[attribute],
+                    &blindings[attribute],
+                    &predicate.min,
+                    &predicate.max,
+                )
+                .map(|proof| (predicate.clone(), proof))
+                .map_err(|error| CredentialError::PredicateFailed { attribute, error })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Presentation {
+            u,
+            c_u_prime,
+            attributes,
+            proof,
+            predicates,
+        })
+    }
+}
+
+/// The predicate that the attribute at index `attribute` is in `[min, max]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RangePredicate {
+    attribute: usize,
+    min: BigInt,
+    max: BigInt,
+}
+
+impl RangePredicate {
+    pub fn new(attribute: usize, min: BigInt, max: BigInt) -> Self {
+        RangePredicate {
+            attribute,
+            min,
+            max,
+        }
+    }
+
+    pub fn attribute(&self) -> usize {
+        self.attribute
+    }
+
+    pub fn min(&self) -> &BigInt {
+        &self.min
+    }
+
+    pub fn max(&self) -> &BigInt {
+        &self.max
+    }
+}
+
+/// An attribute in a presentation, either in the clear or as a commitment.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AttributeValue {
+    Disclosed(BigInt),
+    Hidden(BigInt),
+}
+
+/// A non-interactive proof that the holder has a credential with some attributes disclosed,
+/// and the others satisfying some predicates.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Presentation {
+    u: BigInt,
+    c_u_prime: BigInt,
+    attributes: Vec<AttributeValue>,
+    proof: RepresentationProof,
+    predicates: Vec<(RangePredicate, IntervalProof)>,
+}
+
+impl Presentation {
+    /// The attribute at `index`, if it is disclosed.
+    pub fn disclosed(&self, index: usize) -> Option<&BigInt> {
+        match self.attributes.get(index) {
+            Some(AttributeValue::Disclosed(m_i)) => Some(m_i),
+            _ => None,
+        }
+    }
+
+    pub fn predicates(&self) -> impl Iterator<Item = &RangePredicate> {
+        self.predicates.iter().map(|(predicate, _)| predicate)
+    }
+}
+
+/// The context of the proof of a presentation, which binds it to the verifier's context and
+/// to everything else in the presentation.
+fn presentation_context(
+    context: &[u8],
+    u: &BigInt,
+    c_u_prime: &BigInt,
+    attributes: &[AttributeValue],
+) -> Vec<u8> {
+    let mut transcript = Transcript::new(b"credential presentation");
+    transcript.append(b"context", context);
+    transcript.append_value(b"presentation", &(u, c_u_prime, attributes));
+    transcript.hash().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{applications::range_proof::RangeProofError, schnorr::SchnorrDiscreteLogInstance};
+
+    use super::{
+        AttributeValue, Credential, CredentialError, Issuer, Presentation, RangePredicate,
+    };
+
+    fn make_credential() -> (Issuer, Credential) {
+        // The range proofs need a group where cheating is unlikely.
+        let group = SchnorrDiscreteLogInstance::generate(256, 80).0.group();
+        let issuer = Issuer::new(group, 3);
+        // Say a user id, a year of birth and a country code.
+        let attributes = [12345, 1990, 49].map(BigInt::from).to_vec();
+        let response = issuer.issue(&attributes).unwrap();
+        let credential = Credential::new(issuer.public_key(), attributes, response).unwrap();
+        (issuer, credential)
+    }
+
+    #[test]
+    fn presentation_is_accepted() {
+        let (issuer, credential) = make_credential();
+        let predicate = RangePredicate::new(1, BigInt::from(1900), BigInt::from(2005));
+        let presentation = credential
+            .present(
+                issuer.public_key(),
+                &[2],
+                std::slice::from_ref(&predicate),
+                b"nonce",
+            )
+            .unwrap();
+
+        let res = issuer.verify(&presentation, b"nonce");
+        assert!(res.is_ok(), "Presentation not accepted: {:?}", res);
+        assert_eq!(presentation.disclosed(0), None);
+        assert_eq!(presentation.disclosed(2), Some(&BigInt::from(49)));
+        assert_eq!(presentation.predicates().collect::<Vec<_>>(), [&predicate]);
+
+        let json = serde_json::to_string(&presentation).unwrap();
+        let decoded: Presentation = serde_json::from_str(&json).unwrap();
+        assert!(issuer.verify(&decoded, b"nonce").is_ok());
+
+        // Presentations of the same credential cannot be linked.
+        let other = credential
+            .present(issuer.public_key(), &[2], &[], b"nonce")
+            .unwrap();
+        assert_ne!(other.u, presentation.u);
+    }
+
+    #[test]
+    fn singleton_predicate_is_accepted() {
+        let (issuer, credential) = make_credential();
+        let predicate = RangePredicate::new(2, BigInt::from(49), BigInt::from(49));
+        let presentation = credential
+            .present(issuer.public_key(), &[], &[predicate], b"nonce")
+            .unwrap();
+        let res = issuer.verify(&presentation, b"nonce");
+        assert!(res.is_ok(), "Presentation not accepted: {:?}", res);
+    }
+
+    #[test]
+    fn forged_presentation_is_rejected() {
+        let (issuer, credential) = make_credential();
+        let presentation = credential
+            .present(issuer.public_key(), &[1, 2], &[], b"nonce")
+            .unwrap();
+        assert!(matches!(
+            issuer.verify(&presentation, b"other nonce"),
+            Err(CredentialError::InvalidProof(_))
+        ));
+
+        let mut changed = presentation.clone();
+        changed.attributes[1] = AttributeValue::Disclosed(BigInt::from(1980));
+        assert!(matches!(
+            issuer.verify(&changed, b"nonce"),
+            Err(CredentialError::InvalidProof(_))
+        ));
+
+        // A credential from another issuer is not valid for this one.
+        let other_issuer = Issuer::new(issuer.public_key().group().clone(), 3);
+        assert!(matches!(
+            other_issuer.verify(&presentation, b"nonce"),
+            Err(CredentialError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn unsatisfied_predicate_cannot_be_shown() {
+        let (issuer, credential) = make_credential();
+        let predicate = RangePredicate::new(1, BigInt::from(2000), BigInt::from(2010));
+        assert!(matches!(
+            credential.present(issuer.public_key(), &[], &[predicate], b"nonce"),
+            Err(CredentialError::PredicateFailed {
+                attribute: 1,
+                error: RangeProofError::ValueOutOfRange
+            })
+        ));
+
+        let predicate = RangePredicate::new(1, BigInt::from(1900), BigInt::from(2005));
+        assert!(matches!(
+            credential.present(issuer.public_key(), &[1], &[predicate], b"nonce"),
+            Err(CredentialError::InvalidDisclosure(1))
+        ));
+        assert!(matches!(
+            credential.present(issuer.public_key(), &[0, 0], &[], b"nonce"),
+            Err(CredentialError::InvalidDisclosure(0))
+        ));
+    }
+
+    #[test]
+    fn forged_issuance_is_rejected() {
+        let (issuer, _) = make_credential();
+        let attributes = [1, 2, 3].map(BigInt::from).to_vec();
+        let other_issuer = Issuer::new(issuer.public_key().group().clone(), 3);
+        let response = other_issuer.issue(&attributes).unwrap();
+        assert!(matches!(
+            Credential::new(issuer.public_key(), attributes, response),
+            Err(CredentialError::InvalidIssuanceProof(_))
+        ));
+    }
+}
@@ -0,0 +1,904 @@
+//! Distributed key generation, where `n` parties jointly generate a key `h = g^x` such that
+//! any `threshold` of them can use `x`, but nobody knows it alone.
+//!
+//! This is the DKG of Pedersen, with proofs of knowledge against rogue keys:
+//!
+//! 1. Every party deals a random secret with Feldman VSS, and proves with
+//!    [`SchnorrDiscreteLogProtocol`] that it knows the secret it committed to.
+//! 2. Every party complains about the dealers whose share to it does not match their
+//!    commitments.
+//! 3. Every dealer answers each complaint by revealing the share of the party that complained.
+//!    A dealer with an invalid dealing or proof, or that fails to answer a complaint, is
+//!    disqualified.
+//!
+//! The key is the product of the committed secrets of the remaining dealers, and the share of
+//! each party is the sum of the shares it got from them.
+//!
+//! Messages that everyone should see, like the commitments and complaints, are sent to every
+//! party separately, so a party could send different versions of them to different parties. In
+//! a last round, every party therefore sends a hash of the qualified dealers and the key it
+//! ended up with, and fails unless all qualified dealers agree with it.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io, mem,
+};
+
+use num::{BigInt, Integer, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::vss::{check_threshold, FeldmanCommitments, Share, VssError},
+    driver::{run_rounds_local, Round, RoundParty},
+    fiat_shamir::NonInteractiveProof,
+    group::PrimeOrderGroup,
+    netutil::{BinaryEncode, IntKind},
+    schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol},
+    secret::Secret,
+    transcript::Transcript,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum DkgError {
+    /// The threshold or the index of the party is invalid.
+    InvalidParameters(VssError),
+    /// Fewer than `threshold` dealers are left after disqualifying the misbehaving ones.
+    TooFewQualified { qualified: usize, threshold: usize },
+    /// The qualified dealer `party` ended up with other qualified dealers or another key than
+    /// we did, or did not say what it ended up with.
+    InconsistentViews { party: u32 },
+}
+
+/// Why a dealer was disqualified.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Misbehaviour {
+    /// The dealer sent no message, or one that does not belong in the round.
+    MissingMessage,
+    /// The commitments of the dealer are not elements, or there are not `threshold` of them.
+    InvalidCommitments,
+    /// The proof of knowledge of the committed secret was rejected.
+    InvalidProof,
+    /// The dealer did not reveal the share of a party that complained about it.
+    UnansweredComplaint { accuser: u32 },
+    /// The share the dealer revealed for a party that complained about it is also invalid.
+    InvalidJustification { accuser: u32 },
+}
+
+type KnowledgeProof = NonInteractiveProof<BigInt, BigInt>;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DkgMessage {
+    /// The commitments of the sender with a proof of knowledge of its secret, and the share of
+    /// the recipient, which only the recipient gets.
+    Dealing {
+        commitments: FeldmanCommitments,
+        proof: KnowledgeProof,
+        share: Share,
+    },
+    /// The dealers the sender complains about.
+    Complaints(Vec<u32>),
+    /// The shares the sender dealt to the parties that complained about it.
+    Justifications(Vec<Share>),
+    /// The hash of the qualified dealers and the key of the sender.
+    Confirmation([u8; 32]),
+}
+
+impl BinaryEncode for DkgMessage {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            DkgMessage::Dealing {
+                commitments,
+                proof,
+                share,
+            } => {
+                out.push(0);
+                (commitments.clone(), proof.clone(), share.clone()).encode(int_len, out)
+            }
+            DkgMessage::Complaints(dealers) => {
+                out.push(1);
+                dealers.encode(int_len, out)
+            }
+            DkgMessage::Justifications(shares) => {
+                out.push(2);
+                shares.encode(int_len, out)
+            }
+            DkgMessage::Confirmation(view) => {
+                out.push(3);
+                view.encode(int_len, out)
+            }
+        }
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let [tag] = <[u8; 1]>::decode(int_len, input)?;
+        match tag {
+            0 => {
+                let (commitments, proof, share) = BinaryEncode::decode(int_len, input)?;
+                Ok(DkgMessage::Dealing {
+                    commitments,
+                    proof,
+                    share,
+                })
+            }
+            1 => Ok(DkgMessage::Complaints(BinaryEncode::decode(
+                int_len, input,
+            )?)),
+            2 => Ok(DkgMessage::Justifications(BinaryEncode::decode(
+                int_len, input,
+            )?)),
+            3 => Ok(DkgMessage::Confirmation(BinaryEncode::decode(
+                int_len, input,
+            )?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown message tag",
+            )),
+        }
+    }
+}
+
+/// The result of the DKG for one party.
+#[derive(Clone)]
+pub struct KeyShare {
+    public_key: SchnorrDiscreteLogInstance,
+    index: u32,
+    threshold: usize,
+    parties: u32,
+    secret_share: Secret<BigInt>,
+    commitments: Vec<FeldmanCommitments>,
+    disqualified: Vec<(u32, Misbehaviour)>,
+}
+
+impl KeyShare {
+    /// The joint key, as the instance `h = g^x`.
+    pub fn public_key(&self) -> &SchnorrDiscreteLogInstance {
+        &self.public_key
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn parties(&self) -> u32 {
+        self.parties
+    }
+
+    /// Our share of `x`, which is the value at our index of a polynomial of degree
+    /// `threshold - 1` whose constant term is `x`.
+    pub fn secret_share(&self) -> &BigInt {
+        self.secret_share.expose()
+    }
+
+    /// The dealers that were not disqualified.
+    pub fn qualified(&self) -> Vec<u32> {
+        self.commitments.iter().map(|c| c.dealer()).collect()
+    }
+
+    pub fn disqualified(&self) -> &[(u32, Misbehaviour)] {
+        &self.disqualified
+    }
+
+    /// The value `g^x_i` for the share `x_i` of the party at `index`, for checking what the
+    /// party does with its share.
+    pub fn verification_share(&self, index: u32) -> BigInt {
+        let group = self.public_key.group();
+        self.commitments.iter().fold(group.identity(), |acc, c| {
+            let share = c
+                .public_share(&self.public_key, index)
+                .expect("Qualified commitments are valid.");
+            group.mul(&acc, &share)
+        })
+    }
+}
+
+impl fmt::Debug for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyShare")
+            .field("public_key", &self.public_key)
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .field("parties", &self.parties)
+            .field("commitments", &self.commitments)
+            .field("disqualified", &self.disqualified)
+            .finish_non_exhaustive()
+    }
+}
+
+enum DkgState {
+    Deal,
+    Complain,
+    Justify,
+    Finish,
+    /// Compare our outcome with those of the others.
+    Confirm(Box<Result<KeyShare, DkgError>>),
+    Done,
+}
+
+/// One party of the DKG, as a [`RoundParty`].
+pub struct DkgParty {
+    params: SchnorrDiscreteLogInstance,
+    index: u32,
+    threshold: usize,
+    parties: u32,
+    state: DkgState,
+    /// The shares we dealt to every party, including ourselves.
+    dealt_shares: Secret<Vec<Share>>,
+    /// The commitments of the dealers that have not been disqualified, including ourselves.
+    commitments: BTreeMap<u32, FeldmanCommitments>,
+    /// The valid shares we got from each dealer.
+    shares: BTreeMap<u32, Secret<Share>>,
+    /// The complaints, as pairs of the party that complained and the dealer it complained about.
+    complaints: BTreeSet<(u32, u32)>,
+    disqualified: BTreeMap<u32, Misbehaviour>,
+}
+
+impl DkgParty {
+    /// The party at `index` of `parties`, which are numbered from 1. Only the group of `params`
+    /// is used.
+    pub fn new(
+        params: SchnorrDiscreteLogInstance,
+        index: u32,
+        threshold: usize,
+        parties: u32,
+    ) -> Result<Self, DkgError> {
+        check_threshold(threshold, parties, params.q()).map_err(DkgError::InvalidParameters)?;
+        if index == 0 || index > parties {
+            return Err(DkgError::InvalidParameters(VssError::InvalidIndex(index)));
+        }
+        Ok(DkgParty {
+            params,
+            index,
+            threshold,
+            parties,
+            state: DkgState::Deal,
+            dealt_shares: Secret::new(vec![]),
+            commitments: BTreeMap::new(),
+            shares: BTreeMap::new(),
+            complaints: BTreeSet::new(),
+            disqualified: BTreeMap::new(),
+        })
+    }
+
+    fn peers(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=self.parties).filter(move |&i| i != self.index)
+    }
+
+    /// The same message for every other party.
+    fn to_peers(&self, message: DkgMessage) -> Vec<(u32, DkgMessage)> {
+        self.peers().map(|i| (i, message.clone())).collect()
+    }
+
+    fn disqualify(&mut self, dealer: u32, misbehaviour: Misbehaviour) {
+        self.commitments.remove(&dealer);
+        self.shares.remove(&dealer);
+        self.disqualified.entry(dealer).or_insert(misbehaviour);
+    }
+
+    /// The messages of the peers that have not been disqualified, disqualifying those that sent
+    /// none.
+    fn messages_from_peers(&mut self, received: Vec<(u32, DkgMessage)>) -> Vec<(u32, DkgMessage)> {
+        let mut received = received
+            .into_iter()
+            .filter(|(from, _)| !self.disqualified.contains_key(from))
+            .collect::<BTreeMap<_, _>>();
+        let peers = self.peers().collect::<Vec<_>>();
+        let mut messages = vec![];
+        for peer in peers {
+            if self.disqualified.contains_key(&peer) {
+                continue;
+            }
+            match received.remove(&peer) {
+                Some(message) => messages.push((peer, message)),
+                None => self.disqualify(peer, Misbehaviour::MissingMessage),
+            }
+        }
+        messages
+    }
+
+    fn deal(&mut self) -> Vec<(u32, DkgMessage)> {
+        let secret = self.params.group().random_scalar();
+        let (commitments, shares) = FeldmanCommitments::deal(
+            &self.params,
+            self.index,
+            &secret,
+            self.threshold,
+            self.parties,
+        )
+        .expect("The parameters were checked.");
+        let instance = self.contribution(&commitments);
+        let proof = KnowledgeProof::prove::<SchnorrDiscreteLogProtocol, _, _>(
+            &context(self.index),
+            instance,
+            secret,
+            self.params.challenge_bits(),
+        );
+
+        let messages = self
+            .peers()
+            .map(|i| {
+                let message = DkgMessage::Dealing {
+                    commitments: commitments.clone(),
+                    proof: proof.clone(),
+                    share: shares[i as usize - 1].clone(),
+                };
+                (i, message)
+            })
+            .collect();
+        self.shares.insert(
+            self.index,
+            Secret::new(shares[self.index as usize - 1].clone()),
+        );
+        self.commitments.insert(self.index, commitments);
+        self.dealt_shares = Secret::new(shares);
+        messages
+    }
+
+    /// The instance `g^secret` of the secret of a dealer.
+    fn contribution(&self, commitments: &FeldmanCommitments) -> SchnorrDiscreteLogInstance {
+        SchnorrDiscreteLogInstance::new(
+            self.params.p().clone(),
+            self.params.q().clone(),
+            self.params.g().clone(),
+            commitments
+                .public_value()
+                .expect("Commitments are checked to be non-empty.")
+                .clone(),
+        )
+    }
+
+    /// Check the dealings of the others, and complain about those with an invalid share.
+    fn complain(&mut self, received: Vec<(u32, DkgMessage)>) -> Vec<(u32, DkgMessage)> {
+        for (dealer, message) in self.messages_from_peers(received) {
+            let (commitments, proof, share) = match message {
+                DkgMessage::Dealing {
+                    commitments,
+                    proof,
+                    share,
+                } => (commitments, proof, share),
+                _ => {
+                    self.disqualify(dealer, Misbehaviour::MissingMessage);
+                    continue;
+                }
+            };
+            let group = self.params.group();
+            if commitments.dealer() != dealer
+                || commitments.threshold() != self.threshold
+                || !commitments
+                    .commitments()
+                    .iter()
+                    .all(|c| group.is_element(c))
+            {
+                self.disqualify(dealer, Misbehaviour::InvalidCommitments);
+                continue;
+            }
+            let instance = self.contribution(&commitments);
+            let res = proof.verify::<SchnorrDiscreteLogProtocol, _, BigInt>(
+                &context(dealer),
+                instance,
+                self.params.challenge_bits(),
+            );
+            if res.is_err() {
+                self.disqualify(dealer, Misbehaviour::InvalidProof);
+                continue;
+            }
+
+            if share.index() == self.index
+                && commitments
+                    .verify(&self.params, self.threshold, &share)
+                    .is_ok()
+            {
+                self.shares.insert(dealer, Secret::new(share));
+            } else {
+                self.complaints.insert((self.index, dealer));
+            }
+            self.commitments.insert(dealer, commitments);
+        }
+
+        let dealers = self
+            .complaints
+            .iter()
+            .map(|&(_, dealer)| dealer)
+            .collect::<Vec<_>>();
+        self.to_peers(DkgMessage::Complaints(dealers))
+    }
+
+    /// Record the complaints of the others, and answer those about us.
+    fn justify(&mut self, received: Vec<(u32, DkgMessage)>) -> Vec<(u32, DkgMessage)> {
+        for (accuser, message) in self.messages_from_peers(received) {
+            match message {
+                DkgMessage::Complaints(dealers) => {
+                    for dealer in dealers {
+                        self.complaints.insert((accuser, dealer));
+                    }
+                }
+                _ => self.disqualify(accuser, Misbehaviour::MissingMessage),
+            }
+        }
+
+        let justifications = self
+            .complaints
+            .iter()
+            .filter(|&&(_, dealer)| dealer == self.index)
+            .filter_map(|&(accuser, _)| self.dealt_shares.expose().get(accuser as usize - 1))
+            .cloned()
+            .collect();
+        self.to_peers(DkgMessage::Justifications(justifications))
+    }
+
+    /// The joint key `h`, as the product of the commitments to the secrets of the qualified
+    /// dealers.
+    fn joint_key(&self) -> BigInt {
+        let group = self.params.group();
+        self.commitments
+            .values()
+            .filter_map(FeldmanCommitments::public_value)
+            .fold(group.identity(), |acc, c| group.mul(&acc, c))
+    }
+
+    /// The hash of the qualified dealers and the joint key, which every party should agree on.
+    fn view(&self) -> [u8; 32] {
+        let mut transcript = Transcript::new(b"pedersen dkg view");
+        transcript.append_value(b"qualified", &self.commitments.keys().collect::<Vec<_>>());
+        transcript.append_value(b"key", &self.joint_key());
+        transcript.hash()
+    }
+
+    /// Check that every qualified dealer ended up with the same view as we did.
+    fn confirm(
+        &self,
+        outcome: Result<KeyShare, DkgError>,
+        received: Vec<(u32, DkgMessage)>,
+    ) -> Result<KeyShare, DkgError> {
+        let key = outcome?;
+        let view = self.view();
+        let views = received
+            .into_iter()
+            .filter_map(|(from, message)| match message {
+                DkgMessage::Confirmation(view) => Some((from, view)),
+                _ => None,
+            })
+            .collect::<BTreeMap<_, _>>();
+        for &party in self.commitments.keys() {
+            if party != self.index && views.get(&party) != Some(&view) {
+                return Err(DkgError::InconsistentViews { party });
+            }
+        }
+        Ok(key)
+    }
+
+    /// Check the answers to the complaints, and combine the shares of the qualified dealers.
+    fn finish(&mut self, received: Vec<(u32, DkgMessage)>) -> Result<KeyShare, DkgError> {
+        let mut justifications = BTreeMap::new();
+        for (dealer, message) in self.messages_from_peers(received) {
+            match message {
+                DkgMessage::Justifications(shares) => {
+                    justifications.insert(dealer, shares);
+                }
+                _ => self.disqualify(dealer, Misbehaviour::MissingMessage),
+            }
+        }
+
+        let complaints = self.complaints.iter().copied().collect::<Vec<_>>();
+        for (accuser, dealer) in complaints {
+            // We trust ourselves to have answered correctly.
+            if dealer == self.index {
+                continue;
+            }
+            let commitments = match self.commitments.get(&dealer) {
+                Some(commitments) => commitments,
+                None => continue,
+            };
+            let revealed = justifications
+                .get(&dealer)
+                .and_then(|shares| shares.iter().find(|s| s.index() == accuser));
+            match revealed {
+                None => self.disqualify(dealer, Misbehaviour::UnansweredComplaint { accuser }),
+                Some(share)
+                    if commitments
+                        .verify(&self.params, self.threshold, share)
+                        .is_err() =>
+                {
+                    self.disqualify(dealer, Misbehaviour::InvalidJustification { accuser })
+                }
+                Some(share) if accuser == self.index => {
+                    self.shares.insert(dealer, Secret::new(share.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+
+        if self.commitments.len() < self.threshold {
+            return Err(DkgError::TooFewQualified {
+                qualified: self.commitments.len(),
+                threshold: self.threshold,
+            });
+        }
+
+        let group = self.params.group();
+        let h = self.joint_key();
+        let secret_share = self.shares.values().fold(BigInt::zero(), |acc, s| {
+            (acc + s.expose().value()).mod_floor(group.order())
+        });
+        Ok(KeyShare {
+            public_key: SchnorrDiscreteLogInstance::new(
+                self.params.p().clone(),
+                self.params.q().clone(),
+                self.params.g().clone(),
+                h,
+            ),
+            index: self.index,
+            threshold: self.threshold,
+            parties: self.parties,
+            secret_share: Secret::new(secret_share),
+            commitments: self.commitments.values().cloned().collect(),
+            disqualified: self
+                .disqualified
+                .iter()
+                .map(|(&dealer, m)| (dealer, m.clone()))
+                .collect(),
+        })
+    }
+}
+
+impl RoundParty for DkgParty {
+    type Message = DkgMessage;
+    type Output = Result<KeyShare, DkgError>;
+
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn next_round(&mut self, received: Vec<(u32, DkgMessage)>) -> Round<DkgMessage, Self::Output> {
+        match mem::replace(&mut self.state, DkgState::Done) {
+            DkgState::Deal => {
+                self.state = DkgState::Complain;
+                // The dealings have both elements and shares, so use the larger size for both.
+                Round::Send(self.deal(), IntKind::Element)
+            }
+            DkgState::Complain => {
+                self.state = DkgState::Justify;
+                Round::Send(self.complain(received), IntKind::Scalar)
+            }
+            DkgState::Justify => {
+                self.state = DkgState::Finish;
+                Round::Send(self.justify(received), IntKind::Scalar)
+            }
+            DkgState::Finish => {
+                let outcome = self.finish(received);
+                self.state = DkgState::Confirm(Box::new(outcome));
+                Round::Send(
+                    self.to_peers(DkgMessage::Confirmation(self.view())),
+                    IntKind::Scalar,
+                )
+            }
+            DkgState::Confirm(outcome) => Round::Done(self.confirm(*outcome, received)),
+            DkgState::Done => panic!("The DKG is already done."),
+        }
+    }
+}
+
+/// Run the DKG between `parties` honest parties in memory, and return their key shares in
+/// order of their index. This is mostly useful for tests.
+pub fn simulate(
+    params: &SchnorrDiscreteLogInstance,
+    threshold: usize,
+    parties: u32,
+) -> Result<Vec<KeyShare>, DkgError> {
+    let mut parties = (1..=parties)
+        .map(|i| DkgParty::new(params.clone(), i, threshold, parties))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut parties = parties
+        .iter_mut()
+        .map(|p| p as &mut dyn RoundParty<Message = DkgMessage, Output = _>)
+        .collect::<Vec<_>>();
+    run_rounds_local(&mut parties).into_iter().collect()
+}
+
+/// Bind the proof of knowledge of a dealer to its index, so nobody else can copy it.
+fn context(dealer: u32) -> Vec<u8> {
+    serde_json::to_vec(&("pedersen dkg", dealer)).expect("Value could not be serialized.")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use num::BigInt;
+
+    use crate::{
+        applications::vss::{reconstruct, FeldmanCommitments, Share},
+        driver::{run_rounds_local, run_rounds_remote, Round, RoundParty},
+        group::PrimeOrderGroup,
+        netutil::{Channel, Codec, Limits, NetError},
+        schnorr::SchnorrDiscreteLogInstance,
+    };
+
+    use super::{simulate, DkgError, DkgMessage, DkgParty, KeyShare, Misbehaviour};
+
+    enum Cheat {
+        /// Send an invalid share to this party, but answer the complaint about it.
+        BadShare(u32),
+        /// Send an invalid share to this party, and ignore the complaint about it.
+        UnansweredBadShare(u32),
+        /// Commit to a different secret than the one proven.
+        ForgedCommitment,
+        /// Complain about `dealer` to `told` only.
+        SplitComplaint { dealer: u32, told: u32 },
+    }
+
+    struct Cheater {
+        party: DkgParty,
+        cheat: Cheat,
+    }
+
+    impl RoundParty for Cheater {
+        type Message = DkgMessage;
+        type Output = Result<KeyShare, DkgError>;
+
+        fn index(&self) -> u32 {
+            self.party.index()
+        }
+
+        fn next_round(
+            &mut self,
+            received: Vec<(u32, DkgMessage)>,
+        ) -> Round<DkgMessage, Self::Output> {
+            let mut round = self.party.next_round(received);
+            if let Round::Send(messages, _) = &mut round {
+                for (to, message) in messages {
+                    match (&self.cheat, message) {
+                        (
+                            Cheat::BadShare(victim) | Cheat::UnansweredBadShare(victim),
+                            DkgMessage::Dealing { share, .. },
+                        ) if *to == *victim => {
+                            *share = Share::new(share.index(), share.value() + 1);
+                        }
+                        (Cheat::UnansweredBadShare(_), DkgMessage::Justifications(shares)) => {
+                            shares.clear();
+                        }
+                        (
+                            Cheat::SplitComplaint { dealer, told },
+                            DkgMessage::Complaints(dealers),
+                        ) if *to == *told => {
+                            dealers.push(*dealer);
+                        }
+                        (Cheat::ForgedCommitment, DkgMessage::Dealing { commitments, .. }) => {
+                            let mut values = commitments.commitments().to_vec();
+                            values[0] = self.party.params.g().clone();
+                            *commitments = FeldmanCommitments::new(commitments.dealer(), values);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            round
+        }
+    }
+
+    fn make_params() -> SchnorrDiscreteLogInstance {
+        SchnorrDiscreteLogInstance::generate(256, 64).0
+    }
+
+    /// Check that the key shares agree on the key, and that `threshold` of them reconstruct it.
+    fn check_keys(keys: &[KeyShare]) -> BigInt {
+        let public_key = keys[0].public_key();
+        let group = public_key.group();
+        for key in keys {
+            assert_eq!(key.public_key().h(), public_key.h());
+            for other in keys {
+                assert_eq!(
+                    key.verification_share(other.index()),
+                    group.exp(public_key.g(), other.secret_share())
+                );
+            }
+        }
+
+        let threshold = keys[0].threshold();
+        let shares = keys
+            .iter()
+            .rev()
+            .map(|k| Share::new(k.index(), k.secret_share().clone()))
+            .collect::<Vec<_>>();
+        let x = reconstruct(public_key.q(), &shares, threshold).unwrap();
+        assert_eq!(&group.exp(public_key.g(), &x), public_key.h());
+        x
+    }
+
+    fn run_with_cheater(
+        params: &SchnorrDiscreteLogInstance,
+        threshold: usize,
+        parties: u32,
+        cheat: Cheat,
+    ) -> Vec<Result<KeyShare, DkgError>> {
+        let mut cheater = Cheater {
+            party: DkgParty::new(params.clone(), 1, threshold, parties).unwrap(),
+            cheat,
+        };
+        let mut honest = (2..=parties)
+            .map(|i| DkgParty::new(params.clone(), i, threshold, parties).unwrap())
+            .collect::<Vec<_>>();
+        let mut all: Vec<&mut dyn RoundParty<Message = DkgMessage, Output = _>> =
+            vec![&mut cheater];
+        all.extend(
+            honest
+                .iter_mut()
+                .map(|p| p as &mut dyn RoundParty<Message = DkgMessage, Output = _>),
+        );
+        // Leave out the output of the cheater.
+        run_rounds_local(&mut all).into_iter().skip(1).collect()
+    }
+
+    #[test]
+    fn honest_parties_agree_on_key() {
+        let keys = simulate(&make_params(), 3, 5).unwrap();
+        check_keys(&keys);
+        for key in &keys {
+            assert_eq!(key.qualified(), vec![1, 2, 3, 4, 5]);
+            assert!(key.disqualified().is_empty());
+        }
+    }
+
+    #[test]
+    fn answered_complaint_keeps_dealer() {
+        let params = make_params();
+        let keys = run_with_cheater(&params, 2, 4, Cheat::BadShare(3))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        check_keys(&keys);
+        assert_eq!(keys[0].qualified(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn unanswered_complaint_disqualifies_dealer() {
+        let params = make_params();
+        let keys = run_with_cheater(&params, 2, 4, Cheat::UnansweredBadShare(3))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        check_keys(&keys);
+        for key in &keys {
+            assert_eq!(key.qualified(), vec![2, 3, 4]);
+            assert_eq!(
+                key.disqualified(),
+                &[(1, Misbehaviour::UnansweredComplaint { accuser: 3 })]
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_proof_disqualifies_dealer() {
+        let params = make_params();
+        let keys = run_with_cheater(&params, 2, 3, Cheat::ForgedCommitment)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        check_keys(&keys);
+        for key in &keys {
+            assert_eq!(key.disqualified(), &[(1, Misbehaviour::InvalidProof)]);
+        }
+
+        let results = run_with_cheater(&params, 3, 3, Cheat::ForgedCommitment);
+        for res in results {
+            assert_eq!(
+                res.unwrap_err(),
+                DkgError::TooFewQualified {
+                    qualified: 2,
+                    threshold: 3
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn split_complaint_is_detected() {
+        // Only party 3 hears the complaint about dealer 2, and disqualifies it when it does not
+        // answer, so the others end up with another key.
+        let params = make_params();
+        let results = run_with_cheater(&params, 2, 4, Cheat::SplitComplaint { dealer: 2, told: 3 });
+        for res in results {
+            assert!(matches!(res, Err(DkgError::InconsistentViews { .. })));
+        }
+    }
+
+    /// Connect every pair of `parties` parties, with `limits` on every channel.
+    fn connect(parties: u32, limits: Limits, codec: Codec) -> Vec<Vec<(u32, Channel)>> {
+        let mut channels = (0..parties).map(|_| vec![]).collect::<Vec<_>>();
+        for i in 1..=parties {
+            for j in i + 1..=parties {
+                let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+                let (accepted, _) = listener.accept().unwrap();
+                channels[i as usize - 1].push((j, Channel::with_limits(stream, codec, limits)));
+                channels[j as usize - 1].push((i, Channel::with_limits(accepted, codec, limits)));
+            }
+        }
+        channels
+    }
+
+    /// The key share of a party run remotely, with the peers whose channel failed.
+    type RemoteOutcome = (Result<KeyShare, DkgError>, Vec<(u32, NetError)>);
+
+    /// Run the first `honest` of `channels.len()` parties remotely, while the others stay
+    /// connected but silent.
+    fn run_remotely(
+        params: &SchnorrDiscreteLogInstance,
+        channels: Vec<Vec<(u32, Channel)>>,
+        honest: usize,
+    ) -> Vec<RemoteOutcome> {
+        let parties = channels.len() as u32;
+        let mut channels = channels.into_iter();
+        let handles = channels
+            .by_ref()
+            .take(honest)
+            .enumerate()
+            .map(|(i, mut channels)| {
+                let params = params.clone();
+                thread::spawn(move || {
+                    let mut party = DkgParty::new(params, i as u32 + 1, 2, parties).unwrap();
+                    run_rounds_remote(&mut party, &mut channels)
+                })
+            })
+            .collect::<Vec<_>>();
+        let results = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        drop(channels);
+        results
+    }
+
+    #[test]
+    fn honest_run_is_accepted_remotely() {
+        let params = make_params();
+        let channels = connect(3, Limits::default(), Codec::binary_for(&params));
+        let keys = run_remotely(&params, channels, 3)
+            .into_iter()
+            .map(|(key, failures)| {
+                assert!(failures.is_empty());
+                key.unwrap()
+            })
+            .collect::<Vec<_>>();
+        check_keys(&keys);
+    }
+
+    #[test]
+    fn silent_party_is_disqualified_remotely() {
+        let params = make_params();
+        let limits = Limits {
+            read_timeout: Some(Duration::from_millis(500)),
+            ..Limits::default()
+        };
+        let channels = connect(4, limits, Codec::binary_for(&params));
+        let keys = run_remotely(&params, channels, 3)
+            .into_iter()
+            .map(|(key, failures)| {
+                assert!(matches!(&failures[..], [(4, NetError::ReadTimeout)]));
+                key.unwrap()
+            })
+            .collect::<Vec<_>>();
+        check_keys(&keys);
+        for key in &keys {
+            assert_eq!(key.qualified(), vec![1, 2, 3]);
+            assert_eq!(key.disqualified(), &[(4, Misbehaviour::MissingMessage)]);
+        }
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        let params = make_params();
+        assert!(matches!(
+            DkgParty::new(params.clone(), 0, 2, 3),
+            Err(DkgError::InvalidParameters(_))
+        ));
+        assert!(matches!(
+            simulate(&params, 4, 3),
+            Err(DkgError::InvalidParameters(_))
+        ));
+    }
+}
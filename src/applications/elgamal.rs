@@ -0,0 +1,490 @@
+//! ElGamal encryption in the group of a [`SchnorrDiscreteLogInstance`], with proofs about
+//! ciphertexts and decryptions.
+//!
+//! A ciphertext of `m` under the public key `h = g^x` is `(g^r, m h^r)`. Multiplying two
+//! ciphertexts gives a ciphertext of the product of their plaintexts. Encrypting `g^m` instead
+//! of `m` ("exponential ElGamal", see [`PublicKey::encode`]) turns this into addition, at the
+//! cost of only being able to decrypt small `m`.
+
+use std::{collections::HashMap, io};
+
+use num::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dleq::{DleqInstance, DleqProtocol, DleqVerifierError},
+    fiat_shamir::NonInteractiveProof,
+    group::{PrimeOrderGroup, SchnorrGroup},
+    netutil::BinaryEncode,
+    or::{NaryOrProtocol, OrProtocolVerifierError},
+    schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol, SchnorrVerifierError},
+    secret::Secret,
+};
+
+#[derive(Debug)]
+pub enum ElGamalError {
+    /// A part of the ciphertext is not an element of the group.
+    InvalidCiphertext,
+    /// There are no allowed plaintexts to prove membership for.
+    NoAllowedPlaintexts,
+    PlaintextProofError(OrProtocolVerifierError<DleqVerifierError>),
+    DecryptionProofError(DleqVerifierError),
+}
+
+/// The largest plaintext [`PublicKey::decode`] searches for. Its table of baby steps has
+/// `sqrt(MAX_DECODE)` entries, 2^24 of them.
+pub const MAX_DECODE: u64 = 1 << 48;
+
+/// A non-interactive Schnorr proof of knowledge of a secret key.
+pub type KnowledgeProof = NonInteractiveProof<BigInt, BigInt>;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ciphertext {
+    c1: BigInt,
+    c2: BigInt,
+}
+
+impl Ciphertext {
+    pub fn c1(&self) -> &BigInt {
+        &self.c1
+    }
+
+    pub fn c2(&self) -> &BigInt {
+        &self.c2
+    }
+}
+
+impl BinaryEncode for Ciphertext {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.c1.encode(int_len, out)?;
+        self.c2.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let (c1, c2) = BinaryEncode::decode(int_len, input)?;
+        Ok(Ciphertext { c1, c2 })
+    }
+}
+
+/// A public key, as the instance `h = g^x` of its secret key `x`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicKey {
+    instance: SchnorrDiscreteLogInstance,
+}
+
+impl PublicKey {
+    pub fn new(instance: SchnorrDiscreteLogInstance) -> Self {
+        PublicKey { instance }
+    }
+
+    pub fn instance(&self) -> &SchnorrDiscreteLogInstance {
+        &self.instance
+    }
+
+    pub fn group(&self) -> SchnorrGroup {
+        self.instance.group()
+    }
+
+    /// Encode `m` as the plaintext `g^m`, for exponential ElGamal.
+    pub fn encode(&self, m: &BigInt) -> BigInt {
+        self.group().exp(self.instance.g(), m)
+    }
+
+    /// Encrypt the group element `message`, returning the ciphertext and its randomness.
+    pub fn encrypt(&self, message: &BigInt) -> (Ciphertext, Secret<BigInt>) {
+        let r = Secret::new(self.group().random_scalar());
+        (self.encrypt_with(message, r.expose()), r)
+    }
+
+    pub fn encrypt_with(&self, message: &BigInt, r: &BigInt) -> Ciphertext {
+        let group = self.group();
+        Ciphertext {
+            c1: self.instance.pow_g_secret(r),
+            c2: group.mul(message, &self.instance.pow_h_secret(r)),
+        }
+    }
+
+    /// A ciphertext of the product of the plaintexts, or of the sum for exponential ElGamal.
+    pub fn add(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let group = self.group();
+        Ciphertext {
+            c1: group.mul(&a.c1, &b.c1),
+            c2: group.mul(&a.c2, &b.c2),
+        }
+    }
+
+    /// A fresh ciphertext of the same plaintext, made by multiplying in an encryption of the
+    /// identity with randomness `r`.
+    pub fn reencrypt_with(&self, ciphertext: &Ciphertext, r: &BigInt) -> Ciphertext {
+        self.add(ciphertext, &self.encrypt_with(&self.group().identity(), r))
+    }
+
+    pub fn is_valid(&self, ciphertext: &Ciphertext) -> bool {
+        let group = self.group();
+        group.is_element(&ciphertext.c1) && group.is_element(&ciphertext.c2)
+    }
+
+    /// Find `m` in `[0, max]` such that `plaintext = g^m`, for exponential ElGamal.
+    ///
+    /// This takes about `2 sqrt(max)` group operations, with baby-step giant-step, and gives up
+    /// straight away if `max` is above [`MAX_DECODE`].
+    pub fn decode(&self, plaintext: &BigInt, max: u64) -> Option<u64> {
+        if max > MAX_DECODE {
+            return None;
+        }
+        let group = self.group();
+        let g = self.instance.g();
+        let step = (max as f64).sqrt() as u64 + 1;
+
+        let mut baby_steps = HashMap::new();
+        let mut power = group.identity();
+        for j in 0..step {
+            baby_steps.entry(power.clone()).or_insert(j);
+            power = group.mul(&power, g);
+        }
+
+        // `power` is now `g^step`, so each giant step divides by it.
+        let giant_step = group.inverse(&power);
+        let mut current = plaintext.clone();
+        for i in 0..=max / step {
+            if let Some(j) = baby_steps.get(&current) {
+                let m = i * step + j;
+                return (m <= max).then_some(m);
+            }
+            current = group.mul(&current, &giant_step);
+        }
+        None
+    }
+
+    /// Verify a proof from [`SecretKey::prove_knowledge`].
+    pub fn verify_knowledge(
+        &self,
+        proof: &KnowledgeProof,
+        context: &[u8],
+    ) -> Result<(), SchnorrVerifierError> {
+        let instance = &self.instance;
+        if !instance.is_element(instance.h()) || !instance.is_element(proof.initial_msg()) {
+            return Err(SchnorrVerifierError::NotInGroup);
+        }
+        let bits = instance.challenge_bits();
+        proof.verify::<SchnorrDiscreteLogProtocol, _, BigInt>(context, self.instance.clone(), bits)
+    }
+
+    /// The key of several parties together, whose secret key is the sum of their secret keys.
+    ///
+    /// Decrypting under it needs a [`DecryptionShare`] from each of them.
+    pub fn combine(keys: &[PublicKey]) -> PublicKey {
+        let first = &keys.first().expect("Expected at least one key.").instance;
+        let group = first.group();
+        let h = keys.iter().fold(group.identity(), |acc, key| {
+            group.mul(&acc, key.instance.h())
+        });
+        PublicKey::new(SchnorrDiscreteLogInstance::new(
+            first.p().clone(),
+            first.q().clone(),
+            first.g().clone(),
+            h,
+        ))
+    }
+
+    /// The plaintext of `ciphertext`, given a decryption share from each party of the key.
+    pub fn combine_shares(&self, ciphertext: &Ciphertext, shares: &[DecryptionShare]) -> BigInt {
+        let group = self.group();
+        let product = shares
+            .iter()
+            .fold(group.identity(), |acc, share| group.mul(&acc, &share.share));
+        group.mul(&ciphertext.c2, &group.inverse(&product))
+    }
+}
+
+pub struct SecretKey {
+    public: PublicKey,
+    x: Secret<BigInt>,
+}
+
+impl SecretKey {
+    /// Generate a key in the group of `params`. The `h` of `params` is not used.
+    pub fn generate(params: &SchnorrDiscreteLogInstance) -> Self {
+        let group = params.group();
+        let x = Secret::new(group.random_scalar());
+        let h = group.exp_secret(params.g(), x.expose());
+        let instance = SchnorrDiscreteLogInstance::new(
+            params.p().clone(),
+            params.q().clone(),
+            params.g().clone(),
+            h,
+        );
+        SecretKey {
+            public: PublicKey::new(instance),
+            x,
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    /// Prove knowledge of the secret key. Before combining keys, each party should check this
+    /// proof for the keys of all others, so that nobody can choose their key as a function of
+    /// the keys of others.
+    pub fn prove_knowledge(&self, context: &[u8]) -> KnowledgeProof {
+        let instance = self.public.instance.clone();
+        let bits = instance.challenge_bits();
+        NonInteractiveProof::prove::<SchnorrDiscreteLogProtocol, _, _>(
+            context,
+            instance,
+            self.x.expose().clone(),
+            bits,
+        )
+    }
+
+    pub fn decrypt(&self, ciphertext: &Ciphertext) -> BigInt {
+        let group = self.public.group();
+        let shared = group.exp_secret(&ciphertext.c1, self.x.expose());
+        group.mul(&ciphertext.c2, &group.inverse(&shared))
+    }
+
+    /// Our share `c1^x` of the decryption of `ciphertext`, with a proof that it is correct.
+    pub fn decryption_share(&self, ciphertext: &Ciphertext, context: &[u8]) -> DecryptionShare {
+        let group = self.public.group();
+        let share = group.exp_secret(&ciphertext.c1, self.x.expose());
+        let instance = decryption_instance(&self.public, ciphertext, &share);
+        let bits = instance.challenge_bits();
+        let proof = NonInteractiveProof::prove::<DleqProtocol, _, _>(
+            context,
+            instance,
+            self.x.expose().clone(),
+            bits,
+        );
+        DecryptionShare { share, proof }
+    }
+}
+
+/// A share `c1^x` of a decryption, with a proof that it has the same discrete log as the public
+/// key `h = g^x`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DecryptionShare {
+    share: BigInt,
+    proof: NonInteractiveProof<(BigInt, BigInt), BigInt>,
+}
+
+impl DecryptionShare {
+    pub fn share(&self) -> &BigInt {
+        &self.share
+    }
+
+    /// Verify the share of the holder of `key`.
+    pub fn verify(
+        &self,
+        key: &PublicKey,
+        ciphertext: &Ciphertext,
+        context: &[u8],
+    ) -> Result<(), ElGamalError> {
+        if !key.is_valid(ciphertext) {
+            return Err(ElGamalError::InvalidCiphertext);
+        }
+        let instance = decryption_instance(key, ciphertext, &self.share);
+        let bits = instance.challenge_bits();
+        self.proof
+            .verify::<DleqProtocol, _, BigInt>(context, instance, bits)
+            .map_err(ElGamalError::DecryptionProofError)
+    }
+}
+
+/// A proof that a ciphertext encrypts one of a list of allowed plaintexts, without revealing
+/// which. With a single allowed plaintext, this proves that the plaintext is known.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlaintextProof {
+    proof: NonInteractiveProof<Vec<DleqMessage>, Vec<DleqMessage>>,
+}
+
+type PlaintextProtocol = NaryOrProtocol<DleqProtocol, BigInt, BigInt>;
+
+/// The initial messages of the statements, and their challenges and responses.
+type DleqMessage = (BigInt, BigInt);
+
+impl PlaintextProof {
+    /// Prove that `ciphertext`, encrypted with randomness `r`, encrypts `allowed[index]`.
+    pub fn prove(
+        key: &PublicKey,
+        ciphertext: &Ciphertext,
+        r: &BigInt,
+        allowed: &[BigInt],
+        index: usize,
+        context: &[u8],
+    ) -> Self {
+        let statements = plaintext_instances(key, ciphertext, allowed);
+        let bits = statements[0].challenge_bits();
+        let proof = NonInteractiveProof::prove::<PlaintextProtocol, _, _>(
+            context,
+            statements,
+            (index, r.clone()),
+            bits,
+        );
+        PlaintextProof { proof }
+    }
+
+    pub fn verify(
+        &self,
+        key: &PublicKey,
+        ciphertext: &Ciphertext,
+        allowed: &[BigInt],
+        context: &[u8],
+    ) -> Result<(), ElGamalError> {
+        if !key.is_valid(ciphertext) {
+            return Err(ElGamalError::InvalidCiphertext);
+        }
+        if allowed.is_empty() {
+            return Err(ElGamalError::NoAllowedPlaintexts);
+        }
+        let statements = plaintext_instances(key, ciphertext, allowed);
+        let bits = statements[0].challenge_bits();
+        self.proof
+            .verify::<PlaintextProtocol, _, (usize, BigInt)>(context, statements, bits)
+            .map_err(ElGamalError::PlaintextProofError)
+    }
+}
+
+/// `log_g h = log_c1 share`.
+fn decryption_instance(key: &PublicKey, ciphertext: &Ciphertext, share: &BigInt) -> DleqInstance {
+    DleqInstance::new(
+        key.group(),
+        key.instance.g().clone(),
+        key.instance.h().clone(),
+        ciphertext.c1.clone(),
+        share.clone(),
+    )
+}
+
+/// `log_g c1 = log_h (c2 / m)` for every allowed plaintext `m`.
+fn plaintext_instances(
+    key: &PublicKey,
+    ciphertext: &Ciphertext,
+    allowed: &[BigInt],
+) -> Vec<DleqInstance> {
+    let group = key.group();
+    allowed
+        .iter()
+        .map(|m| {
+            DleqInstance::new(
+                group.clone(),
+                key.instance.g().clone(),
+                ciphertext.c1.clone(),
+                key.instance.h().clone(),
+                group.mul(&ciphertext.c2, &group.inverse(m)),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::schnorr::SchnorrDiscreteLogInstance;
+
+    use super::{ElGamalError, PlaintextProof, PublicKey, SecretKey};
+
+    fn make_key() -> SecretKey {
+        let (params, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        SecretKey::generate(&params)
+    }
+
+    #[test]
+    fn decryption_inverts_encryption() {
+        let key = make_key();
+        let pk = key.public_key();
+        let m = pk.encode(&BigInt::from(42));
+        let (c, _) = pk.encrypt(&m);
+        assert_eq!(key.decrypt(&c), m);
+    }
+
+    #[test]
+    fn exponential_encryption_adds_up() {
+        let key = make_key();
+        let pk = key.public_key();
+        let (a, _) = pk.encrypt(&pk.encode(&BigInt::from(3)));
+        let (b, _) = pk.encrypt(&pk.encode(&BigInt::from(4)));
+        let sum = pk.add(&a, &b);
+        assert_eq!(pk.decode(&key.decrypt(&sum), 10), Some(7));
+        assert_eq!(pk.decode(&key.decrypt(&sum), 7), Some(7));
+        assert_eq!(pk.decode(&key.decrypt(&sum), 6), None);
+
+        let large = pk.encode(&BigInt::from(123_456));
+        assert_eq!(pk.decode(&large, 1_000_000), Some(123_456));
+        assert_eq!(pk.decode(&pk.encode(&BigInt::from(0)), 0), Some(0));
+    }
+
+    #[test]
+    fn plaintext_proofs() {
+        let key = make_key();
+        let pk = key.public_key();
+        let allowed = [0, 1, 2].map(|m| pk.encode(&BigInt::from(m)));
+
+        let (c, r) = pk.encrypt(&allowed[1]);
+        let proof = PlaintextProof::prove(pk, &c, r.expose(), &allowed, 1, b"ballot 1");
+        assert!(proof.verify(pk, &c, &allowed, b"ballot 1").is_ok());
+        assert!(proof.verify(pk, &c, &allowed, b"ballot 2").is_err());
+        assert!(proof.verify(pk, &c, &allowed[..1], b"ballot 1").is_err());
+
+        // A known plaintext is the case of a single allowed one.
+        let proof = PlaintextProof::prove(pk, &c, r.expose(), &allowed[1..2], 0, b"known");
+        assert!(proof.verify(pk, &c, &allowed[1..2], b"known").is_ok());
+        assert!(proof.verify(pk, &c, &allowed[2..3], b"known").is_err());
+    }
+
+    #[test]
+    fn disallowed_plaintext_is_rejected() {
+        let key = make_key();
+        let pk = key.public_key();
+        let allowed = [0, 1].map(|m| pk.encode(&BigInt::from(m)));
+
+        let (c, r) = pk.encrypt(&pk.encode(&BigInt::from(5)));
+        for index in 0..2 {
+            let proof = PlaintextProof::prove(pk, &c, r.expose(), &allowed, index, b"ballot");
+            assert!(matches!(
+                proof.verify(pk, &c, &allowed, b"ballot"),
+                Err(ElGamalError::PlaintextProofError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn threshold_decryption_with_proofs() {
+        let (params, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let keys = (0..3)
+            .map(|_| SecretKey::generate(&params))
+            .collect::<Vec<_>>();
+        let public_keys = keys
+            .iter()
+            .map(|key| key.public_key().clone())
+            .collect::<Vec<_>>();
+        let joint = PublicKey::combine(&public_keys);
+
+        let m = joint.encode(&BigInt::from(9));
+        let (c, _) = joint.encrypt(&m);
+        let shares = keys
+            .iter()
+            .map(|key| key.decryption_share(&c, b"tally"))
+            .collect::<Vec<_>>();
+        for (i, key) in keys.iter().enumerate() {
+            let proof = key.prove_knowledge(b"registration");
+            assert!(public_keys[i]
+                .verify_knowledge(&proof, b"registration")
+                .is_ok());
+            assert!(public_keys[(i + 1) % 3]
+                .verify_knowledge(&proof, b"registration")
+                .is_err());
+        }
+        for (share, key) in shares.iter().zip(&public_keys) {
+            assert!(share.verify(key, &c, b"tally").is_ok());
+        }
+        assert_eq!(joint.combine_shares(&c, &shares), m);
+
+        // A share cannot be passed off as another party's.
+        assert!(matches!(
+            shares[0].verify(&public_keys[1], &c, b"tally"),
+            Err(ElGamalError::DecryptionProofError(_))
+        ));
+    }
+}
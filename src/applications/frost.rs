@@ -0,0 +1,656 @@
+//! Threshold Schnorr signatures, following [FROST](https://eprint.iacr.org/2020/852).
+//!
+//! Any `threshold` of the parties holding shares of a key, for example from the
+//! [`dkg`](crate::applications::dkg), can sign together in two rounds:
+//!
+//! 1. Each signer picks two nonces `d_i` and `e_i`, and publishes the commitments `D_i = g^d_i`
+//!    and `E_i = g^e_i`. This can be done before the message is known.
+//! 2. Given the message and the commitments of all signers, each signer computes the binding
+//!    factors `rho_j`, the group commitment `R = prod D_j E_j^rho_j` and the challenge `c`, and
+//!    sends `z_i = d_i + e_i rho_i + lambda_i s_i c`, where `lambda_i` is its Lagrange
+//!    coefficient and `s_i` its share of the key.
+//!
+//! Each share can be checked against the verification share `Y_i = g^s_i` of its signer, so a
+//! signer that sends a wrong share is caught. The shares add up to an ordinary
+//! [`Signature`], which is checked with [`signatures::verify`](super::signatures::verify).
+//!
+//! The binding factors tie the nonces of every signer to the message and to all commitments,
+//! which is what makes it safe to run many signing sessions at once. Nonces must never be
+//! reused, so [`SigningNonces`] are consumed when signing.
+
+use std::{collections::BTreeMap, io, mem};
+
+use num::{BigInt, Integer, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::{
+        dkg::KeyShare,
+        signatures::{challenge, Signature},
+        vss::lagrange_coefficient,
+    },
+    driver::{Round, RoundParty},
+    group::{PrimeOrderGroup, SchnorrGroup},
+    netutil::{BinaryEncode, IntKind},
+    secret::Secret,
+    transcript::Transcript,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum FrostError {
+    /// Signing needs at least `needed` signers.
+    NotEnoughSigners { needed: usize, actual: usize },
+    /// There is no verification share for the signer, so it does not hold a share of the key.
+    UnknownSigner(u32),
+    /// The signing package has no commitment of the signer.
+    MissingCommitment(u32),
+    /// A message came from a party that is not one of the signers of the session.
+    UnexpectedSigner(u32),
+    /// The nonces are not the ones of the commitment in the signing package.
+    WrongNonces,
+    /// A nonce commitment of the signer is not an element of the group.
+    InvalidCommitment { signer: u32 },
+    /// The signature share of the signer does not match its verification share.
+    InvalidShare { signer: u32 },
+    /// There is no signature share of the signer.
+    MissingShare { signer: u32 },
+}
+
+/// The public information about a shared key, which signers and whoever aggregates the
+/// signature shares need.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublicKeyPackage<G: PrimeOrderGroup> {
+    group: G,
+    threshold: usize,
+    public_key: G::Element,
+    /// The verification share `g^s_i` of each party.
+    verification_shares: BTreeMap<u32, G::Element>,
+}
+
+impl<G: PrimeOrderGroup> PublicKeyPackage<G> {
+    pub fn new(
+        group: G,
+        threshold: usize,
+        public_key: G::Element,
+        verification_shares: BTreeMap<u32, G::Element>,
+    ) -> Self {
+        PublicKeyPackage {
+            group,
+            threshold,
+            public_key,
+            verification_shares,
+        }
+    }
+
+    pub fn group(&self) -> &G {
+        &self.group
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The key the signatures are valid under.
+    pub fn public_key(&self) -> &G::Element {
+        &self.public_key
+    }
+
+    /// Check the signature share of `share.index()` for `package`.
+    pub fn verify_share(
+        &self,
+        package: &SigningPackage<G::Element>,
+        share: &SignatureShare,
+    ) -> Result<(), FrostError> {
+        let state = self.signing_state(package)?;
+        self.check_share(&state, package, share)
+    }
+
+    /// Check the signature shares of all signers in `package`, and combine them into a
+    /// signature.
+    pub fn aggregate(
+        &self,
+        package: &SigningPackage<G::Element>,
+        shares: &[SignatureShare],
+    ) -> Result<Signature<G::Element>, FrostError> {
+        let state = self.signing_state(package)?;
+        let mut z = BigInt::zero();
+        for &signer in package.commitments.keys() {
+            let share = shares
+                .iter()
+                .find(|s| s.index == signer)
+                .ok_or(FrostError::MissingShare { signer })?;
+            self.check_share(&state, package, share)?;
+            z = (z + &share.z).mod_floor(self.group.order());
+        }
+        Ok(Signature::new(state.group_commitment, z))
+    }
+
+    fn check_share(
+        &self,
+        state: &SigningState<G::Element>,
+        package: &SigningPackage<G::Element>,
+        share: &SignatureShare,
+    ) -> Result<(), FrostError> {
+        let signer = share.index;
+        let commitment = package
+            .commitments
+            .get(&signer)
+            .ok_or(FrostError::MissingCommitment(signer))?;
+        let verification_share = &self.verification_shares[&signer];
+
+        // g^z_i = D_i E_i^rho_i Y_i^(lambda_i c)
+        let group = &self.group;
+        let lhs = group.exp(&group.generator(), &share.z);
+        let rhs = group.multi_exp(
+            &[
+                commitment.hiding.clone(),
+                commitment.binding.clone(),
+                verification_share.clone(),
+            ],
+            &[
+                BigInt::from(1),
+                state.binding_factors[&signer].clone(),
+                &state.lagrange[&signer] * &state.challenge,
+            ],
+        );
+        if lhs != rhs {
+            return Err(FrostError::InvalidShare { signer });
+        }
+        Ok(())
+    }
+
+    /// The binding factors, Lagrange coefficients, group commitment and challenge for
+    /// `package`, after checking it.
+    fn signing_state(
+        &self,
+        package: &SigningPackage<G::Element>,
+    ) -> Result<SigningState<G::Element>, FrostError> {
+        let group = &self.group;
+        let signers = package.commitments.keys().copied().collect::<Vec<_>>();
+        if signers.len() < self.threshold {
+            return Err(FrostError::NotEnoughSigners {
+                needed: self.threshold,
+                actual: signers.len(),
+            });
+        }
+        for (&signer, commitment) in &package.commitments {
+            if !self.verification_shares.contains_key(&signer) {
+                return Err(FrostError::UnknownSigner(signer));
+            }
+            if !group.is_element(&commitment.hiding) || !group.is_element(&commitment.binding) {
+                return Err(FrostError::InvalidCommitment { signer });
+            }
+        }
+
+        let mut transcript = Transcript::new(b"frost binding factors");
+        transcript.append_value(b"public key", &self.public_key);
+        transcript.append(b"message", &package.message);
+        transcript.append_value(b"commitments", &package.commitments);
+        let bits = group.order().bits() + 128;
+        let binding_factors = signers
+            .iter()
+            .map(|&i| {
+                let mut transcript = transcript.clone();
+                transcript.append(b"signer", &i.to_be_bytes());
+                let rho = transcript.challenge(b"binding factor", bits) % group.order();
+                (i, rho)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let (bases, exponents): (Vec<_>, Vec<_>) = package
+            .commitments
+            .iter()
+            .flat_map(|(i, commitment)| {
+                [
+                    (commitment.hiding.clone(), BigInt::from(1)),
+                    (commitment.binding.clone(), binding_factors[i].clone()),
+                ]
+            })
+            .unzip();
+        let group_commitment = group.multi_exp(&bases, &exponents);
+        let challenge = challenge(group, &group_commitment, &self.public_key, &package.message);
+        let lagrange = signers
+            .iter()
+            .map(|&i| (i, lagrange_coefficient(group.order(), i, &signers)))
+            .collect();
+        Ok(SigningState {
+            binding_factors,
+            lagrange,
+            group_commitment,
+            challenge,
+        })
+    }
+}
+
+impl PublicKeyPackage<SchnorrGroup> {
+    /// The public information about the key from the DKG.
+    pub fn from_key_share(key: &KeyShare) -> Self {
+        let public_key = key.public_key();
+        let verification_shares = (1..=key.parties())
+            .map(|i| (i, key.verification_share(i)))
+            .collect();
+        Self::new(
+            public_key.group(),
+            key.threshold(),
+            public_key.h().clone(),
+            verification_shares,
+        )
+    }
+}
+
+struct SigningState<E> {
+    binding_factors: BTreeMap<u32, BigInt>,
+    lagrange: BTreeMap<u32, BigInt>,
+    group_commitment: E,
+    challenge: BigInt,
+}
+
+/// The nonces of one signing session. They are consumed by [`SignerKey::sign`], as signing
+/// twice with the same nonces reveals the share of the key.
+pub struct SigningNonces {
+    hiding: Secret<BigInt>,
+    binding: Secret<BigInt>,
+}
+
+/// The commitments `(D_i, E_i)` to the nonces of a signer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NonceCommitment<E> {
+    hiding: E,
+    binding: E,
+}
+
+/// What the signers sign: the message, and the nonce commitments of every signer by index.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigningPackage<E> {
+    message: Vec<u8>,
+    commitments: BTreeMap<u32, NonceCommitment<E>>,
+}
+
+impl<E> SigningPackage<E> {
+    pub fn new(message: Vec<u8>, commitments: BTreeMap<u32, NonceCommitment<E>>) -> Self {
+        SigningPackage {
+            message,
+            commitments,
+        }
+    }
+
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    pub fn signers(&self) -> Vec<u32> {
+        self.commitments.keys().copied().collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignatureShare {
+    index: u32,
+    z: BigInt,
+}
+
+impl SignatureShare {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// The share of a key of one signer.
+#[derive(Clone)]
+pub struct SignerKey<G: PrimeOrderGroup> {
+    index: u32,
+    secret_share: Secret<BigInt>,
+    public: PublicKeyPackage<G>,
+}
+
+impl<G: PrimeOrderGroup> SignerKey<G> {
+    pub fn new(index: u32, secret_share: Secret<BigInt>, public: PublicKeyPackage<G>) -> Self {
+        SignerKey {
+            index,
+            secret_share,
+            public,
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn public(&self) -> &PublicKeyPackage<G> {
+        &self.public
+    }
+
+    /// The first round: new nonces, and the commitment to publish.
+    pub fn commit(&self) -> (SigningNonces, NonceCommitment<G::Element>) {
+        let group = &self.public.group;
+        let nonces = SigningNonces {
+            hiding: Secret::new(group.random_scalar()),
+            binding: Secret::new(group.random_scalar()),
+        };
+        let g = group.generator();
+        let commitment = NonceCommitment {
+            hiding: group.exp_secret(&g, nonces.hiding.expose()),
+            binding: group.exp_secret(&g, nonces.binding.expose()),
+        };
+        (nonces, commitment)
+    }
+
+    /// The second round: our signature share for `package`.
+    pub fn sign(
+        &self,
+        nonces: SigningNonces,
+        package: &SigningPackage<G::Element>,
+    ) -> Result<SignatureShare, FrostError> {
+        let group = &self.public.group;
+        let commitment = package
+            .commitments
+            .get(&self.index)
+            .ok_or(FrostError::MissingCommitment(self.index))?;
+        let g = group.generator();
+        if commitment.hiding != group.exp_secret(&g, nonces.hiding.expose())
+            || commitment.binding != group.exp_secret(&g, nonces.binding.expose())
+        {
+            return Err(FrostError::WrongNonces);
+        }
+
+        let state = self.public.signing_state(package)?;
+        // `d + e rho + lambda s c`, as two responses.
+        let nonce = group.response(
+            nonces.hiding.expose(),
+            &state.binding_factors[&self.index],
+            nonces.binding.expose(),
+        );
+        let challenge = &state.lagrange[&self.index] * state.challenge;
+        Ok(SignatureShare {
+            index: self.index,
+            z: group.response(&nonce, &challenge, self.secret_share.expose()),
+        })
+    }
+}
+
+impl SignerKey<SchnorrGroup> {
+    /// Our key from the DKG.
+    pub fn from_key_share(key: &KeyShare) -> Self {
+        Self::new(
+            key.index(),
+            Secret::new(key.secret_share().clone()),
+            PublicKeyPackage::from_key_share(key),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FrostMessage<E> {
+    Commitment(NonceCommitment<E>),
+    Share(SignatureShare),
+}
+
+impl<E: BinaryEncode> BinaryEncode for FrostMessage<E> {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            FrostMessage::Commitment(commitment) => {
+                out.push(0);
+                commitment.hiding.encode(int_len, out)?;
+                commitment.binding.encode(int_len, out)
+            }
+            FrostMessage::Share(share) => {
+                out.push(1);
+                share.index.encode(int_len, out)?;
+                share.z.encode(int_len, out)
+            }
+        }
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let [tag] = <[u8; 1]>::decode(int_len, input)?;
+        match tag {
+            0 => {
+                let (hiding, binding) = BinaryEncode::decode(int_len, input)?;
+                Ok(FrostMessage::Commitment(NonceCommitment {
+                    hiding,
+                    binding,
+                }))
+            }
+            1 => {
+                let (index, z) = BinaryEncode::decode(int_len, input)?;
+                Ok(FrostMessage::Share(SignatureShare { index, z }))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown message tag",
+            )),
+        }
+    }
+}
+
+enum SignerState<E> {
+    Commit,
+    Sign(SigningNonces, NonceCommitment<E>),
+    Aggregate(SigningPackage<E>, SignatureShare),
+    Done,
+}
+
+/// One signer of a session without a coordinator, as a [`RoundParty`]. Every signer sends its
+/// commitment and then its share to all others, and aggregates the signature itself.
+pub struct FrostSigner<G: PrimeOrderGroup> {
+    key: SignerKey<G>,
+    signers: Vec<u32>,
+    message: Vec<u8>,
+    state: SignerState<G::Element>,
+}
+
+impl<G: PrimeOrderGroup> FrostSigner<G> {
+    /// Sign `message` together with `signers`, which includes us.
+    pub fn new(key: SignerKey<G>, signers: Vec<u32>, message: Vec<u8>) -> Self {
+        FrostSigner {
+            key,
+            signers,
+            message,
+            state: SignerState::Commit,
+        }
+    }
+
+    fn to_others(&self, message: FrostMessage<G::Element>) -> Vec<(u32, FrostMessage<G::Element>)> {
+        self.signers
+            .iter()
+            .filter(|&&i| i != self.key.index)
+            .map(|&i| (i, message.clone()))
+            .collect()
+    }
+}
+
+impl<G: PrimeOrderGroup> RoundParty for FrostSigner<G> {
+    type Message = FrostMessage<G::Element>;
+    type Output = Result<Signature<G::Element>, FrostError>;
+
+    fn index(&self) -> u32 {
+        self.key.index
+    }
+
+    fn next_round(
+        &mut self,
+        received: Vec<(u32, Self::Message)>,
+    ) -> Round<Self::Message, Self::Output> {
+        match mem::replace(&mut self.state, SignerState::Done) {
+            SignerState::Commit => {
+                let (nonces, commitment) = self.key.commit();
+                let messages = self.to_others(FrostMessage::Commitment(commitment.clone()));
+                self.state = SignerState::Sign(nonces, commitment);
+                Round::Send(messages, IntKind::Element)
+            }
+            SignerState::Sign(nonces, commitment) => {
+                let mut commitments = BTreeMap::new();
+                commitments.insert(self.key.index, commitment);
+                for (from, message) in received {
+                    // Another key holder joining in would change the coefficients of everyone.
+                    if !self.signers.contains(&from) {
+                        return Round::Done(Err(FrostError::UnexpectedSigner(from)));
+                    }
+                    match message {
+                        FrostMessage::Commitment(c) => {
+                            commitments.insert(from, c);
+                        }
+                        FrostMessage::Share(_) => {
+                            return Round::Done(Err(FrostError::MissingCommitment(from)))
+                        }
+                    }
+                }
+                if let Some(&missing) = self.signers.iter().find(|i| !commitments.contains_key(i)) {
+                    return Round::Done(Err(FrostError::MissingCommitment(missing)));
+                }
+
+                let package = SigningPackage::new(mem::take(&mut self.message), commitments);
+                match self.key.sign(nonces, &package) {
+                    Ok(share) => {
+                        let messages = self.to_others(FrostMessage::Share(share.clone()));
+                        self.state = SignerState::Aggregate(package, share);
+                        Round::Send(messages, IntKind::Scalar)
+                    }
+                    Err(e) => Round::Done(Err(e)),
+                }
+            }
+            SignerState::Aggregate(package, share) => {
+                let mut shares = vec![share];
+                shares.extend(received.into_iter().filter_map(|(_, m)| match m {
+                    FrostMessage::Share(share) => Some(share),
+                    FrostMessage::Commitment(_) => None,
+                }));
+                Round::Done(self.key.public.aggregate(&package, &shares))
+            }
+            SignerState::Done => panic!("The signing session is already done."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use num::BigInt;
+
+    use crate::{
+        applications::{
+            dkg::simulate,
+            signatures::{verify, Signature},
+        },
+        driver::{run_rounds_local, Round, RoundParty},
+        group::SchnorrGroup,
+        schnorr::SchnorrDiscreteLogInstance,
+    };
+
+    use super::{FrostError, FrostMessage, FrostSigner, SignatureShare, SignerKey, SigningPackage};
+
+    type Output = Result<Signature<BigInt>, FrostError>;
+
+    fn make_keys(threshold: usize, parties: u32) -> Vec<SignerKey<SchnorrGroup>> {
+        let params = SchnorrDiscreteLogInstance::generate(256, 64).0;
+        simulate(&params, threshold, parties)
+            .unwrap()
+            .iter()
+            .map(SignerKey::from_key_share)
+            .collect()
+    }
+
+    #[test]
+    fn signers_produce_ordinary_signature() {
+        let keys = make_keys(3, 5);
+        let signers = vec![1, 3, 4];
+        let mut parties = signers
+            .iter()
+            .map(|&i| {
+                FrostSigner::new(
+                    keys[i as usize - 1].clone(),
+                    signers.clone(),
+                    b"hi".to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut parties = parties
+            .iter_mut()
+            .map(|p| p as &mut dyn RoundParty<Message = FrostMessage<BigInt>, Output = Output>)
+            .collect::<Vec<_>>();
+
+        let public = keys[0].public();
+        for signature in run_rounds_local(&mut parties) {
+            let signature = signature.unwrap();
+            assert_eq!(
+                verify(public.group(), public.public_key(), b"hi", &signature),
+                Ok(())
+            );
+            assert!(verify(public.group(), public.public_key(), b"bye", &signature).is_err());
+        }
+    }
+
+    #[test]
+    fn misbehaving_signer_is_detected() {
+        let keys = make_keys(2, 3);
+        let (nonces, commitments): (Vec<_>, BTreeMap<_, _>) = [1, 3]
+            .iter()
+            .map(|&i| {
+                let (nonces, commitment) = keys[i - 1].commit();
+                (nonces, (i as u32, commitment))
+            })
+            .unzip();
+        let package = SigningPackage::new(b"message".to_vec(), commitments);
+        let mut shares = nonces
+            .into_iter()
+            .zip([0, 2])
+            .map(|(nonces, i)| keys[i].sign(nonces, &package).unwrap())
+            .collect::<Vec<_>>();
+
+        let public = keys[1].public();
+        assert!(public.aggregate(&package, &shares).is_ok());
+
+        shares[1] = SignatureShare {
+            index: 3,
+            z: &shares[1].z + 1,
+        };
+        assert_eq!(
+            public.verify_share(&package, &shares[1]),
+            Err(FrostError::InvalidShare { signer: 3 })
+        );
+        assert_eq!(
+            public.aggregate(&package, &shares),
+            Err(FrostError::InvalidShare { signer: 3 })
+        );
+        assert_eq!(
+            public.aggregate(&package, &shares[..1]),
+            Err(FrostError::MissingShare { signer: 3 })
+        );
+    }
+
+    #[test]
+    fn commitment_from_outside_the_signers_is_rejected() {
+        let keys = make_keys(2, 3);
+        let mut signer = FrostSigner::new(keys[0].clone(), vec![1, 2], b"message".to_vec());
+        assert!(matches!(signer.next_round(vec![]), Round::Send(..)));
+
+        let received = [2, 3]
+            .map(|i| (i, FrostMessage::Commitment(keys[i as usize - 1].commit().1)))
+            .to_vec();
+        assert!(matches!(
+            signer.next_round(received),
+            Round::Done(Err(FrostError::UnexpectedSigner(3)))
+        ));
+    }
+
+    #[test]
+    fn too_few_signers_are_rejected() {
+        let keys = make_keys(2, 3);
+        let (nonces, commitment) = keys[0].commit();
+        let package = SigningPackage::new(b"message".to_vec(), [(1, commitment)].into());
+        assert_eq!(
+            keys[0].sign(nonces, &package),
+            Err(FrostError::NotEnoughSigners {
+                needed: 2,
+                actual: 1
+            })
+        );
+
+        let (nonces, _) = keys[0].commit();
+        let (_, other) = keys[1].commit();
+        let (_, mine) = keys[0].commit();
+        let package = SigningPackage::new(b"message".to_vec(), [(1, mine), (2, other)].into());
+        assert_eq!(keys[0].sign(nonces, &package), Err(FrostError::WrongNonces));
+    }
+}
@@ -1 +1,14 @@
+pub mod bit_proof;
+pub mod blind_signatures;
+pub mod bulletproofs;
 pub mod commitments;
+pub mod credentials;
+pub mod dkg;
+pub mod elgamal;
+pub mod frost;
+pub mod musig;
+pub mod range_proof;
+pub mod shuffle;
+pub mod signatures;
+pub mod voting;
+pub mod vss;
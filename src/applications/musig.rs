@@ -0,0 +1,560 @@
+//! Multi-signatures with key aggregation, following [MuSig2](https://eprint.iacr.org/2020/1261).
+//!
+//! `n` signers with keys `X_1, ..., X_n` make one signature under the aggregated key
+//! `X = prod X_i^a_i`, which is checked with [`signatures::verify`](super::signatures::verify)
+//! like any other. The coefficients `a_i` are hashes of all keys and `X_i`, so that a signer
+//! cannot pick its key as a function of the others' to control the aggregated key.
+//!
+//! Signing takes two rounds:
+//!
+//! 1. Each signer picks two nonces `r_i1` and `r_i2`, and publishes `R_i1 = g^r_i1` and
+//!    `R_i2 = g^r_i2`. This can be done before the message is known.
+//! 2. With the products `R_1` and `R_2` of the nonces of all signers, each signer computes
+//!    `b = H(X, R_1, R_2, m)`, the nonce `R = R_1 R_2^b` and the challenge `c`, and sends the
+//!    partial signature `s_i = r_i1 + b r_i2 + c a_i x_i`.
+//!
+//! The signature is `(R, sum s_i)`. As in [`frost`](super::frost), the second nonce keeps
+//! concurrent sessions secure, and nonces must never be reused, so [`SecretNonce`]s are
+//! consumed when signing.
+
+use std::{io, mem};
+
+use num::{BigInt, Integer, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::signatures::{challenge, Signature},
+    driver::{Round, RoundParty},
+    group::PrimeOrderGroup,
+    netutil::{BinaryEncode, IntKind},
+    secret::Secret,
+    transcript::Transcript,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum MuSigError {
+    NoKeys,
+    /// The key at `index` is not an element of the group.
+    InvalidKey {
+        index: usize,
+    },
+    /// The public key of the secret key is not one of the keys.
+    KeyNotInList,
+    /// There is not one nonce or partial signature for every signer.
+    WrongLength {
+        expected: usize,
+        actual: usize,
+    },
+    /// The nonces are not the ones we published.
+    WrongNonces,
+    /// A nonce of the signer is not an element of the group.
+    InvalidNonce {
+        signer: usize,
+    },
+    /// The partial signature of the signer does not match its key.
+    InvalidPartialSignature {
+        signer: usize,
+    },
+}
+
+/// The keys of the signers, with their coefficients and the aggregated key.
+#[derive(Clone, Debug)]
+pub struct KeyAggContext<G: PrimeOrderGroup> {
+    group: G,
+    keys: Vec<G::Element>,
+    coefficients: Vec<BigInt>,
+    aggregated_key: G::Element,
+}
+
+impl<G: PrimeOrderGroup> KeyAggContext<G> {
+    /// Aggregate `keys`. Every signer must use the keys in the same order.
+    pub fn new(group: G, keys: Vec<G::Element>) -> Result<Self, MuSigError> {
+        if keys.is_empty() {
+            return Err(MuSigError::NoKeys);
+        }
+        if let Some(index) = keys.iter().position(|x| !group.is_element(x)) {
+            return Err(MuSigError::InvalidKey { index });
+        }
+
+        let mut transcript = Transcript::new(b"musig key aggregation");
+        transcript.append_value(b"keys", &keys);
+        let bits = group.order().bits() + 128;
+        let coefficients = keys
+            .iter()
+            .map(|x| {
+                let mut transcript = transcript.clone();
+                transcript.append_value(b"key", x);
+                transcript.challenge(b"coefficient", bits) % group.order()
+            })
+            .collect::<Vec<_>>();
+        let aggregated_key = group.multi_exp(&keys, &coefficients);
+        Ok(KeyAggContext {
+            group,
+            keys,
+            coefficients,
+            aggregated_key,
+        })
+    }
+
+    pub fn group(&self) -> &G {
+        &self.group
+    }
+
+    pub fn keys(&self) -> &[G::Element] {
+        &self.keys
+    }
+
+    /// The key the signatures are valid under.
+    pub fn aggregated_key(&self) -> &G::Element {
+        &self.aggregated_key
+    }
+
+    /// Check the partial signature of the signer at `signer` for `message`, where `nonces` are
+    /// the public nonces of all signers.
+    ///
+    /// # Panics
+    ///
+    /// If there is no key at `signer`.
+    pub fn verify_partial(
+        &self,
+        nonces: &[PublicNonce<G::Element>],
+        message: &[u8],
+        signer: usize,
+        partial: &PartialSignature,
+    ) -> Result<(), MuSigError> {
+        let state = self.signing_state(nonces, message)?;
+        self.check_partial(&state, nonces, signer, partial)
+    }
+
+    /// Check the partial signatures of all signers, in the order of their keys, and combine
+    /// them into a signature.
+    pub fn aggregate(
+        &self,
+        nonces: &[PublicNonce<G::Element>],
+        message: &[u8],
+        partials: &[PartialSignature],
+    ) -> Result<Signature<G::Element>, MuSigError> {
+        let state = self.signing_state(nonces, message)?;
+        check_length(self.keys.len(), partials.len())?;
+        let mut s = BigInt::zero();
+        for (signer, partial) in partials.iter().enumerate() {
+            self.check_partial(&state, nonces, signer, partial)?;
+            s = (s + &partial.s).mod_floor(self.group.order());
+        }
+        Ok(Signature::new(state.nonce, s))
+    }
+
+    fn check_partial(
+        &self,
+        state: &SigningState<G::Element>,
+        nonces: &[PublicNonce<G::Element>],
+        signer: usize,
+        partial: &PartialSignature,
+    ) -> Result<(), MuSigError> {
+        // g^s_i = R_i1 R_i2^b X_i^(c a_i)
+        let group = &self.group;
+        let nonce = &nonces[signer];
+        let lhs = group.exp(&group.generator(), &partial.s);
+        let rhs = group.multi_exp(
+            &[
+                nonce.r1.clone(),
+                nonce.r2.clone(),
+                self.keys[signer].clone(),
+            ],
+            &[
+                BigInt::from(1),
+                state.b.clone(),
+                &state.challenge * &self.coefficients[signer],
+            ],
+        );
+        if lhs != rhs {
+            return Err(MuSigError::InvalidPartialSignature { signer });
+        }
+        Ok(())
+    }
+
+    /// The nonce coefficient `b`, the nonce `R` and the challenge, after checking the nonces.
+    fn signing_state(
+        &self,
+        nonces: &[PublicNonce<G::Element>],
+        message: &[u8],
+    ) -> Result<SigningState<G::Element>, MuSigError> {
+        let group = &self.group;
+        check_length(self.keys.len(), nonces.len())?;
+        if let Some(signer) = nonces
+            .iter()
+            .position(|n| !group.is_element(&n.r1) || !group.is_element(&n.r2))
+        {
+            return Err(MuSigError::InvalidNonce { signer });
+        }
+
+        let (r1, r2) = nonces
+            .iter()
+            .fold((group.identity(), group.identity()), |(r1, r2), n| {
+                (group.mul(&r1, &n.r1), group.mul(&r2, &n.r2))
+            });
+        let mut transcript = Transcript::new(b"musig nonce coefficient");
+        transcript.append_value(b"aggregated key", &self.aggregated_key);
+        transcript.append_value(b"nonces", &(&r1, &r2));
+        transcript.append(b"message", message);
+        let b = transcript.challenge(b"b", group.order().bits() + 128) % group.order();
+
+        let nonce = group.mul(&r1, &group.exp(&r2, &b));
+        let challenge = challenge(group, &nonce, &self.aggregated_key, message);
+        Ok(SigningState {
+            b,
+            nonce,
+            challenge,
+        })
+    }
+}
+
+struct SigningState<E> {
+    b: BigInt,
+    nonce: E,
+    challenge: BigInt,
+}
+
+fn check_length(expected: usize, actual: usize) -> Result<(), MuSigError> {
+    if expected != actual {
+        return Err(MuSigError::WrongLength { expected, actual });
+    }
+    Ok(())
+}
+
+/// The nonces of one signing session. They are consumed by [`MuSigSigner::sign`], as signing
+/// twice with the same nonces reveals the secret key.
+pub struct SecretNonce {
+    r1: Secret<BigInt>,
+    r2: Secret<BigInt>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublicNonce<E> {
+    r1: E,
+    r2: E,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    s: BigInt,
+}
+
+/// One signer, with its secret key.
+#[derive(Clone)]
+pub struct MuSigSigner<G: PrimeOrderGroup> {
+    context: KeyAggContext<G>,
+    index: usize,
+    secret_key: Secret<BigInt>,
+}
+
+impl<G: PrimeOrderGroup> MuSigSigner<G> {
+    /// The signer with `secret_key`, whose public key must be one of the keys of `context`.
+    pub fn new(context: KeyAggContext<G>, secret_key: Secret<BigInt>) -> Result<Self, MuSigError> {
+        let group = &context.group;
+        let public_key = group.exp_secret(&group.generator(), secret_key.expose());
+        let index = context
+            .keys
+            .iter()
+            .position(|x| *x == public_key)
+            .ok_or(MuSigError::KeyNotInList)?;
+        Ok(MuSigSigner {
+            context,
+            index,
+            secret_key,
+        })
+    }
+
+    /// Our position among the keys.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn context(&self) -> &KeyAggContext<G> {
+        &self.context
+    }
+
+    /// The first round: new nonces, and the public nonces to send to the others.
+    pub fn commit(&self) -> (SecretNonce, PublicNonce<G::Element>) {
+        let group = &self.context.group;
+        let secret = SecretNonce {
+            r1: Secret::new(group.random_scalar()),
+            r2: Secret::new(group.random_scalar()),
+        };
+        let g = group.generator();
+        let public = PublicNonce {
+            r1: group.exp_secret(&g, secret.r1.expose()),
+            r2: group.exp_secret(&g, secret.r2.expose()),
+        };
+        (secret, public)
+    }
+
+    /// The second round: our partial signature on `message`, where `nonces` are the public
+    /// nonces of all signers in the order of their keys.
+    pub fn sign(
+        &self,
+        secret: SecretNonce,
+        nonces: &[PublicNonce<G::Element>],
+        message: &[u8],
+    ) -> Result<PartialSignature, MuSigError> {
+        let group = &self.context.group;
+        let g = group.generator();
+        let ours = nonces.get(self.index).ok_or(MuSigError::WrongLength {
+            expected: self.context.keys.len(),
+            actual: nonces.len(),
+        })?;
+        if ours.r1 != group.exp_secret(&g, secret.r1.expose())
+            || ours.r2 != group.exp_secret(&g, secret.r2.expose())
+        {
+            return Err(MuSigError::WrongNonces);
+        }
+
+        let state = self.context.signing_state(nonces, message)?;
+        // `r1 + b r2 + c a x`, as two responses.
+        let nonce = group.response(secret.r1.expose(), &state.b, secret.r2.expose());
+        let challenge = state.challenge * &self.context.coefficients[self.index];
+        Ok(PartialSignature {
+            s: group.response(&nonce, &challenge, self.secret_key.expose()),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MuSigMessage<E> {
+    Nonce(PublicNonce<E>),
+    PartialSignature(PartialSignature),
+}
+
+impl<E: BinaryEncode> BinaryEncode for MuSigMessage<E> {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            MuSigMessage::Nonce(nonce) => {
+                out.push(0);
+                nonce.r1.encode(int_len, out)?;
+                nonce.r2.encode(int_len, out)
+            }
+            MuSigMessage::PartialSignature(partial) => {
+                out.push(1);
+                partial.s.encode(int_len, out)
+            }
+        }
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let [tag] = <[u8; 1]>::decode(int_len, input)?;
+        match tag {
+            0 => {
+                let (r1, r2) = BinaryEncode::decode(int_len, input)?;
+                Ok(MuSigMessage::Nonce(PublicNonce { r1, r2 }))
+            }
+            1 => Ok(MuSigMessage::PartialSignature(PartialSignature {
+                s: BigInt::decode(int_len, input)?,
+            })),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown message tag",
+            )),
+        }
+    }
+}
+
+enum PartyState<E> {
+    Commit,
+    Sign(SecretNonce, PublicNonce<E>),
+    Aggregate(Vec<PublicNonce<E>>, PartialSignature),
+    Done,
+}
+
+/// One signer of a session, as a [`RoundParty`]. The signer at position `i` among the keys has
+/// the index `i + 1`. Every signer aggregates the signature itself.
+pub struct MuSigParty<G: PrimeOrderGroup> {
+    signer: MuSigSigner<G>,
+    message: Vec<u8>,
+    state: PartyState<G::Element>,
+}
+
+impl<G: PrimeOrderGroup> MuSigParty<G> {
+    pub fn new(signer: MuSigSigner<G>, message: Vec<u8>) -> Self {
+        MuSigParty {
+            signer,
+            message,
+            state: PartyState::Commit,
+        }
+    }
+
+    fn to_others(&self, message: MuSigMessage<G::Element>) -> Vec<(u32, MuSigMessage<G::Element>)> {
+        (1..=self.signer.context.keys.len() as u32)
+            .filter(|&i| i != self.index())
+            .map(|i| (i, message.clone()))
+            .collect()
+    }
+
+    /// The messages of the others with `ours` in between, in the order of the keys.
+    fn in_order<T>(&self, ours: T, received: Vec<(u32, T)>) -> Vec<T> {
+        let mut all = received;
+        all.push((self.index(), ours));
+        all.sort_by_key(|(i, _)| *i);
+        all.into_iter().map(|(_, m)| m).collect()
+    }
+}
+
+impl<G: PrimeOrderGroup> RoundParty for MuSigParty<G> {
+    type Message = MuSigMessage<G::Element>;
+    type Output = Result<Signature<G::Element>, MuSigError>;
+
+    fn index(&self) -> u32 {
+        self.signer.index as u32 + 1
+    }
+
+    fn next_round(
+        &mut self,
+        received: Vec<(u32, Self::Message)>,
+    ) -> Round<Self::Message, Self::Output> {
+        match mem::replace(&mut self.state, PartyState::Done) {
+            PartyState::Commit => {
+                let (secret, public) = self.signer.commit();
+                let messages = self.to_others(MuSigMessage::Nonce(public.clone()));
+                self.state = PartyState::Sign(secret, public);
+                Round::Send(messages, IntKind::Element)
+            }
+            PartyState::Sign(secret, public) => {
+                let received = received
+                    .into_iter()
+                    .filter_map(|(i, m)| match m {
+                        MuSigMessage::Nonce(nonce) => Some((i, nonce)),
+                        MuSigMessage::PartialSignature(_) => None,
+                    })
+                    .collect();
+                let nonces = self.in_order(public, received);
+                match self.signer.sign(secret, &nonces, &self.message) {
+                    Ok(partial) => {
+                        let messages =
+                            self.to_others(MuSigMessage::PartialSignature(partial.clone()));
+                        self.state = PartyState::Aggregate(nonces, partial);
+                        Round::Send(messages, IntKind::Scalar)
+                    }
+                    Err(e) => Round::Done(Err(e)),
+                }
+            }
+            PartyState::Aggregate(nonces, partial) => {
+                let received = received
+                    .into_iter()
+                    .filter_map(|(i, m)| match m {
+                        MuSigMessage::PartialSignature(partial) => Some((i, partial)),
+                        MuSigMessage::Nonce(_) => None,
+                    })
+                    .collect();
+                let partials = self.in_order(partial, received);
+                Round::Done(
+                    self.signer
+                        .context
+                        .aggregate(&nonces, &self.message, &partials),
+                )
+            }
+            PartyState::Done => panic!("The signing session is already done."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::signatures::{generate_key, verify, Signature},
+        driver::{run_rounds_local, RoundParty},
+        group::{PrimeOrderGroup, SchnorrGroup},
+        schnorr::SchnorrDiscreteLogInstance,
+    };
+
+    use super::{
+        KeyAggContext, MuSigError, MuSigMessage, MuSigParty, MuSigSigner, PartialSignature,
+    };
+
+    type Output = Result<Signature<BigInt>, MuSigError>;
+
+    fn make_signers(n: usize) -> Vec<MuSigSigner<SchnorrGroup>> {
+        let group = SchnorrDiscreteLogInstance::generate(256, 64).0.group();
+        let (secrets, keys): (Vec<_>, Vec<_>) = (0..n).map(|_| generate_key(&group)).unzip();
+        let context = KeyAggContext::new(group, keys).unwrap();
+        secrets
+            .into_iter()
+            .map(|x| MuSigSigner::new(context.clone(), x).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn signers_produce_ordinary_signature() {
+        let signers = make_signers(3);
+        let context = signers[0].context().clone();
+        let mut parties = signers
+            .into_iter()
+            .map(|s| MuSigParty::new(s, b"message".to_vec()))
+            .collect::<Vec<_>>();
+        let mut parties = parties
+            .iter_mut()
+            .map(|p| p as &mut dyn RoundParty<Message = MuSigMessage<BigInt>, Output = Output>)
+            .collect::<Vec<_>>();
+
+        let key = context.aggregated_key();
+        for signature in run_rounds_local(&mut parties) {
+            let signature = signature.unwrap();
+            assert_eq!(verify(context.group(), key, b"message", &signature), Ok(()));
+            assert!(verify(context.group(), key, b"other", &signature).is_err());
+        }
+    }
+
+    #[test]
+    fn invalid_partial_signature_is_detected() {
+        let signers = make_signers(2);
+        let context = signers[0].context();
+        let (secrets, nonces): (Vec<_>, Vec<_>) = signers.iter().map(|s| s.commit()).unzip();
+        let mut partials = signers
+            .iter()
+            .zip(secrets)
+            .map(|(s, secret)| s.sign(secret, &nonces, b"message").unwrap())
+            .collect::<Vec<_>>();
+        assert!(context.aggregate(&nonces, b"message", &partials).is_ok());
+
+        partials[1] = PartialSignature {
+            s: &partials[1].s + 1,
+        };
+        assert_eq!(
+            context.verify_partial(&nonces, b"message", 1, &partials[1]),
+            Err(MuSigError::InvalidPartialSignature { signer: 1 })
+        );
+        assert_eq!(
+            context.aggregate(&nonces, b"message", &partials),
+            Err(MuSigError::InvalidPartialSignature { signer: 1 })
+        );
+        assert_eq!(
+            context.aggregate(&nonces, b"message", &partials[..1]),
+            Err(MuSigError::WrongLength {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn rogue_key_does_not_control_aggregated_key() {
+        let group = SchnorrDiscreteLogInstance::generate(256, 64).0.group();
+        let (_, honest) = generate_key(&group);
+
+        // Without coefficients, the product of the keys would be g^y.
+        let (_, target) = generate_key(&group);
+        let rogue = group.mul(&target, &group.inverse(&honest));
+        let context = KeyAggContext::new(group.clone(), vec![honest.clone(), rogue]).unwrap();
+        assert_ne!(context.aggregated_key(), &target);
+
+        let (x, _) = generate_key(&group);
+        assert!(matches!(
+            MuSigSigner::new(context, x),
+            Err(MuSigError::KeyNotInList)
+        ));
+        assert_eq!(
+            KeyAggContext::new(group.clone(), vec![honest, group.modulus() - 1]).unwrap_err(),
+            MuSigError::InvalidKey { index: 1 }
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Proofs that a Pedersen commitment hides a value in a range, without opening it.
+//!
+//! To show that `C = g^v h^r` hides a value in `[0, 2^n)`, the prover commits to each bit `b_i`
+//! of `v` as `C_i = g^b_i h^r_i`, and proves with a [`BitProof`] that each of them hides a bit.
+//! The randomness is chosen so that `C_0^1 C_1^2 ... C_(n-1)^(2^(n-1)) = C`, which the verifier
+//! checks. For `[a, b]`, the prover shows that both `v - a` and `b - v` are in `[0, 2^n)`.
+
+use std::io;
+
+use num::{bigint::Sign, BigInt, Integer, One, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::{
+        bit_proof::{BitInstance, BitProof, BitVerifierError},
+        commitments::PedersenScheme,
+    },
+    netutil::BinaryEncode,
+    secret::Secret,
+};
+
+#[derive(Debug)]
+pub enum RangeProofError {
+    /// The prover's value is not in the range.
+    ValueOutOfRange,
+    /// The range has `bits` bits, but the group only allows ranges of up to `max` bits.
+    TooManyBits { bits: usize, max: usize },
+    /// A range of zero bits has no bit proofs, so a proof for it could never be verified.
+    NoBits,
+    /// The lower bound of the range is above the upper bound.
+    EmptyRange,
+    /// The commitment is not an element of the group.
+    InvalidCommitment,
+    /// The proof does not have one bit commitment and one bit proof for each bit.
+    WrongLength { expected: usize, actual: usize },
+    /// The bit commitments do not recombine into the commitment.
+    BitsDoNotMatchCommitment,
+    /// The bit proof at `index` was rejected.
+    BitProofError {
+        index: usize,
+        error: BitVerifierError,
+    },
+}
+
+/// The largest number of bits a range may have in the group of `scheme`.
+///
+/// The values must stay below `q`, where the exponents wrap around, even after adding two of
+/// them as in [`IntervalProof`].
+pub fn max_bits(scheme: &PedersenScheme) -> usize {
+    scheme.params().q().bits() - 2
+}
+
+/// A proof that a commitment hides a value in `[0, 2^n)`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RangeProof {
+    bit_commitments: Vec<BigInt>,
+    bit_proofs: Vec<BitProof>,
+}
+
+impl RangeProof {
+    /// Prove that `commitment`, which commits to `value` with randomness `r`, hides a value in
+    /// `[0, 2^bits)`.
+    pub fn prove(
+        scheme: &PedersenScheme,
+        commitment: &BigInt,
+        value: &BigInt,
+        r: &BigInt,
+        bits: usize,
+    ) -> Result<Self, RangeProofError> {
+        check_bits(scheme, bits)?;
+        if value.sign() == Sign::Minus || value.bits() > bits {
+            return Err(RangeProofError::ValueOutOfRange);
+        }
+        debug_assert!(scheme.verify(commitment, value, r));
+
+        let q = scheme.params().q();
+        let mut randomness = Secret::new(
+            (0..bits.saturating_sub(1))
+                .map(|_| scheme.commit(&BigInt::zero()).1.expose().clone())
+                .collect::<Vec<_>>(),
+        );
+        if bits > 0 {
+            // Pick the last randomness so that the weighted sum of all of them is `r`.
+            let sum = randomness
+                .expose()
+                .iter()
+                .enumerate()
+                .fold(BigInt::zero(), |acc, (i, r_i)| acc + (r_i << i));
+            let weight_inv = (BigInt::one() << (bits - 1)).modpow(&(q - 2), q);
+            randomness
+                .expose_mut()
+                .push(((r - sum) * weight_inv).mod_floor(q));
+        }
+
+        let params = scheme.params();
+        let (bit_commitments, bit_proofs) = randomness
+            .expose()
+            .iter()
+            .enumerate()
+            .map(|(i, r_i)| {
+                let bit = ((value >> i) & BigInt::one()).is_one();
+                let c_i = scheme.commit_with(&BigInt::from(bit as u8), r_i);
+                let proof =
+                    BitProof::prove(&BitInstance::new(params.clone(), c_i.clone()), bit, r_i);
+                (c_i, proof)
+            })
+            .unzip();
+
+        Ok(RangeProof {
+            bit_commitments,
+            bit_proofs,
+        })
+    }
+
+    pub fn verify(
+        &self,
+        scheme: &PedersenScheme,
+        commitment: &BigInt,
+        bits: usize,
+    ) -> Result<(), RangeProofError> {
+        check_bits(scheme, bits)?;
+        if !scheme.is_element(commitment) {
+            return Err(RangeProofError::InvalidCommitment);
+        }
+        for actual in [self.bit_commitments.len(), self.bit_proofs.len()] {
+            if actual != bits {
+                return Err(RangeProofError::WrongLength {
+                    expected: bits,
+                    actual,
+                });
+            }
+        }
+
+        let p = scheme.params().p();
+        let recombined = self
+            .bit_commitments
+            .iter()
+            .enumerate()
+            .fold(BigInt::one(), |acc, (i, c_i)| {
+                acc * c_i.modpow(&(BigInt::one() << i), p) % p
+            });
+        if &recombined != commitment {
+            return Err(RangeProofError::BitsDoNotMatchCommitment);
+        }
+
+        for (index, (c_i, proof)) in self
+            .bit_commitments
+            .iter()
+            .zip(&self.bit_proofs)
+            .enumerate()
+        {
+            proof
+                .verify(&BitInstance::new(scheme.params().clone(), c_i.clone()))
+                .map_err(|error| RangeProofError::BitProofError { index, error })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BinaryEncode for RangeProof {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.bit_commitments.encode(int_len, out)?;
+        self.bit_proofs.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        Ok(RangeProof {
+            bit_commitments: BinaryEncode::decode(int_len, input)?,
+            bit_proofs: BinaryEncode::decode(int_len, input)?,
+        })
+    }
+}
+
+/// A proof that a commitment hides a value in `[a, b]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntervalProof {
+    /// Shows that `v - a` is in `[0, 2^n)`.
+    lower: RangeProof,
+    /// Shows that `b - v` is in `[0, 2^n)`.
+    upper: RangeProof,
+}
+
+impl IntervalProof {
+    /// Prove that `commitment`, which commits to `value` with randomness `r`, hides a value in
+    /// `[a, b]`.
+    pub fn prove(
+        scheme: &PedersenScheme,
+        commitment: &BigInt,
+        value: &BigInt,
+        r: &BigInt,
+        a: &BigInt,
+        b: &BigInt,
+    ) -> Result<Self, RangeProofError> {
+        let bits = interval_bits(a, b)?;
+        if value < a || value > b {
+            return Err(RangeProofError::ValueOutOfRange);
+        }
+
+        let (lower, upper) = shifted_commitments(scheme, commitment, a, b);
+        Ok(IntervalProof {
+            lower: RangeProof::prove(scheme, &lower, &(value - a), r, bits)?,
+            upper: RangeProof::prove(scheme, &upper, &(b - value), &-r, bits)?,
+        })
+    }
+
+    pub fn verify(
+        &self,
+        scheme: &PedersenScheme,
+        commitment: &BigInt,
+        a: &BigInt,
+        b: &BigInt,
+    ) -> Result<(), RangeProofError> {
+        let bits = interval_bits(a, b)?;
+        if !scheme.is_element(commitment) {
+            return Err(RangeProofError::InvalidCommitment);
+        }
+
+        let (lower, upper) = shifted_commitments(scheme, commitment, a, b);
+        self.lower.verify(scheme, &lower, bits)?;
+        self.upper.verify(scheme, &upper, bits)
+    }
+}
+
+impl BinaryEncode for IntervalProof {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.lower.encode(int_len, out)?;
+        self.upper.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        Ok(IntervalProof {
+            lower: BinaryEncode::decode(int_len, input)?,
+            upper: BinaryEncode::decode(int_len, input)?,
+        })
+    }
+}
+
+fn check_bits(scheme: &PedersenScheme, bits: usize) -> Result<(), RangeProofError> {
+    if bits == 0 {
+        return Err(RangeProofError::NoBits);
+    }
+    let max = max_bits(scheme);
+    if bits > max {
+        return Err(RangeProofError::TooManyBits { bits, max });
+    }
+    Ok(())
+}
+
+/// The number of bits needed for the width of `[a, b]`.
+///
+/// A [`RangeProof`] without bits cannot be verified, so the singleton range `[a, a]` takes one.
+fn interval_bits(a: &BigInt, b: &BigInt) -> Result<usize, RangeProofError> {
+    if a > b {
+        return Err(RangeProofError::EmptyRange);
+    }
+    Ok((b - a).bits().max(1))
+}
+
+/// The commitments `C / g^a` to `v - a` and `g^b / C` to `b - v`.
+fn shifted_commitments(
+    scheme: &PedersenScheme,
+    commitment: &BigInt,
+    a: &BigInt,
+    b: &BigInt,
+) -> (BigInt, BigInt) {
+    let (p, q) = (scheme.params().p(), scheme.params().q());
+    let zero = BigInt::zero();
+    let lower = commitment * scheme.commit_with(&-a, &zero) % p;
+    let upper = commitment.modpow(&(q - 1), p) * scheme.commit_with(b, &zero) % p;
+    (lower, upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::commitments::{CommitmentScheme, PedersenScheme},
+        netutil::BinaryEncode,
+    };
+
+    use super::{max_bits, IntervalProof, RangeProof, RangeProofError};
+
+    // The bit proofs need a group where cheating is unlikely, so not the usual test group.
+    fn make_scheme() -> PedersenScheme {
+        PedersenScheme::new(CommitmentScheme::gen_params(256, 80))
+    }
+
+    #[test]
+    fn values_in_range_are_accepted() {
+        let scheme = make_scheme();
+        for v in [0, 1, 37, 255] {
+            let v = BigInt::from(v);
+            let (c, r) = scheme.commit(&v);
+            let proof = RangeProof::prove(&scheme, &c, &v, r.expose(), 8).unwrap();
+            let res = proof.verify(&scheme, &c, 8);
+            assert!(res.is_ok(), "Proof for {} not accepted: {:?}", v, res);
+        }
+    }
+
+    #[test]
+    fn values_out_of_range_cannot_be_proven() {
+        let scheme = make_scheme();
+        for v in [-1, 256] {
+            let v = BigInt::from(v);
+            let (c, r) = scheme.commit(&v);
+            assert!(matches!(
+                RangeProof::prove(&scheme, &c, &v, r.expose(), 8),
+                Err(RangeProofError::ValueOutOfRange)
+            ));
+        }
+    }
+
+    #[test]
+    fn proof_for_other_commitment_is_rejected() {
+        let scheme = make_scheme();
+        let v = BigInt::from(200);
+        let (c, r) = scheme.commit(&v);
+        let proof = RangeProof::prove(&scheme, &c, &v, r.expose(), 8).unwrap();
+
+        let (other, _) = scheme.commit(&BigInt::from(256));
+        assert!(matches!(
+            proof.verify(&scheme, &other, 8),
+            Err(RangeProofError::BitsDoNotMatchCommitment)
+        ));
+        assert!(matches!(
+            proof.verify(&scheme, &c, 9),
+            Err(RangeProofError::WrongLength {
+                expected: 9,
+                actual: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn too_many_bits_are_rejected() {
+        let scheme = make_scheme();
+        let v = BigInt::from(1);
+        let (c, r) = scheme.commit(&v);
+        let max = max_bits(&scheme);
+        assert!(max >= 64);
+
+        let res = RangeProof::prove(&scheme, &c, &v, r.expose(), max + 1);
+        assert!(
+            matches!(res, Err(RangeProofError::TooManyBits { bits, max: m }) if bits == max + 1 && m == max),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn zero_bits_are_rejected() {
+        let scheme = make_scheme();
+        let v = BigInt::from(0);
+        let (c, r) = scheme.commit(&v);
+        let res = RangeProof::prove(&scheme, &c, &v, r.expose(), 0);
+        assert!(matches!(res, Err(RangeProofError::NoBits)), "{:?}", res);
+
+        let proof = RangeProof::prove(&scheme, &c, &v, r.expose(), 1).unwrap();
+        assert!(matches!(
+            proof.verify(&scheme, &c, 0),
+            Err(RangeProofError::NoBits)
+        ));
+    }
+
+    #[test]
+    fn interval_proof_is_accepted() {
+        let scheme = make_scheme();
+        let (a, b) = (BigInt::from(18), BigInt::from(130));
+        for v in [18, 65, 130] {
+            let v = BigInt::from(v);
+            let (c, r) = scheme.commit(&v);
+            let proof = IntervalProof::prove(&scheme, &c, &v, r.expose(), &a, &b).unwrap();
+            let res = proof.verify(&scheme, &c, &a, &b);
+            assert!(res.is_ok(), "Proof for {} not accepted: {:?}", v, res);
+        }
+    }
+
+    #[test]
+    fn singleton_interval_proof_is_accepted() {
+        let scheme = make_scheme();
+        let a = BigInt::from(42);
+        let (c, r) = scheme.commit(&a);
+        let proof = IntervalProof::prove(&scheme, &c, &a, r.expose(), &a, &a).unwrap();
+        let res = proof.verify(&scheme, &c, &a, &a);
+        assert!(res.is_ok(), "Proof not accepted: {:?}", res);
+
+        let (other, _) = scheme.commit(&BigInt::from(43));
+        assert!(proof.verify(&scheme, &other, &a, &a).is_err());
+    }
+
+    #[test]
+    fn interval_proof_does_not_transfer() {
+        let scheme = make_scheme();
+        let (a, b) = (BigInt::from(-5), BigInt::from(5));
+        let v = BigInt::from(-3);
+        let (c, r) = scheme.commit(&v);
+        let proof = IntervalProof::prove(&scheme, &c, &v, r.expose(), &a, &b).unwrap();
+        assert!(proof.verify(&scheme, &c, &a, &b).is_ok());
+
+        assert!(proof
+            .verify(&scheme, &c, &BigInt::from(-4), &BigInt::from(6))
+            .is_err());
+        assert!(matches!(
+            IntervalProof::prove(&scheme, &c, &v, r.expose(), &b, &a),
+            Err(RangeProofError::EmptyRange)
+        ));
+        assert!(matches!(
+            IntervalProof::prove(&scheme, &c, &v, r.expose(), &BigInt::from(-2), &b),
+            Err(RangeProofError::ValueOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let scheme = make_scheme();
+        let v = BigInt::from(3);
+        let (c, r) = scheme.commit(&v);
+        let proof = IntervalProof::prove(
+            &scheme,
+            &c,
+            &v,
+            r.expose(),
+            &BigInt::from(0),
+            &BigInt::from(9),
+        )
+        .unwrap();
+
+        let mut bytes = vec![];
+        proof.encode(32, &mut bytes).unwrap();
+        assert_eq!(IntervalProof::decode(32, &mut &bytes[..]).unwrap(), proof);
+    }
+}
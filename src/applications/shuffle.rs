@@ -0,0 +1,609 @@
+//! Verifiable shuffles of ElGamal ciphertexts, for mix-nets.
+//!
+//! A shuffle permutes a list of ciphertexts and reencrypts each of them, so that nobody can
+//! tell which output came from which input. The proof that the outputs are a shuffle of the
+//! inputs is the one of [Terelius and Wikström](https://eprint.iacr.org/2010/066), as described
+//! by [Haenni et al.](https://eprint.iacr.org/2017/1193):
+//!
+//! 1. The prover commits to the permutation `psi`, column by column, as `c_psi(i) = g^r_psi(i)
+//!    h_i`.
+//! 2. The challenges `u_j` are a hash of the ciphertexts and these commitments, and the prover
+//!    commits to the permuted challenges `u'_i = u_psi(i)` in a chain `c^_i = g^r^_i
+//!    c^_(i-1)^u'_i`, starting from `c^_0 = h`.
+//! 3. The prover shows that the commitments are to a permutation matrix, and that the outputs
+//!    raised to the `u'_i` are the inputs raised to the `u_j`, up to a reencryption. All of
+//!    this is one [`RepresentationProtocol`] statement, made non-interactive with the
+//!    Fiat-Shamir transform.
+//!
+//! The proof has about `2N` group elements and `2N` scalars for `N` ciphertexts.
+
+use std::io;
+
+use num::{BigInt, Integer, One};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::elgamal::{Ciphertext, PublicKey},
+    fiat_shamir::NonInteractiveProof,
+    group::{PrimeOrderGroup, SchnorrGroup},
+    netutil::BinaryEncode,
+    representation::{
+        Equation, RepresentationInstance, RepresentationProtocol, RepresentationVerifierError,
+    },
+    secret::Secret,
+    transcript::Transcript,
+};
+
+#[derive(Debug)]
+pub enum ShuffleError {
+    /// The group of the key is not a valid Schnorr group, or `g` or `h` is not an element of it.
+    InvalidKey,
+    /// The record is for another key than the one expected.
+    UnexpectedKey,
+    /// There are no ciphertexts to shuffle.
+    NoCiphertexts,
+    /// There are `expected` inputs, but `actual` outputs or commitments.
+    WrongLength {
+        expected: usize,
+        actual: usize,
+    },
+    /// A ciphertext is not made of elements of the group.
+    InvalidCiphertext,
+    /// A commitment of the proof is not an element of the group.
+    InvalidCommitment,
+    ProofError(RepresentationVerifierError),
+}
+
+/// The secret of a shuffle: output `i` is input `permutation[i]`, reencrypted with
+/// `randomness[i]`.
+pub struct ShuffleWitness {
+    permutation: Secret<Vec<usize>>,
+    randomness: Secret<Vec<BigInt>>,
+}
+
+/// Shuffle `inputs` with a random permutation and fresh randomness.
+pub fn shuffle(key: &PublicKey, inputs: &[Ciphertext]) -> (Vec<Ciphertext>, ShuffleWitness) {
+    let group = key.group();
+    let mut permutation = (0..inputs.len()).collect::<Vec<_>>();
+    rand::thread_rng().shuffle(&mut permutation);
+    let randomness = (0..inputs.len())
+        .map(|_| group.random_scalar())
+        .collect::<Vec<_>>();
+    let outputs = permutation
+        .iter()
+        .zip(&randomness)
+        .map(|(&j, r)| key.reencrypt_with(&inputs[j], r))
+        .collect();
+    (
+        outputs,
+        ShuffleWitness {
+            permutation: Secret::new(permutation),
+            randomness: Secret::new(randomness),
+        },
+    )
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShuffleProof {
+    /// The commitments `c_j` to the columns of the permutation matrix.
+    permutation_commitments: Vec<BigInt>,
+    /// The commitments `c^_1, ..., c^_N` to the permuted challenges.
+    chain: Vec<BigInt>,
+    proof: NonInteractiveProof<Vec<BigInt>, Vec<BigInt>>,
+}
+
+impl ShuffleProof {
+    /// Prove that `outputs` is a shuffle of `inputs`, made with `witness`, in `context`.
+    ///
+    /// # Panics
+    ///
+    /// If the lists are empty, or the witness is not for them.
+    pub fn prove(
+        key: &PublicKey,
+        inputs: &[Ciphertext],
+        outputs: &[Ciphertext],
+        witness: &ShuffleWitness,
+        context: &[u8],
+    ) -> Self {
+        let n = inputs.len();
+        assert!(n > 0 && witness.permutation.expose().len() == n && outputs.len() == n);
+        let group = key.group();
+        let q = group.order();
+        let g = group.generator();
+        let (h, generators) = generators(&group, n);
+        let psi = witness.permutation.expose();
+
+        let mut r = vec![BigInt::from(0); n];
+        let mut permutation_commitments = vec![BigInt::from(0); n];
+        for (i, &j) in psi.iter().enumerate() {
+            r[j] = group.random_scalar();
+            permutation_commitments[j] = group.mul(&group.exp_secret(&g, &r[j]), &generators[i]);
+        }
+
+        let transcript = transcript(key, inputs, outputs, &permutation_commitments, context);
+        let u = challenges(&transcript, q, n);
+        let u_permuted = psi.iter().map(|&j| u[j].clone()).collect::<Vec<_>>();
+
+        let r_chain = (0..n).map(|_| group.random_scalar()).collect::<Vec<_>>();
+        let mut chain = Vec::with_capacity(n);
+        let mut previous = h;
+        for (r_i, u_i) in r_chain.iter().zip(&u_permuted) {
+            let c_i = group.multi_exp_secret(&[g.clone(), previous], &[r_i.clone(), u_i.clone()]);
+            previous = c_i.clone();
+            chain.push(c_i);
+        }
+
+        // `v_i` is the product of `u'_(i+1), ..., u'_N`, by which `r^_i` ends up multiplied in
+        // `c^_N`.
+        let mut v = vec![BigInt::from(1); n];
+        for i in (0..n.saturating_sub(1)).rev() {
+            v[i] = (&v[i + 1] * &u_permuted[i + 1]).mod_floor(q);
+        }
+        let dot = |a: &[BigInt], b: &[BigInt]| {
+            a.iter()
+                .zip(b)
+                .fold(BigInt::from(0), |acc, (a, b)| acc + a * b)
+                .mod_floor(q)
+        };
+        let mut w = vec![
+            r.iter()
+                .fold(BigInt::from(0), |acc, r_j| acc + r_j)
+                .mod_floor(q),
+            dot(&r_chain, &v),
+            dot(&r, &u),
+            dot(witness.randomness.expose(), &u_permuted),
+        ];
+        w.extend(r_chain);
+        w.extend(u_permuted);
+
+        let instance = statement(key, inputs, outputs, &permutation_commitments, &chain, &u);
+        debug_assert!(instance.is_satisfied_by(&w));
+        let bits = instance.challenge_bits();
+        let proof = NonInteractiveProof::prove::<RepresentationProtocol, _, _>(
+            &proof_context(transcript, &chain),
+            instance,
+            w,
+            bits,
+        );
+
+        ShuffleProof {
+            permutation_commitments,
+            chain,
+            proof,
+        }
+    }
+
+    pub fn verify(
+        &self,
+        key: &PublicKey,
+        inputs: &[Ciphertext],
+        outputs: &[Ciphertext],
+        context: &[u8],
+    ) -> Result<(), ShuffleError> {
+        // The key may come from an untrusted record, and the group operations below, such as
+        // hashing to the group, assume that it is sound.
+        let instance = key.instance();
+        if !instance.is_valid()
+            || !instance.is_element(instance.g())
+            || instance.g().is_one()
+            || !instance.is_element(instance.h())
+        {
+            return Err(ShuffleError::InvalidKey);
+        }
+        let n = inputs.len();
+        if n == 0 {
+            return Err(ShuffleError::NoCiphertexts);
+        }
+        for actual in [
+            outputs.len(),
+            self.permutation_commitments.len(),
+            self.chain.len(),
+        ] {
+            if actual != n {
+                return Err(ShuffleError::WrongLength {
+                    expected: n,
+                    actual,
+                });
+            }
+        }
+        if !inputs.iter().chain(outputs).all(|c| key.is_valid(c)) {
+            return Err(ShuffleError::InvalidCiphertext);
+        }
+        let group = key.group();
+        if !self
+            .permutation_commitments
+            .iter()
+            .chain(&self.chain)
+            .all(|c| group.is_element(c))
+        {
+            return Err(ShuffleError::InvalidCommitment);
+        }
+
+        let transcript = transcript(key, inputs, outputs, &self.permutation_commitments, context);
+        let u = challenges(&transcript, group.order(), n);
+        let instance = statement(
+            key,
+            inputs,
+            outputs,
+            &self.permutation_commitments,
+            &self.chain,
+            &u,
+        );
+        let bits = instance.challenge_bits();
+        self.proof
+            .verify::<RepresentationProtocol, _, _>(
+                &proof_context(transcript, &self.chain),
+                instance,
+                bits,
+            )
+            .map_err(ShuffleError::ProofError)
+    }
+}
+
+impl BinaryEncode for ShuffleProof {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.permutation_commitments.encode(int_len, out)?;
+        self.chain.encode(int_len, out)?;
+        self.proof.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        Ok(ShuffleProof {
+            permutation_commitments: BinaryEncode::decode(int_len, input)?,
+            chain: BinaryEncode::decode(int_len, input)?,
+            proof: BinaryEncode::decode(int_len, input)?,
+        })
+    }
+}
+
+/// Everything needed to check one shuffle of a mix-net on its own.
+///
+/// In a mix-net with several servers, the outputs of each record are the inputs of the next.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShuffleRecord {
+    key: PublicKey,
+    context: String,
+    inputs: Vec<Ciphertext>,
+    outputs: Vec<Ciphertext>,
+    proof: ShuffleProof,
+}
+
+impl ShuffleRecord {
+    /// Shuffle `inputs` and prove it, in `context`, such as the id of the mix-net and the
+    /// position of the server in it.
+    pub fn shuffle(key: PublicKey, context: String, inputs: Vec<Ciphertext>) -> Self {
+        let (outputs, witness) = shuffle(&key, &inputs);
+        let proof = ShuffleProof::prove(&key, &inputs, &outputs, &witness, context.as_bytes());
+        ShuffleRecord {
+            key,
+            context,
+            inputs,
+            outputs,
+            proof,
+        }
+    }
+
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    pub fn inputs(&self) -> &[Ciphertext] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Ciphertext] {
+        &self.outputs
+    }
+
+    /// Check the shuffle under the key it names. Use [`ShuffleRecord::verify_for`] unless the
+    /// key of the record has been checked separately.
+    pub fn verify(&self) -> Result<(), ShuffleError> {
+        self.proof.verify(
+            &self.key,
+            &self.inputs,
+            &self.outputs,
+            self.context.as_bytes(),
+        )
+    }
+
+    /// Check the shuffle, and that it is under the expected key `key`.
+    pub fn verify_for(&self, key: &PublicKey) -> Result<(), ShuffleError> {
+        if &self.key != key {
+            return Err(ShuffleError::UnexpectedKey);
+        }
+        self.verify()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+/// The bases `h` and `h_1, ..., h_N` of the commitments, which are independent of `g` and of
+/// each other.
+fn generators(group: &SchnorrGroup, n: usize) -> (BigInt, Vec<BigInt>) {
+    let h = group.hash_to_element(b"shuffle base");
+    let generators = (0..n as u64)
+        .map(|i| {
+            let mut label = b"shuffle generator ".to_vec();
+            label.extend_from_slice(&i.to_be_bytes());
+            group.hash_to_element(&label)
+        })
+        .collect();
+    (h, generators)
+}
+
+fn transcript(
+    key: &PublicKey,
+    inputs: &[Ciphertext],
+    outputs: &[Ciphertext],
+    permutation_commitments: &[BigInt],
+    context: &[u8],
+) -> Transcript {
+    let mut transcript = Transcript::new(b"shuffle");
+    transcript.append(b"context", context);
+    transcript.append_value(b"key", key);
+    transcript.append_value(b"inputs", &inputs);
+    transcript.append_value(b"outputs", &outputs);
+    transcript.append_value(b"permutation commitments", &permutation_commitments);
+    transcript
+}
+
+/// The challenges `u_1, ..., u_N`.
+fn challenges(transcript: &Transcript, q: &BigInt, n: usize) -> Vec<BigInt> {
+    (0..n as u64)
+        .map(|i| {
+            let mut transcript = transcript.clone();
+            transcript.append(b"index", &i.to_be_bytes());
+            // Take 128 more bits than needed, so the result is close to uniform modulo `q`.
+            transcript.challenge(b"challenge", q.bits() + 128) % q
+        })
+        .collect()
+}
+
+fn proof_context(mut transcript: Transcript, chain: &[BigInt]) -> Vec<u8> {
+    transcript.append_value(b"chain", &chain);
+    transcript.hash().to_vec()
+}
+
+/// The statement that the commitments are to a permutation, and that the outputs are the
+/// inputs permuted by it and reencrypted.
+///
+/// The witness is `r_1 + ... + r_N`, the randomness `r^` of `c^_N`, `r_1 u_1 + ... + r_N u_N`,
+/// the reencryption randomness `r'_1 u'_1 + ... + r'_N u'_N`, then `r^_1, ..., r^_N` and
+/// `u'_1, ..., u'_N`.
+fn statement(
+    key: &PublicKey,
+    inputs: &[Ciphertext],
+    outputs: &[Ciphertext],
+    permutation_commitments: &[BigInt],
+    chain: &[BigInt],
+    u: &[BigInt],
+) -> RepresentationInstance {
+    let n = inputs.len();
+    let group = key.group();
+    let g = group.generator();
+    let (h, generators) = generators(&group, n);
+    let product = |values: &mut dyn Iterator<Item = &BigInt>| {
+        values.fold(group.identity(), |acc, x| group.mul(&acc, x))
+    };
+    let u_index = |i: usize| 4 + n + i;
+
+    // The product of the commitments is `g^(r_1 + ... + r_N) h_1 ... h_N` exactly when each
+    // row of the matrix sums to one.
+    let c_bar = group.mul(
+        &product(&mut permutation_commitments.iter()),
+        &group.inverse(&product(&mut generators.iter())),
+    );
+    // The chain ends in `g^r^ h^(u_1 ... u_N)` exactly when the `u'_i` are a permutation of
+    // the `u_j`, given the other equations.
+    let u_product = u.iter().fold(BigInt::from(1), |acc, u_j| {
+        (acc * u_j).mod_floor(group.order())
+    });
+    let c_hat = group.mul(
+        chain.last().expect("There is at least one ciphertext."),
+        &group.inverse(&group.exp(&h, &u_product)),
+    );
+    let c_tilde = group.multi_exp(permutation_commitments, u);
+    let c1 = inputs.iter().map(|c| c.c1().clone()).collect::<Vec<_>>();
+    let c2 = inputs.iter().map(|c| c.c2().clone()).collect::<Vec<_>>();
+    let a = group.multi_exp(&c2, u);
+    let b = group.multi_exp(&c1, u);
+
+    let mut equations = vec![
+        Equation::new(c_bar, vec![(g.clone(), 0)]),
+        Equation::new(c_hat, vec![(g.clone(), 1)]),
+        Equation::new(
+            c_tilde,
+            std::iter::once((g.clone(), 2))
+                .chain(
+                    generators
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, h_i)| (h_i, u_index(i))),
+                )
+                .collect(),
+        ),
+        Equation::new(
+            a,
+            std::iter::once((group.inverse(key.instance().h()), 3))
+                .chain(
+                    outputs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| (c.c2().clone(), u_index(i))),
+                )
+                .collect(),
+        ),
+        Equation::new(
+            b,
+            std::iter::once((group.inverse(&g), 3))
+                .chain(
+                    outputs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| (c.c1().clone(), u_index(i))),
+                )
+                .collect(),
+        ),
+    ];
+    let mut previous = h;
+    for (i, c_i) in chain.iter().enumerate() {
+        equations.push(Equation::new(
+            c_i.clone(),
+            vec![(g.clone(), 4 + i), (previous, u_index(i))],
+        ));
+        previous = c_i.clone();
+    }
+    RepresentationInstance::new(group, 4 + 2 * n, equations)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::elgamal::{Ciphertext, PublicKey, SecretKey},
+        netutil::BinaryEncode,
+        schnorr::SchnorrDiscreteLogInstance,
+    };
+
+    use super::{shuffle, ShuffleError, ShuffleProof, ShuffleRecord};
+
+    fn make_inputs(n: u32) -> (SecretKey, Vec<Ciphertext>) {
+        let (params, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let key = SecretKey::generate(&params);
+        let inputs = (0..n)
+            .map(|m| {
+                key.public_key()
+                    .encrypt(&key.public_key().encode(&m.into()))
+                    .0
+            })
+            .collect();
+        (key, inputs)
+    }
+
+    fn plaintexts(key: &SecretKey, ciphertexts: &[Ciphertext]) -> Vec<BigInt> {
+        let mut plaintexts = ciphertexts
+            .iter()
+            .map(|c| key.decrypt(c))
+            .collect::<Vec<_>>();
+        plaintexts.sort();
+        plaintexts
+    }
+
+    #[test]
+    fn shuffle_is_accepted() {
+        let (key, inputs) = make_inputs(6);
+        let public_key: &PublicKey = key.public_key();
+        let (outputs, witness) = shuffle(public_key, &inputs);
+        assert_eq!(plaintexts(&key, &inputs), plaintexts(&key, &outputs));
+
+        let proof = ShuffleProof::prove(public_key, &inputs, &outputs, &witness, b"mix 1");
+        let res = proof.verify(public_key, &inputs, &outputs, b"mix 1");
+        assert!(res.is_ok(), "Shuffle not accepted: {:?}", res);
+
+        let mut encoded = Vec::new();
+        proof.encode(32, &mut encoded).unwrap();
+        let decoded = ShuffleProof::decode(32, &mut encoded.as_slice()).unwrap();
+        assert_eq!(decoded, proof);
+
+        assert!(matches!(
+            proof.verify(public_key, &inputs, &outputs, b"mix 2"),
+            Err(ShuffleError::ProofError(_))
+        ));
+    }
+
+    #[test]
+    fn changed_ciphertext_is_rejected() {
+        let (key, inputs) = make_inputs(4);
+        let public_key = key.public_key();
+        let (mut outputs, witness) = shuffle(public_key, &inputs);
+        let proof = ShuffleProof::prove(public_key, &inputs, &outputs, &witness, b"mix");
+
+        // Replace an output by an encryption of another plaintext.
+        outputs[2] = public_key.encrypt(&public_key.encode(&BigInt::from(9))).0;
+        assert!(matches!(
+            proof.verify(public_key, &inputs, &outputs, b"mix"),
+            Err(ShuffleError::ProofError(_))
+        ));
+        assert!(matches!(
+            proof.verify(public_key, &inputs, &outputs[1..], b"mix"),
+            Err(ShuffleError::WrongLength {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn mix_net_records_are_verified() {
+        let (key, inputs) = make_inputs(3);
+        let first = ShuffleRecord::shuffle(key.public_key().clone(), "mix 1".into(), inputs);
+        let second = ShuffleRecord::shuffle(
+            key.public_key().clone(),
+            "mix 2".into(),
+            first.outputs().to_vec(),
+        );
+        let json = second.to_json().unwrap();
+        let second = ShuffleRecord::from_json(&json).unwrap();
+
+        assert!(first.verify().is_ok());
+        assert!(second.verify().is_ok());
+        assert_eq!(
+            plaintexts(&key, first.inputs()),
+            plaintexts(&key, second.outputs())
+        );
+    }
+
+    #[test]
+    fn record_under_other_key_is_rejected() {
+        let (key, inputs) = make_inputs(3);
+        let record = ShuffleRecord::shuffle(key.public_key().clone(), "mix".into(), inputs);
+        assert!(record.verify_for(key.public_key()).is_ok());
+
+        let other = SecretKey::generate(key.public_key().instance());
+        assert!(matches!(
+            record.verify_for(other.public_key()),
+            Err(ShuffleError::UnexpectedKey)
+        ));
+    }
+
+    #[test]
+    fn invalid_key_is_rejected() {
+        let (key, inputs) = make_inputs(3);
+        let record = ShuffleRecord::shuffle(key.public_key().clone(), "mix".into(), inputs);
+        let params = key.public_key().instance();
+        let with = |p: &BigInt, q: &BigInt, g: &BigInt| {
+            let instance = SchnorrDiscreteLogInstance::new(
+                p.clone(),
+                q.clone(),
+                g.clone(),
+                params.h().clone(),
+            );
+            let mut record = record.clone();
+            record.key = PublicKey::new(instance);
+            record
+        };
+
+        // With `q >= p`, hashing to the group would never find an element.
+        let (p, q, g) = (params.p(), params.q(), params.g());
+        for record in [
+            with(q, p, g),
+            with(&(p + 1), q, g),
+            with(p, q, &(p - 1)),
+            with(p, q, &BigInt::from(1)),
+        ] {
+            assert!(matches!(record.verify(), Err(ShuffleError::InvalidKey)));
+        }
+    }
+}
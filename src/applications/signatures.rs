@@ -0,0 +1,167 @@
+//! Schnorr signatures in any prime-order group.
+//!
+//! A signature on `m` under the key `X = g^x` is `(R, z)` with `g^z = R X^c`, where the
+//! challenge `c` is a hash of `R`, `X` and `m`. This is the Fiat-Shamir transform of
+//! [`SchnorrDiscreteLogProtocol`](crate::schnorr::SchnorrDiscreteLogProtocol), with the message
+//! added to the hash.
+//!
+//! Only the verification equation matters, so signatures made jointly by several parties, as
+//! in [`frost`](crate::applications::frost) and [`musig`](crate::applications::musig), are
+//! checked with [`verify`] like any other.
+
+use num::{BigInt, Signed};
+use serde::{Deserialize, Serialize};
+
+use crate::{group::PrimeOrderGroup, secret::Secret, transcript::Transcript};
+
+#[derive(Debug, PartialEq)]
+pub enum SignatureError {
+    /// The key or the nonce commitment of the signature is not an element of the group.
+    InvalidElement,
+    /// The response is not in `[0, q)`. Adding `q` to it would otherwise give a second valid
+    /// signature for the same message.
+    ResponseOutOfRange,
+    /// The signature does not satisfy the verification equation.
+    InvalidSignature,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Signature<E> {
+    r: E,
+    z: BigInt,
+}
+
+impl<E> Signature<E> {
+    /// The signature with nonce commitment `r` and response `z`.
+    pub fn new(r: E, z: BigInt) -> Self {
+        Signature { r, z }
+    }
+
+    pub fn r(&self) -> &E {
+        &self.r
+    }
+
+    pub fn z(&self) -> &BigInt {
+        &self.z
+    }
+}
+
+/// A new key pair, as the secret key `x` and the public key `g^x`.
+pub fn generate_key<G: PrimeOrderGroup>(group: &G) -> (Secret<BigInt>, G::Element) {
+    let x = Secret::new(group.random_scalar());
+    let public_key = group.exp_secret(&group.generator(), x.expose());
+    (x, public_key)
+}
+
+pub fn sign<G: PrimeOrderGroup>(
+    group: &G,
+    secret_key: &BigInt,
+    message: &[u8],
+) -> Signature<G::Element> {
+    let g = group.generator();
+    let public_key = group.exp_secret(&g, secret_key);
+    let k = Secret::new(group.random_scalar());
+    let r = group.exp_secret(&g, k.expose());
+    let c = challenge(group, &r, &public_key, message);
+    let z = group.response(k.expose(), &c, secret_key);
+    Signature { r, z }
+}
+
+pub fn verify<G: PrimeOrderGroup>(
+    group: &G,
+    public_key: &G::Element,
+    message: &[u8],
+    signature: &Signature<G::Element>,
+) -> Result<(), SignatureError> {
+    if !group.is_element(public_key) || !group.is_element(&signature.r) {
+        return Err(SignatureError::InvalidElement);
+    }
+    if signature.z.is_negative() || &signature.z >= group.order() {
+        return Err(SignatureError::ResponseOutOfRange);
+    }
+    let c = challenge(group, &signature.r, public_key, message);
+    let lhs = group.exp(&group.generator(), &signature.z);
+    let rhs = group.mul(&signature.r, &group.exp(public_key, &c));
+    if lhs != rhs {
+        return Err(SignatureError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// The challenge `c` for the nonce commitment `r` and the key `public_key`, as a scalar.
+pub fn challenge<G: PrimeOrderGroup>(
+    group: &G,
+    r: &G::Element,
+    public_key: &G::Element,
+    message: &[u8],
+) -> BigInt {
+    let mut transcript = Transcript::new(b"schnorr signature");
+    transcript.append_value(b"nonce commitment", r);
+    transcript.append_value(b"public key", public_key);
+    transcript.append(b"message", message);
+    // Take 128 more bits than needed, so the result is close to uniform modulo the order.
+    let bits = group.order().bits() + 128;
+    transcript.challenge(b"challenge", bits) % group.order()
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        group::{PrimeOrderGroup, SchnorrGroup},
+        schnorr::SchnorrDiscreteLogInstance,
+    };
+
+    use super::{generate_key, sign, verify, Signature, SignatureError};
+
+    fn make_group() -> SchnorrGroup {
+        SchnorrGroup::new(BigInt::from(1907), BigInt::from(953), BigInt::from(343)).unwrap()
+    }
+
+    #[test]
+    fn signature_is_accepted() {
+        let group = make_group();
+        let (x, public_key) = generate_key(&group);
+        let signature = sign(&group, x.expose(), b"message");
+        assert_eq!(verify(&group, &public_key, b"message", &signature), Ok(()));
+    }
+
+    #[test]
+    fn wrong_message_or_key_is_rejected() {
+        let group = SchnorrDiscreteLogInstance::generate(256, 64).0.group();
+        let (x, public_key) = generate_key(&group);
+        let signature = sign(&group, x.expose(), b"message");
+        assert_eq!(
+            verify(&group, &public_key, b"other message", &signature),
+            Err(SignatureError::InvalidSignature)
+        );
+
+        let other_key = group.mul(&public_key, &group.generator());
+        assert_eq!(
+            verify(&group, &other_key, b"message", &signature),
+            Err(SignatureError::InvalidSignature)
+        );
+
+        let forged = Signature::new(group.modulus() - 1, signature.z().clone());
+        assert_eq!(
+            verify(&group, &public_key, b"message", &forged),
+            Err(SignatureError::InvalidElement)
+        );
+    }
+
+    #[test]
+    fn response_out_of_range_is_rejected() {
+        let group = make_group();
+        let (x, public_key) = generate_key(&group);
+        let signature = sign(&group, x.expose(), b"message");
+        let q = group.order();
+        for z in [signature.z() + q, signature.z() - q] {
+            let malleated = Signature::new(signature.r().clone(), z);
+            assert_eq!(
+                verify(&group, &public_key, b"message", &malleated),
+                Err(SignatureError::ResponseOutOfRange)
+            );
+        }
+    }
+}
@@ -0,0 +1,741 @@
+//! A referendum with encrypted ballots, a homomorphic tally and a public bulletin board.
+//!
+//! 1. Each trustee registers an ElGamal key with a proof of knowledge of its secret key. The
+//!    election key is the product of their keys, so all trustees are needed to decrypt.
+//! 2. Each voter posts a [`Ballot`]: an exponential ElGamal encryption of `M^i` for their option
+//!    `i`, where `M` is larger than the number of voters, with a proof that it encrypts one of
+//!    the options.
+//! 3. The product of all ballots encrypts `sum_i count_i M^i`. Each trustee posts a decryption
+//!    share of it with a Chaum-Pedersen proof, and the counts are read off the digits of the
+//!    decrypted sum in base `M`.
+//!
+//! Everything is posted on the [`BulletinBoard`], which anyone can serialise, publish and check
+//! independently with [`verify_record`].
+
+use std::collections::HashSet;
+
+use num::{pow::Pow, BigInt, One, ToPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::elgamal::{
+        Ciphertext, DecryptionShare, ElGamalError, KnowledgeProof, PlaintextProof, PublicKey,
+        SecretKey, MAX_DECODE,
+    },
+    schnorr::{SchnorrDiscreteLogInstance, SchnorrVerifierError},
+};
+
+#[derive(Debug)]
+pub enum ElectionError {
+    /// The group of the election is not a valid Schnorr group.
+    InvalidParams,
+    /// The options cannot be encoded in the group, as `(max_voters + 1)^options` is too large.
+    TooManyOptions,
+    /// The key of the trustee at `index` is not in the group of the election.
+    KeyNotInGroup {
+        index: usize,
+    },
+    /// The registration proof of the trustee at `index` was rejected.
+    InvalidTrusteeKey {
+        index: usize,
+        error: SchnorrVerifierError,
+    },
+    /// Trustees can only register before the first ballot.
+    RegistrationClosed,
+    NoTrustees,
+    /// There is no option at this index.
+    InvalidOption(usize),
+    /// The voter has already cast a ballot.
+    DuplicateVoter(String),
+    /// The election already has `max_voters` ballots.
+    TooManyBallots,
+    /// The proof of the ballot of the voter was rejected.
+    InvalidBallot {
+        voter: String,
+        error: ElGamalError,
+    },
+    /// No ballots can be cast once the tally has started.
+    VotingClosed,
+    /// The decryption share of the trustee at `index` was rejected.
+    InvalidDecryptionShare {
+        index: usize,
+        error: ElGamalError,
+    },
+    /// The trustee at `index` has already posted a share, or there is no such trustee.
+    UnexpectedShare {
+        index: usize,
+    },
+    /// Not every trustee has posted a decryption share yet.
+    MissingShares,
+    /// The decrypted tally is not a sum of at most `max_voters` votes.
+    UndecodableTally,
+    /// The tally could be too large to decrypt, as its discrete log is searched for.
+    TallyTooLarge,
+    /// The published result is not the one on the bulletin board.
+    ResultMismatch,
+}
+
+/// The public description of an election.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Election {
+    id: String,
+    question: String,
+    options: Vec<String>,
+    max_voters: u64,
+    params: SchnorrDiscreteLogInstance,
+}
+
+impl Election {
+    /// Describe an election in the group of `params`. The `h` of `params` is not used.
+    ///
+    /// The group is checked first, as the election may come from an untrusted record.
+    pub fn new(
+        id: String,
+        question: String,
+        options: Vec<String>,
+        max_voters: u64,
+        params: SchnorrDiscreteLogInstance,
+    ) -> Result<Self, ElectionError> {
+        if !params.is_valid() {
+            return Err(ElectionError::InvalidParams);
+        }
+        let election = Election {
+            id,
+            question,
+            options,
+            max_voters,
+            params,
+        };
+        // The largest possible tally must stay below `q`, or it would wrap around.
+        let largest = election.base().pow(election.options.len() as u32);
+        if election.options.len() < 2 || &largest >= election.params.q() {
+            return Err(ElectionError::TooManyOptions);
+        }
+        Ok(election)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn question(&self) -> &str {
+        &self.question
+    }
+
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    pub fn params(&self) -> &SchnorrDiscreteLogInstance {
+        &self.params
+    }
+
+    /// The base `M` in which the counts are encoded.
+    fn base(&self) -> BigInt {
+        BigInt::from(self.max_voters) + 1
+    }
+
+    /// The plaintexts `g^(M^i)` of the options.
+    fn encoded_options(&self, key: &PublicKey) -> Vec<BigInt> {
+        let base = self.base();
+        (0..self.options.len() as u32)
+            .map(|i| key.encode(&base.pow(i)))
+            .collect()
+    }
+
+    /// Bind the proofs of a party to this election, so they cannot be replayed in another one
+    /// or by someone else.
+    fn context(&self, purpose: &str, party: &str) -> Vec<u8> {
+        serde_json::to_vec(&(&self.id, purpose, party)).expect("Value could not be serialized.")
+    }
+}
+
+/// A trustee, who holds one part of the election key.
+pub struct Trustee {
+    index: usize,
+    key: SecretKey,
+}
+
+/// The public key of a trustee, with a proof that the trustee knows its secret key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrusteeRegistration {
+    key: PublicKey,
+    proof: KnowledgeProof,
+}
+
+impl Trustee {
+    /// A new trustee, who will register as the `index`-th trustee of `election`.
+    pub fn new(election: &Election, index: usize) -> Self {
+        Trustee {
+            index,
+            key: SecretKey::generate(election.params()),
+        }
+    }
+
+    pub fn registration(&self, election: &Election) -> TrusteeRegistration {
+        let context = election.context("trustee", &self.index.to_string());
+        TrusteeRegistration {
+            key: self.key.public_key().clone(),
+            proof: self.key.prove_knowledge(&context),
+        }
+    }
+
+    /// Our decryption share of the tally on `board`.
+    pub fn decrypt_tally(&self, board: &BulletinBoard) -> DecryptionShare {
+        let context = board
+            .election
+            .context("decryption", &self.index.to_string());
+        self.key.decryption_share(&board.tally(), &context)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ballot {
+    voter: String,
+    ciphertext: Ciphertext,
+    proof: PlaintextProof,
+}
+
+impl Ballot {
+    /// Encrypt the vote of `voter` for the option at index `option`.
+    pub fn cast(
+        election: &Election,
+        key: &PublicKey,
+        voter: String,
+        option: usize,
+    ) -> Result<Self, ElectionError> {
+        let options = election.encoded_options(key);
+        if option >= options.len() {
+            return Err(ElectionError::InvalidOption(option));
+        }
+        let (ciphertext, r) = key.encrypt(&options[option]);
+        let context = election.context("ballot", &voter);
+        let proof = PlaintextProof::prove(key, &ciphertext, r.expose(), &options, option, &context);
+        Ok(Ballot {
+            voter,
+            ciphertext,
+            proof,
+        })
+    }
+
+    pub fn voter(&self) -> &str {
+        &self.voter
+    }
+
+    fn verify(&self, election: &Election, key: &PublicKey) -> Result<(), ElectionError> {
+        let context = election.context("ballot", &self.voter);
+        self.proof
+            .verify(
+                key,
+                &self.ciphertext,
+                &election.encoded_options(key),
+                &context,
+            )
+            .map_err(|error| ElectionError::InvalidBallot {
+                voter: self.voter.clone(),
+                error,
+            })
+    }
+}
+
+/// The public record of an election. Every post is checked as it is made, and the whole record
+/// can be checked again by anyone with [`verify_record`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BulletinBoard {
+    election: Election,
+    trustees: Vec<TrusteeRegistration>,
+    ballots: Vec<Ballot>,
+    shares: Vec<Option<DecryptionShare>>,
+    result: Option<Vec<u64>>,
+}
+
+impl BulletinBoard {
+    pub fn new(election: Election) -> Self {
+        BulletinBoard {
+            election,
+            trustees: vec![],
+            ballots: vec![],
+            shares: vec![],
+            result: None,
+        }
+    }
+
+    pub fn election(&self) -> &Election {
+        &self.election
+    }
+
+    pub fn ballots(&self) -> &[Ballot] {
+        &self.ballots
+    }
+
+    /// The counts for each option, once every trustee has posted a share.
+    pub fn result(&self) -> Option<&[u64]> {
+        self.result.as_deref()
+    }
+
+    pub fn register_trustee(
+        &mut self,
+        registration: TrusteeRegistration,
+    ) -> Result<(), ElectionError> {
+        if !self.ballots.is_empty() {
+            return Err(ElectionError::RegistrationClosed);
+        }
+        let index = self.trustees.len();
+        verify_registration(&self.election, index, &registration)?;
+        self.trustees.push(registration);
+        self.shares.push(None);
+        Ok(())
+    }
+
+    /// The key the ballots are encrypted under.
+    pub fn election_key(&self) -> Result<PublicKey, ElectionError> {
+        if self.trustees.is_empty() {
+            return Err(ElectionError::NoTrustees);
+        }
+        let keys = self
+            .trustees
+            .iter()
+            .map(|trustee| trustee.key.clone())
+            .collect::<Vec<_>>();
+        Ok(PublicKey::combine(&keys))
+    }
+
+    pub fn cast(&mut self, ballot: Ballot) -> Result<(), ElectionError> {
+        if self.shares.iter().any(Option::is_some) {
+            return Err(ElectionError::VotingClosed);
+        }
+        if self.ballots.len() as u64 >= self.election.max_voters {
+            return Err(ElectionError::TooManyBallots);
+        }
+        if self.ballots.iter().any(|b| b.voter == ballot.voter) {
+            return Err(ElectionError::DuplicateVoter(ballot.voter));
+        }
+        ballot.verify(&self.election, &self.election_key()?)?;
+        self.ballots.push(ballot);
+        Ok(())
+    }
+
+    /// The encryption of the sum of all votes.
+    pub fn tally(&self) -> Ciphertext {
+        let key = self.election_key().expect("Tally without trustees.");
+        let zero = key.encrypt_with(&BigInt::one(), &BigInt::from(0));
+        self.ballots
+            .iter()
+            .fold(zero, |acc, ballot| key.add(&acc, &ballot.ciphertext))
+    }
+
+    /// Post the decryption share of the trustee at `index`. Once every trustee has posted one,
+    /// the result is decrypted.
+    pub fn post_share(
+        &mut self,
+        index: usize,
+        share: DecryptionShare,
+    ) -> Result<(), ElectionError> {
+        if !matches!(self.shares.get(index), Some(None)) {
+            return Err(ElectionError::UnexpectedShare { index });
+        }
+        verify_share(
+            &self.election,
+            &self.trustees[index],
+            index,
+            &self.tally(),
+            &share,
+        )?;
+        self.shares[index] = Some(share);
+
+        if self.shares.iter().all(Option::is_some) {
+            self.result = Some(self.decrypt_result()?);
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    fn decrypt_result(&self) -> Result<Vec<u64>, ElectionError> {
+        let shares = self
+            .shares
+            .iter()
+            .map(|share| share.clone().ok_or(ElectionError::MissingShares))
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = self.election_key()?;
+        let sum = key.combine_shares(&self.tally(), &shares);
+
+        // Each ballot adds at most `M^(options - 1)` to the sum, so the search is bounded by the
+        // number of ballots rather than by the number of voters the election allows.
+        let options = self.election.options.len() as u32;
+        let max = self.election.base().pow(options.saturating_sub(1)) * self.ballots.len();
+        let max = max
+            .to_u64()
+            .filter(|max| *max <= MAX_DECODE)
+            .ok_or(ElectionError::TallyTooLarge)?;
+        let mut sum = key
+            .decode(&sum, max)
+            .ok_or(ElectionError::UndecodableTally)?;
+
+        let base = self.election.max_voters + 1;
+        let counts = (0..options)
+            .map(|_| {
+                let count = sum % base;
+                sum /= base;
+                count
+            })
+            .collect::<Vec<_>>();
+        if counts.iter().sum::<u64>() != self.ballots.len() as u64 {
+            return Err(ElectionError::UndecodableTally);
+        }
+        Ok(counts)
+    }
+}
+
+/// Check the whole record of an election: every registration, ballot and decryption share, and
+/// that the result is the decryption of the tally. Returns the result.
+///
+/// This does not trust anything on the board, and is what an independent auditor would run.
+pub fn verify_record(board: &BulletinBoard) -> Result<Vec<u64>, ElectionError> {
+    let election = Election::new(
+        board.election.id.clone(),
+        board.election.question.clone(),
+        board.election.options.clone(),
+        board.election.max_voters,
+        board.election.params.clone(),
+    )?;
+
+    for (index, registration) in board.trustees.iter().enumerate() {
+        verify_registration(&election, index, registration)?;
+    }
+    let key = board.election_key()?;
+
+    if board.ballots.len() as u64 > election.max_voters {
+        return Err(ElectionError::TooManyBallots);
+    }
+    let mut voters = HashSet::new();
+    for ballot in &board.ballots {
+        if !voters.insert(&ballot.voter) {
+            return Err(ElectionError::DuplicateVoter(ballot.voter.clone()));
+        }
+        ballot.verify(&election, &key)?;
+    }
+
+    let tally = board.tally();
+    if board.shares.len() != board.trustees.len() {
+        return Err(ElectionError::MissingShares);
+    }
+    for (index, share) in board.shares.iter().enumerate() {
+        let share = share.as_ref().ok_or(ElectionError::MissingShares)?;
+        verify_share(&election, &board.trustees[index], index, &tally, share)?;
+    }
+
+    let result = board.decrypt_result()?;
+    if board.result.as_ref() != Some(&result) {
+        return Err(ElectionError::ResultMismatch);
+    }
+    Ok(result)
+}
+
+fn verify_registration(
+    election: &Election,
+    index: usize,
+    registration: &TrusteeRegistration,
+) -> Result<(), ElectionError> {
+    let params = election.params();
+    let key = registration.key.instance();
+    let same_group = key.p() == params.p() && key.q() == params.q() && key.g() == params.g();
+    // A key outside the subgroup would take the election key, and every ballot, out of it.
+    if !same_group || !params.is_element(key.h()) {
+        return Err(ElectionError::KeyNotInGroup { index });
+    }
+    let context = election.context("trustee", &index.to_string());
+    registration
+        .key
+        .verify_knowledge(&registration.proof, &context)
+        .map_err(|error| ElectionError::InvalidTrusteeKey { index, error })
+}
+
+fn verify_share(
+    election: &Election,
+    trustee: &TrusteeRegistration,
+    index: usize,
+    tally: &Ciphertext,
+    share: &DecryptionShare,
+) -> Result<(), ElectionError> {
+    let context = election.context("decryption", &index.to_string());
+    share
+        .verify(&trustee.key, tally, &context)
+        .map_err(|error| ElectionError::InvalidDecryptionShare { index, error })
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{BigInt, Integer};
+
+    use crate::{
+        applications::elgamal::PublicKey,
+        fiat_shamir::NonInteractiveProof,
+        group::PrimeOrderGroup,
+        schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol, SchnorrVerifierError},
+    };
+
+    use super::{
+        verify_record, Ballot, BulletinBoard, Election, ElectionError, Trustee, TrusteeRegistration,
+    };
+
+    fn setup(trustee_count: usize) -> (BulletinBoard, Vec<Trustee>) {
+        let options = ["yes", "no", "abstain"];
+        let (params, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        setup_with(&options, 100, params, trustee_count)
+    }
+
+    fn setup_with(
+        options: &[&str],
+        max_voters: u64,
+        params: SchnorrDiscreteLogInstance,
+        trustee_count: usize,
+    ) -> (BulletinBoard, Vec<Trustee>) {
+        let election = Election::new(
+            "referendum 1".to_string(),
+            "Should we?".to_string(),
+            options.iter().map(|option| option.to_string()).collect(),
+            max_voters,
+            params,
+        )
+        .unwrap();
+
+        let mut board = BulletinBoard::new(election.clone());
+        let trustees = (0..trustee_count)
+            .map(|i| Trustee::new(&election, i))
+            .collect::<Vec<_>>();
+        for trustee in &trustees {
+            board
+                .register_trustee(trustee.registration(&election))
+                .unwrap();
+        }
+        (board, trustees)
+    }
+
+    fn vote(board: &mut BulletinBoard, voter: &str, option: usize) -> Result<(), ElectionError> {
+        let key = board.election_key().unwrap();
+        let ballot = Ballot::cast(board.election(), &key, voter.to_string(), option)?;
+        board.cast(ballot)
+    }
+
+    fn run_tally(board: &mut BulletinBoard, trustees: &[Trustee]) {
+        for (index, trustee) in trustees.iter().enumerate() {
+            let share = trustee.decrypt_tally(board);
+            board.post_share(index, share).unwrap();
+        }
+    }
+
+    #[test]
+    fn election_is_tallied_and_verified() {
+        let (mut board, trustees) = setup(3);
+        for (voter, option) in [
+            ("alice", 0),
+            ("bob", 1),
+            ("carol", 0),
+            ("dave", 2),
+            ("erin", 0),
+        ] {
+            vote(&mut board, voter, option).unwrap();
+        }
+        run_tally(&mut board, &trustees);
+        assert_eq!(board.result(), Some(&[3, 1, 1][..]));
+
+        let json = board.to_json().unwrap();
+        let record = BulletinBoard::from_json(&json).unwrap();
+        assert_eq!(verify_record(&record).unwrap(), vec![3, 1, 1]);
+    }
+
+    #[test]
+    fn invalid_votes_are_rejected() {
+        let (mut board, _) = setup(1);
+        vote(&mut board, "alice", 0).unwrap();
+        assert!(matches!(
+            vote(&mut board, "alice", 1),
+            Err(ElectionError::DuplicateVoter(_))
+        ));
+        assert!(matches!(
+            vote(&mut board, "bob", 3),
+            Err(ElectionError::InvalidOption(3))
+        ));
+
+        // Bob's ballot cannot be replayed by Carol.
+        let key = board.election_key().unwrap();
+        let mut ballot = Ballot::cast(board.election(), &key, "bob".to_string(), 1).unwrap();
+        ballot.voter = "carol".to_string();
+        assert!(matches!(
+            board.cast(ballot),
+            Err(ElectionError::InvalidBallot { .. })
+        ));
+    }
+
+    #[test]
+    fn ballot_for_two_votes_is_rejected() {
+        let (mut board, _) = setup(1);
+        let election = board.election().clone();
+        let key = board.election_key().unwrap();
+
+        // Encrypt two votes for "yes", with a proof for a single one.
+        let options = election.encoded_options(&key);
+        let double = key.encode(&BigInt::from(2));
+        let (ciphertext, r) = key.encrypt(&double);
+        let context = election.context("ballot", "mallory");
+        let proof = crate::applications::elgamal::PlaintextProof::prove(
+            &key,
+            &ciphertext,
+            r.expose(),
+            &options,
+            0,
+            &context,
+        );
+        let ballot = Ballot {
+            voter: "mallory".to_string(),
+            ciphertext,
+            proof,
+        };
+        assert!(matches!(
+            board.cast(ballot),
+            Err(ElectionError::InvalidBallot { .. })
+        ));
+    }
+
+    #[test]
+    fn tampered_record_is_rejected() {
+        let (mut board, trustees) = setup(2);
+        vote(&mut board, "alice", 0).unwrap();
+        vote(&mut board, "bob", 1).unwrap();
+        run_tally(&mut board, &trustees);
+        assert!(verify_record(&board).is_ok());
+
+        let mut tampered = board.clone();
+        tampered.result = Some(vec![2, 0, 0]);
+        assert!(matches!(
+            verify_record(&tampered),
+            Err(ElectionError::ResultMismatch)
+        ));
+
+        // Dropping a ballot changes the tally, so the shares no longer match it.
+        let mut tampered = board.clone();
+        tampered.ballots.pop();
+        assert!(matches!(
+            verify_record(&tampered),
+            Err(ElectionError::InvalidDecryptionShare { index: 0, .. })
+        ));
+
+        let mut tampered = board;
+        tampered.shares.swap(0, 1);
+        assert!(verify_record(&tampered).is_err());
+    }
+
+    #[test]
+    fn voting_closes_when_tally_starts() {
+        let (mut board, trustees) = setup(2);
+        vote(&mut board, "alice", 0).unwrap();
+        let share = trustees[0].decrypt_tally(&board);
+        board.post_share(0, share).unwrap();
+        assert!(matches!(
+            vote(&mut board, "bob", 1),
+            Err(ElectionError::VotingClosed)
+        ));
+        assert!(board.result().is_none());
+        assert!(matches!(
+            verify_record(&board),
+            Err(ElectionError::MissingShares)
+        ));
+    }
+
+    #[test]
+    fn large_election_is_tallied() {
+        // `M^2` is far above `2^64`, but the search only goes up to `ballots * M`.
+        let (params, _) = SchnorrDiscreteLogInstance::generate(256, 160);
+        let (mut board, trustees) = setup_with(&["yes", "no"], 1 << 33, params, 2);
+        for (voter, option) in [("alice", 1), ("bob", 0), ("carol", 1)] {
+            vote(&mut board, voter, option).unwrap();
+        }
+        run_tally(&mut board, &trustees);
+        assert_eq!(board.result(), Some(&[1, 2][..]));
+        assert_eq!(verify_record(&board).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn tally_too_large_to_decode_is_refused() {
+        let (params, _) = SchnorrDiscreteLogInstance::generate(256, 160);
+        let (mut board, trustees) = setup_with(&["yes", "no", "abstain"], 1 << 33, params, 1);
+        vote(&mut board, "alice", 2).unwrap();
+        let share = trustees[0].decrypt_tally(&board);
+        assert!(matches!(
+            board.post_share(0, share),
+            Err(ElectionError::TallyTooLarge)
+        ));
+    }
+
+    #[test]
+    fn record_with_invalid_group_is_rejected() {
+        let (mut board, trustees) = setup(1);
+        vote(&mut board, "alice", 0).unwrap();
+        run_tally(&mut board, &trustees);
+
+        let params = board.election.params.clone();
+        board.election.params = SchnorrDiscreteLogInstance::new(
+            params.p() + 1,
+            params.q().clone(),
+            params.g().clone(),
+            params.h().clone(),
+        );
+        assert!(matches!(
+            verify_record(&board),
+            Err(ElectionError::InvalidParams)
+        ));
+    }
+
+    #[test]
+    fn key_outside_the_subgroup_is_rejected() {
+        let (mut board, _) = setup(0);
+        let params = board.election.params.clone();
+        let group = params.group();
+
+        // `-g^x` is not in the subgroup, but passes the check of a proof for `x` whenever the
+        // challenge is even.
+        let x = group.random_scalar();
+        let h = params.p() - group.exp(params.g(), &x);
+        let instance = SchnorrDiscreteLogInstance::new(
+            params.p().clone(),
+            params.q().clone(),
+            params.g().clone(),
+            h,
+        );
+        let context = board.election.context("trustee", "0");
+        let bits = instance.challenge_bits();
+        let proof = loop {
+            let proof = NonInteractiveProof::prove::<SchnorrDiscreteLogProtocol, _, _>(
+                &context,
+                instance.clone(),
+                x.clone(),
+                bits,
+            );
+            if proof.challenge(&context, &instance, bits).is_even() {
+                break proof;
+            }
+        };
+        let registration = TrusteeRegistration {
+            key: PublicKey::new(instance),
+            proof,
+        };
+
+        assert!(matches!(
+            registration
+                .key
+                .verify_knowledge(&registration.proof, &context),
+            Err(SchnorrVerifierError::NotInGroup)
+        ));
+        assert!(matches!(
+            board.register_trustee(registration),
+            Err(ElectionError::KeyNotInGroup { index: 0 })
+        ));
+    }
+}
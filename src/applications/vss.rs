@@ -0,0 +1,597 @@
+//! Verifiable secret sharing, where a dealer splits a secret into shares so that any `threshold`
+//! of them reconstruct it, and fewer reveal nothing about it.
+//!
+//! The shares are points on a random polynomial of degree `threshold - 1` over `Z_q` whose
+//! constant term is the secret, as in Shamir's scheme. The dealer also publishes commitments to
+//! the coefficients, which every party checks its share against, so a dealer cannot hand out
+//! shares that do not fit together.
+//!
+//! - With Feldman commitments `g^a_i`, the commitment to the constant term is `g^secret`, so the
+//!   secret is only hidden computationally.
+//! - With Pedersen commitments `g^a_i h^b_i`, the secret is hidden perfectly, but each party
+//!   needs a second share of the blinding polynomial `b`.
+//!
+//! Parties are numbered from 1, as the share of party 0 would be the secret itself.
+
+use std::{collections::HashSet, io};
+
+use num::{BigInt, Integer, One, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    applications::commitments::PedersenScheme,
+    group::{PrimeOrderGroup, SchnorrGroup},
+    netutil::BinaryEncode,
+    schnorr::SchnorrDiscreteLogInstance,
+    secret::Wipe,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum VssError {
+    /// The threshold must be between 1 and the number of parties, and there must be fewer
+    /// parties than the order of the group so that their indices are distinct scalars.
+    InvalidThreshold {
+        threshold: usize,
+        parties: u32,
+    },
+    /// A commitment of the dealer is not an element of the group, or there are none.
+    InvalidCommitments {
+        dealer: u32,
+    },
+    /// The dealer committed to a polynomial for another threshold than the agreed one, so
+    /// that different sets of shares would reconstruct different secrets.
+    WrongThreshold {
+        dealer: u32,
+        expected: usize,
+        actual: usize,
+    },
+    /// The share the dealer gave to the party at `index` does not match its commitments.
+    InvalidShare {
+        dealer: u32,
+        index: u32,
+    },
+    /// Parties are numbered from 1.
+    InvalidIndex(u32),
+    DuplicateIndex(u32),
+    NotEnoughShares {
+        needed: usize,
+        actual: usize,
+    },
+}
+
+/// The share of the party at `index`, which is the value at `index` of the polynomial.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Share {
+    index: u32,
+    value: BigInt,
+}
+
+impl Share {
+    pub fn new(index: u32, value: BigInt) -> Self {
+        Share { index, value }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn value(&self) -> &BigInt {
+        &self.value
+    }
+}
+
+impl Wipe for Share {
+    fn wipe(&mut self) {
+        self.value.wipe();
+    }
+}
+
+impl BinaryEncode for Share {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.index.encode(int_len, out)?;
+        self.value.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let (index, value) = BinaryEncode::decode(int_len, input)?;
+        Ok(Share { index, value })
+    }
+}
+
+/// A share with Pedersen commitments, which also has the value of the blinding polynomial.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PedersenShare {
+    share: Share,
+    blinding: BigInt,
+}
+
+impl PedersenShare {
+    pub fn index(&self) -> u32 {
+        self.share.index
+    }
+
+    /// The share of the secret itself.
+    pub fn share(&self) -> &Share {
+        &self.share
+    }
+
+    pub fn blinding(&self) -> &BigInt {
+        &self.blinding
+    }
+}
+
+/// The commitments `g^a_i` of a dealer to the coefficients of its polynomial.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FeldmanCommitments {
+    dealer: u32,
+    commitments: Vec<BigInt>,
+}
+
+impl FeldmanCommitments {
+    /// The commitments `commitments` of `dealer`, as received from it. They are checked when a
+    /// share is verified against them.
+    pub fn new(dealer: u32, commitments: Vec<BigInt>) -> Self {
+        FeldmanCommitments {
+            dealer,
+            commitments,
+        }
+    }
+
+    /// Share `secret` among `parties` parties so that `threshold` of them can reconstruct it.
+    /// The dealer is identified by `dealer` in errors about its shares.
+    pub fn deal(
+        params: &SchnorrDiscreteLogInstance,
+        dealer: u32,
+        secret: &BigInt,
+        threshold: usize,
+        parties: u32,
+    ) -> Result<(Self, Vec<Share>), VssError> {
+        check_threshold(threshold, parties, params.q())?;
+        let group = params.group();
+        let polynomial = random_polynomial(&group, secret, threshold);
+        let commitments = polynomial
+            .iter()
+            .map(|a| group.exp_secret(params.g(), a))
+            .collect();
+        let shares = (1..=parties)
+            .map(|i| Share::new(i, evaluate(&polynomial, i, group.order())))
+            .collect();
+        Ok((
+            FeldmanCommitments {
+                dealer,
+                commitments,
+            },
+            shares,
+        ))
+    }
+
+    pub fn dealer(&self) -> u32 {
+        self.dealer
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    pub fn commitments(&self) -> &[BigInt] {
+        &self.commitments
+    }
+
+    /// The commitment `g^secret` to the secret, if there are any commitments at all.
+    pub fn public_value(&self) -> Option<&BigInt> {
+        self.commitments.first()
+    }
+
+    /// The value `g^s` for the share `s` of the party at `index`, which anyone can compute.
+    pub fn public_share(
+        &self,
+        params: &SchnorrDiscreteLogInstance,
+        index: u32,
+    ) -> Result<BigInt, VssError> {
+        let group = params.group();
+        self.check_commitments(&group)?;
+        if index == 0 {
+            return Err(VssError::InvalidIndex(index));
+        }
+        Ok(evaluate_in_exponent(&group, &self.commitments, index))
+    }
+
+    /// Check `share` against the commitments, which must be to a polynomial for `threshold`
+    /// parties.
+    pub fn verify(
+        &self,
+        params: &SchnorrDiscreteLogInstance,
+        threshold: usize,
+        share: &Share,
+    ) -> Result<(), VssError> {
+        check_commitment_count(self.dealer, threshold, self.commitments.len())?;
+        let expected = self.public_share(params, share.index)?;
+        if params.group().exp_secret(params.g(), &share.value) != expected {
+            return Err(VssError::InvalidShare {
+                dealer: self.dealer,
+                index: share.index,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_commitments(&self, group: &SchnorrGroup) -> Result<(), VssError> {
+        if self.commitments.is_empty() || !self.commitments.iter().all(|c| group.is_element(c)) {
+            return Err(VssError::InvalidCommitments {
+                dealer: self.dealer,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl BinaryEncode for FeldmanCommitments {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.dealer.encode(int_len, out)?;
+        self.commitments.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let (dealer, commitments) = BinaryEncode::decode(int_len, input)?;
+        Ok(FeldmanCommitments {
+            dealer,
+            commitments,
+        })
+    }
+}
+
+/// The commitments `g^a_i h^b_i` of a dealer to the coefficients of its polynomial and of the
+/// blinding polynomial.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PedersenCommitments {
+    dealer: u32,
+    commitments: Vec<BigInt>,
+}
+
+impl PedersenCommitments {
+    /// Share `secret` among `parties` parties so that `threshold` of them can reconstruct it.
+    /// The dealer is identified by `dealer` in errors about its shares.
+    pub fn deal(
+        scheme: &PedersenScheme,
+        dealer: u32,
+        secret: &BigInt,
+        threshold: usize,
+        parties: u32,
+    ) -> Result<(Self, Vec<PedersenShare>), VssError> {
+        check_threshold(threshold, parties, scheme.params().q())?;
+        let group = scheme.params().group();
+        let polynomial = random_polynomial(&group, secret, threshold);
+        let blinding = random_polynomial(&group, &group.random_scalar(), threshold);
+        let commitments = polynomial
+            .iter()
+            .zip(&blinding)
+            .map(|(a, b)| scheme.commit_with(a, b))
+            .collect();
+        let shares = (1..=parties)
+            .map(|i| PedersenShare {
+                share: Share::new(i, evaluate(&polynomial, i, group.order())),
+                blinding: evaluate(&blinding, i, group.order()),
+            })
+            .collect();
+        Ok((
+            PedersenCommitments {
+                dealer,
+                commitments,
+            },
+            shares,
+        ))
+    }
+
+    pub fn dealer(&self) -> u32 {
+        self.dealer
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    pub fn commitments(&self) -> &[BigInt] {
+        &self.commitments
+    }
+
+    /// Check `share` against the commitments, which must be to polynomials for `threshold`
+    /// parties.
+    pub fn verify(
+        &self,
+        scheme: &PedersenScheme,
+        threshold: usize,
+        share: &PedersenShare,
+    ) -> Result<(), VssError> {
+        check_commitment_count(self.dealer, threshold, self.commitments.len())?;
+        let group = scheme.params().group();
+        if self.commitments.is_empty() || !self.commitments.iter().all(|c| scheme.is_element(c)) {
+            return Err(VssError::InvalidCommitments {
+                dealer: self.dealer,
+            });
+        }
+        let index = share.index();
+        if index == 0 {
+            return Err(VssError::InvalidIndex(index));
+        }
+
+        let expected = evaluate_in_exponent(&group, &self.commitments, index);
+        if scheme.commit_with(&share.share.value, &share.blinding) != expected {
+            return Err(VssError::InvalidShare {
+                dealer: self.dealer,
+                index,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A dealer for `threshold` parties commits to the `threshold` coefficients of its polynomial.
+fn check_commitment_count(dealer: u32, threshold: usize, actual: usize) -> Result<(), VssError> {
+    if actual != threshold {
+        return Err(VssError::WrongThreshold {
+            dealer,
+            expected: threshold,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Reconstruct the secret from at least `threshold` distinct shares. The shares are not
+/// verified, so they should have been checked against the commitments of the dealer first.
+pub fn reconstruct(q: &BigInt, shares: &[Share], threshold: usize) -> Result<BigInt, VssError> {
+    if shares.len() < threshold || threshold == 0 {
+        return Err(VssError::NotEnoughShares {
+            needed: threshold.max(1),
+            actual: shares.len(),
+        });
+    }
+    let shares = &shares[..threshold];
+    let indices = shares.iter().map(Share::index).collect::<Vec<_>>();
+    check_indices(&indices)?;
+
+    Ok(shares.iter().fold(BigInt::zero(), |acc, share| {
+        let lambda = lagrange_coefficient(q, share.index, &indices);
+        (acc + lambda * &share.value).mod_floor(q)
+    }))
+}
+
+/// The Lagrange coefficient of the party at `index` for interpolating at 0 from the values of
+/// the parties at `indices`, so that `f(0) = sum_i lambda_i f(i)` for any polynomial `f` of
+/// degree less than the number of indices.
+///
+/// The indices must be distinct, non-zero and contain `index`.
+pub fn lagrange_coefficient(q: &BigInt, index: u32, indices: &[u32]) -> BigInt {
+    let (numerator, denominator) = indices.iter().filter(|&&j| j != index).fold(
+        (BigInt::one(), BigInt::one()),
+        |(num, den), &j| {
+            let j = BigInt::from(j);
+            let diff = &j - index;
+            ((num * j).mod_floor(q), (den * diff).mod_floor(q))
+        },
+    );
+    let inverse = denominator.modpow(&(q - 2u32), q);
+    (numerator * inverse).mod_floor(q)
+}
+
+pub(crate) fn check_threshold(threshold: usize, parties: u32, q: &BigInt) -> Result<(), VssError> {
+    if threshold == 0 || threshold > parties as usize || BigInt::from(parties) >= *q {
+        return Err(VssError::InvalidThreshold { threshold, parties });
+    }
+    Ok(())
+}
+
+fn check_indices(indices: &[u32]) -> Result<(), VssError> {
+    let mut seen = HashSet::new();
+    for &index in indices {
+        if index == 0 {
+            return Err(VssError::InvalidIndex(index));
+        }
+        if !seen.insert(index) {
+            return Err(VssError::DuplicateIndex(index));
+        }
+    }
+    Ok(())
+}
+
+/// A polynomial of degree `threshold - 1` with constant term `secret`, as its coefficients.
+fn random_polynomial(group: &SchnorrGroup, secret: &BigInt, threshold: usize) -> Vec<BigInt> {
+    let mut coefficients = vec![secret.mod_floor(group.order())];
+    coefficients.extend((1..threshold).map(|_| group.random_scalar()));
+    coefficients
+}
+
+fn evaluate(coefficients: &[BigInt], x: u32, q: &BigInt) -> BigInt {
+    coefficients
+        .iter()
+        .rev()
+        .fold(BigInt::zero(), |acc, a| (acc * x + a).mod_floor(q))
+}
+
+/// The product of `commitments[j]^(x^j)`, which commits to the value of the polynomial at `x`.
+fn evaluate_in_exponent(group: &SchnorrGroup, commitments: &[BigInt], x: u32) -> BigInt {
+    let mut power = BigInt::one();
+    let exponents = commitments
+        .iter()
+        .map(|_| {
+            let current = power.clone();
+            power = (&power * x).mod_floor(group.order());
+            current
+        })
+        .collect::<Vec<_>>();
+    group.multi_exp(commitments, &exponents)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::commitments::{CommitmentScheme, PedersenScheme},
+        schnorr::SchnorrDiscreteLogInstance,
+    };
+
+    use super::{reconstruct, FeldmanCommitments, PedersenCommitments, Share, VssError};
+
+    fn make_params() -> SchnorrDiscreteLogInstance {
+        SchnorrDiscreteLogInstance::new(
+            BigInt::from(1907),
+            BigInt::from(953),
+            BigInt::from(343),
+            BigInt::from(1429),
+        )
+    }
+
+    #[test]
+    fn feldman_shares_verify_and_reconstruct() {
+        let params = make_params();
+        let secret = BigInt::from(121);
+        let (commitments, shares) = FeldmanCommitments::deal(&params, 7, &secret, 3, 5).unwrap();
+
+        for share in &shares {
+            assert_eq!(commitments.verify(&params, 3, share), Ok(()));
+        }
+        assert_eq!(reconstruct(params.q(), &shares[2..], 3), Ok(secret.clone()));
+        assert_eq!(
+            reconstruct(
+                params.q(),
+                &[shares[4].clone(), shares[0].clone(), shares[2].clone()],
+                3
+            ),
+            Ok(secret)
+        );
+        assert_eq!(
+            reconstruct(params.q(), &shares[..2], 3),
+            Err(VssError::NotEnoughShares {
+                needed: 3,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn bad_share_names_the_dealer() {
+        let params = make_params();
+        let (commitments, shares) =
+            FeldmanCommitments::deal(&params, 7, &BigInt::from(121), 2, 3).unwrap();
+
+        let bad = Share::new(2, shares[1].value() + 1);
+        assert_eq!(
+            commitments.verify(&params, 2, &bad),
+            Err(VssError::InvalidShare {
+                dealer: 7,
+                index: 2
+            })
+        );
+        assert_eq!(
+            commitments.verify(&params, 2, &Share::new(0, BigInt::from(121))),
+            Err(VssError::InvalidIndex(0))
+        );
+    }
+
+    #[test]
+    fn dealer_with_too_many_commitments_is_rejected() {
+        // The shares of a polynomial of degree 2 match its commitments, but pairs of them
+        // would reconstruct different secrets.
+        let params = make_params();
+        let (commitments, shares) =
+            FeldmanCommitments::deal(&params, 7, &BigInt::from(121), 3, 5).unwrap();
+        for share in &shares {
+            assert_eq!(
+                commitments.verify(&params, 2, share),
+                Err(VssError::WrongThreshold {
+                    dealer: 7,
+                    expected: 2,
+                    actual: 3
+                })
+            );
+        }
+
+        let scheme = PedersenScheme::new(CommitmentScheme::gen_params(256, 64));
+        let (commitments, shares) =
+            PedersenCommitments::deal(&scheme, 3, &BigInt::from(5), 3, 4).unwrap();
+        assert_eq!(
+            commitments.verify(&scheme, 2, &shares[0]),
+            Err(VssError::WrongThreshold {
+                dealer: 3,
+                expected: 2,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn pedersen_shares_verify_and_reconstruct() {
+        let scheme = PedersenScheme::new(CommitmentScheme::gen_params(256, 64));
+        let secret = BigInt::from(123456789);
+        let (commitments, shares) = PedersenCommitments::deal(&scheme, 3, &secret, 2, 4).unwrap();
+
+        for share in &shares {
+            assert_eq!(commitments.verify(&scheme, 2, share), Ok(()));
+        }
+        let values = shares
+            .iter()
+            .skip(1)
+            .map(|s| s.share().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(reconstruct(scheme.params().q(), &values, 2), Ok(secret));
+
+        let mut bad = shares[3].clone();
+        bad.blinding += 1;
+        assert_eq!(
+            commitments.verify(&scheme, 2, &bad),
+            Err(VssError::InvalidShare {
+                dealer: 3,
+                index: 4
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        let params = make_params();
+        let secret = BigInt::from(5);
+        assert_eq!(
+            FeldmanCommitments::deal(&params, 1, &secret, 4, 3).unwrap_err(),
+            VssError::InvalidThreshold {
+                threshold: 4,
+                parties: 3
+            }
+        );
+        let shares = [
+            Share::new(1, BigInt::from(3)),
+            Share::new(1, BigInt::from(4)),
+        ];
+        assert_eq!(
+            reconstruct(params.q(), &shares, 2),
+            Err(VssError::DuplicateIndex(1))
+        );
+
+        // With 11 parties in a group of order 11, the last one would get the secret itself.
+        let small = SchnorrDiscreteLogInstance::new(
+            BigInt::from(23),
+            BigInt::from(11),
+            BigInt::from(2),
+            BigInt::from(4),
+        );
+        assert!(FeldmanCommitments::deal(&small, 1, &secret, 2, 10).is_ok());
+        assert_eq!(
+            FeldmanCommitments::deal(&small, 1, &secret, 2, 11).unwrap_err(),
+            VssError::InvalidThreshold {
+                threshold: 2,
+                parties: 11
+            }
+        );
+    }
+
+    #[test]
+    fn empty_commitments_have_no_public_value() {
+        let params = make_params();
+        let commitments = FeldmanCommitments::new(1, vec![]);
+        assert_eq!(commitments.public_value(), None);
+        assert_eq!(
+            commitments.public_share(&params, 1),
+            Err(VssError::InvalidCommitments { dealer: 1 })
+        );
+    }
+}
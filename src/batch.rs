@@ -0,0 +1,345 @@
+//! Batch verification of Schnorr proofs and signatures.
+//!
+//! Each proof `(a, e, z)` for a key `h` is checked as `g^z = a h^e`. Instead of checking them
+//! one by one, the verifier picks random coefficients `d_i` and checks the single equation
+//!
+//! `g^(d_1 z_1 + ... + d_n z_n) = a_1^d_1 ... a_n^d_n h_1^(d_1 e_1) ... h_n^(d_n e_n)`,
+//!
+//! with multi-exponentiations. Proofs under the same key share its term, and the exponents
+//! `d_i` only have 128 bits, so this is much cheaper than `2n` full exponentiations when there
+//! are few keys. If any proof is invalid, the equation fails except with probability
+//! `2^-128`, and the batch is split in halves until the invalid proofs are found.
+//!
+//! The coefficients only rule out errors in the subgroup of order `q`. An initial message or
+//! key that is off by an element of small order from `Z_p^*`, such as `-1`, would pass in a
+//! batch whenever its coefficient is a multiple of that order. Both are therefore checked to be
+//! in the group as items are added, which is cheap for safe primes, and each key only once.
+
+use std::collections::{HashMap, HashSet};
+
+use num::{bigint::RandBigInt, BigInt, Signed, Zero};
+
+use crate::{
+    applications::signatures::{self, Signature},
+    fiat_shamir::NonInteractiveProof,
+    group::{PrimeOrderGroup, SchnorrGroup},
+    schnorr::SchnorrDiscreteLogInstance,
+};
+
+/// The number of bits of the random coefficients.
+const COEFFICIENT_BITS: usize = 128;
+
+#[derive(Debug, PartialEq)]
+pub enum BatchError {
+    /// The instance is not in the group of the batch.
+    WrongGroup,
+    /// The key or the initial message of the item is not an element of the group.
+    InvalidElement,
+    /// The response of a signature is not in `[0, q)`, which [`signatures::verify`] rejects.
+    ResponseOutOfRange,
+    /// The items at these indices, in the order they were added, are invalid. All other items
+    /// are valid.
+    InvalidItems(Vec<usize>),
+}
+
+/// One equation `g^z = a h^e`.
+struct Item {
+    key: BigInt,
+    a: BigInt,
+    e: BigInt,
+    z: BigInt,
+}
+
+/// A batch of Schnorr proofs and signatures in one group.
+pub struct SchnorrBatch {
+    group: SchnorrGroup,
+    items: Vec<Item>,
+    /// The keys that are known to be in the group.
+    checked_keys: HashSet<BigInt>,
+}
+
+impl SchnorrBatch {
+    pub fn new(group: SchnorrGroup) -> Self {
+        SchnorrBatch {
+            group,
+            items: Vec::new(),
+            checked_keys: HashSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Add the transcript `(a, e, z)` of [`SchnorrDiscreteLogProtocol`] for `instance`.
+    ///
+    /// [`SchnorrDiscreteLogProtocol`]: crate::schnorr::SchnorrDiscreteLogProtocol
+    pub fn add_transcript(
+        &mut self,
+        instance: &SchnorrDiscreteLogInstance,
+        a: BigInt,
+        e: BigInt,
+        z: BigInt,
+    ) -> Result<(), BatchError> {
+        if instance.group() != self.group {
+            return Err(BatchError::WrongGroup);
+        }
+        self.push(instance.h(), a, e, z)
+    }
+
+    /// Add a non-interactive proof for `instance` in `context`.
+    pub fn add_proof(
+        &mut self,
+        instance: &SchnorrDiscreteLogInstance,
+        proof: &NonInteractiveProof<BigInt, BigInt>,
+        context: &[u8],
+    ) -> Result<(), BatchError> {
+        let e = proof.challenge(context, instance, instance.challenge_bits());
+        self.add_transcript(
+            instance,
+            proof.initial_msg().clone(),
+            e,
+            proof.response().clone(),
+        )
+    }
+
+    /// Add a [`Signature`] on `message` under `public_key`.
+    pub fn add_signature(
+        &mut self,
+        public_key: &BigInt,
+        message: &[u8],
+        signature: &Signature<BigInt>,
+    ) -> Result<(), BatchError> {
+        let z = signature.z();
+        if z.is_negative() || z >= self.group.order() {
+            return Err(BatchError::ResponseOutOfRange);
+        }
+        let e = signatures::challenge(&self.group, signature.r(), public_key, message);
+        self.push(public_key, signature.r().clone(), e, signature.z().clone())
+    }
+
+    fn push(&mut self, key: &BigInt, a: BigInt, e: BigInt, z: BigInt) -> Result<(), BatchError> {
+        if !self.checked_keys.contains(key) {
+            if !self.group.is_element(key) {
+                return Err(BatchError::InvalidElement);
+            }
+            self.checked_keys.insert(key.clone());
+        }
+        if !self.group.is_element(&a) {
+            return Err(BatchError::InvalidElement);
+        }
+        self.items.push(Item {
+            key: key.clone(),
+            a,
+            e,
+            z,
+        });
+        Ok(())
+    }
+
+    /// Check all items, and find the invalid ones if there are any.
+    pub fn verify(&self) -> Result<(), BatchError> {
+        let mut invalid = Vec::new();
+        self.bisect(&(0..self.items.len()).collect::<Vec<_>>(), &mut invalid);
+        if invalid.is_empty() {
+            return Ok(());
+        }
+        invalid.sort_unstable();
+        Err(BatchError::InvalidItems(invalid))
+    }
+
+    /// Add the invalid items among `indices` to `invalid`.
+    fn bisect(&self, indices: &[usize], invalid: &mut Vec<usize>) {
+        if indices.is_empty() || self.check(indices) {
+            return;
+        }
+        if let [index] = indices {
+            invalid.push(*index);
+            return;
+        }
+        let (left, right) = indices.split_at(indices.len() / 2);
+        self.bisect(left, invalid);
+        self.bisect(right, invalid);
+    }
+
+    /// Check the items at `indices` with one random linear combination.
+    fn check(&self, indices: &[usize]) -> bool {
+        let group = &self.group;
+        let q = group.order();
+        // A single item needs no coefficient, and keeps the answer of an individual check.
+        let bits = if indices.len() == 1 {
+            0
+        } else {
+            COEFFICIENT_BITS.min(q.bits() - 1)
+        };
+        let mut rng = rand::thread_rng();
+
+        let mut g_exponent = BigInt::zero();
+        let mut key_exponents: HashMap<&BigInt, BigInt> = HashMap::new();
+        let mut nonces = Vec::with_capacity(indices.len());
+        let mut coefficients = Vec::with_capacity(indices.len());
+        for &index in indices {
+            let Item { key, a, e, z } = &self.items[index];
+            let d = if bits == 0 {
+                BigInt::from(1)
+            } else {
+                rng.gen_bigint_range(&BigInt::from(1), &(BigInt::from(1) << bits))
+            };
+            g_exponent += &d * z;
+            *key_exponents.entry(key).or_insert_with(BigInt::zero) -= &d * e;
+            nonces.push(a.clone());
+            coefficients.push(d);
+        }
+
+        let mut bases = vec![group.generator()];
+        let mut exponents = vec![g_exponent];
+        for (key, exponent) in key_exponents {
+            bases.push(key.clone());
+            exponents.push(exponent);
+        }
+        // The short coefficients stay on their own side, where they are not reduced modulo `q`.
+        group.multi_exp(&bases, &exponents) == group.multi_exp(&nonces, &coefficients)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        applications::signatures::{generate_key, sign, Signature},
+        fiat_shamir::NonInteractiveProof,
+        group::PrimeOrderGroup,
+        schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol},
+    };
+
+    use super::{BatchError, SchnorrBatch};
+
+    fn make_proofs(n: usize) -> (SchnorrBatch, Vec<SchnorrDiscreteLogInstance>) {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let group = instance.group();
+        let mut batch = SchnorrBatch::new(group.clone());
+        let mut instances = Vec::new();
+        for i in 0..n {
+            // Alternate between one shared key and fresh ones.
+            let (instance, w) = if i % 2 == 0 {
+                (instance.clone(), w.clone())
+            } else {
+                let (x, h) = generate_key(&group);
+                let params = &instance;
+                let instance = SchnorrDiscreteLogInstance::new(
+                    params.p().clone(),
+                    params.q().clone(),
+                    params.g().clone(),
+                    h,
+                );
+                (instance, x.expose().clone())
+            };
+            let bits = instance.challenge_bits();
+            let proof = NonInteractiveProof::prove::<SchnorrDiscreteLogProtocol, _, _>(
+                b"batch",
+                instance.clone(),
+                w,
+                bits,
+            );
+            batch.add_proof(&instance, &proof, b"batch").unwrap();
+            instances.push(instance);
+        }
+        (batch, instances)
+    }
+
+    #[test]
+    fn valid_batch_is_accepted() {
+        let (mut batch, instances) = make_proofs(9);
+        let group = instances[0].group();
+        let (x, public_key) = generate_key(&group);
+        for message in [&b"one"[..], b"two"] {
+            batch
+                .add_signature(&public_key, message, &sign(&group, x.expose(), message))
+                .unwrap();
+        }
+        assert_eq!(batch.len(), 11);
+        assert_eq!(batch.verify(), Ok(()));
+        assert_eq!(SchnorrBatch::new(group).verify(), Ok(()));
+    }
+
+    #[test]
+    fn invalid_items_are_found() {
+        let (mut batch, instances) = make_proofs(10);
+        let group = instances[0].group();
+        let (x, public_key) = generate_key(&group);
+        let signature = sign(&group, x.expose(), b"message");
+        batch
+            .add_signature(&public_key, b"other message", &signature)
+            .unwrap();
+        batch
+            .add_signature(&public_key, b"message", &signature)
+            .unwrap();
+        batch.items[3].z += 1;
+        batch.items[6].a = BigInt::from(1);
+
+        assert_eq!(
+            batch.verify(),
+            Err(BatchError::InvalidItems(vec![3, 6, 10]))
+        );
+    }
+
+    #[test]
+    fn elements_outside_group_are_refused() {
+        // `-1` has order 2, and would pass whenever its coefficient is even.
+        let (mut batch, instances) = make_proofs(1);
+        let group = instances[0].group();
+        let minus_one = group.modulus() - 1;
+        let (x, public_key) = generate_key(&group);
+        let signature = sign(&group, x.expose(), b"message");
+        assert_eq!(
+            batch.add_signature(&minus_one, b"message", &signature),
+            Err(BatchError::InvalidElement)
+        );
+
+        let negated = Signature::new(group.mul(signature.r(), &minus_one), signature.z().clone());
+        assert_eq!(
+            batch.add_signature(&public_key, b"message", &negated),
+            Err(BatchError::InvalidElement)
+        );
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[test]
+    fn signature_response_out_of_range_is_refused() {
+        let (mut batch, instances) = make_proofs(1);
+        let group = instances[0].group();
+        let (x, public_key) = generate_key(&group);
+        let signature = sign(&group, x.expose(), b"message");
+        let q = group.order();
+        for z in [signature.z() + q, signature.z() - q] {
+            let malleated = Signature::new(signature.r().clone(), z);
+            assert_eq!(
+                batch.add_signature(&public_key, b"message", &malleated),
+                Err(BatchError::ResponseOutOfRange)
+            );
+        }
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[test]
+    fn instance_from_other_group_is_refused() {
+        let (mut batch, _) = make_proofs(1);
+        let (other, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let bits = other.challenge_bits();
+        let proof = NonInteractiveProof::prove::<SchnorrDiscreteLogProtocol, _, _>(
+            b"batch",
+            other.clone(),
+            w,
+            bits,
+        );
+        assert_eq!(
+            batch.add_proof(&other, &proof, b"batch"),
+            Err(BatchError::WrongGroup)
+        );
+    }
+}
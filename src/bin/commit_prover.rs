@@ -1,31 +1,42 @@
 use std::{io, net::TcpListener};
 
 use sigma::applications::commitments::{encode, CommitmentScheme};
-use sigma::netutil::{read_value_from_stream, write_value_to_stream};
+use sigma::netutil::{Channel, Codec, IntKind, Limits, Role};
+use sigma::secret::Secret;
 
 fn main() -> io::Result<()> {
     println!("Enter a value to commit to: ");
-    let mut buf = String::new();
-    io::stdin().read_line(&mut buf)?;
+    let mut buf = Secret::new(String::new());
+    io::stdin().read_line(buf.expose_mut())?;
 
-    let encoded = encode(buf);
+    let encoded = Secret::new(encode(buf.expose()));
 
     let listener = TcpListener::bind("127.0.0.1:8080")?;
     println!("Listening for a connection on: {}", listener.local_addr()?);
 
-    let (mut stream, addr) = listener.accept()?;
+    let (stream, addr) = listener.accept()?;
     println!("Received connection from: {}", addr);
 
-    let instance = read_value_from_stream(&mut stream)?;
+    // The verifier waits for us to open the commitment, which may take a while.
+    let limits = Limits {
+        read_timeout: None,
+        ..Limits::default()
+    };
+    let mut channel = Channel::with_limits(stream, Codec::Json, limits);
+
+    let instance = channel.receive(IntKind::Element)?;
+    channel.secure(&instance, Role::Responder)?;
+    let session = channel.start_session(Role::Responder, true)?;
     let scheme = CommitmentScheme::new(instance);
 
-    let (a, z) = scheme.commit(&encoded);
-    write_value_to_stream(&mut stream, &a)?;
+    let (a, z) = scheme.commit_in_session(encoded.expose(), &session);
+    channel.send(&a, IntKind::Element)?;
 
     let mut buf = String::new();
     io::stdin().read_line(&mut buf)?;
 
-    write_value_to_stream(&mut stream, &(encoded, z))?;
+    let opening = Secret::new((encoded.expose().clone(), z.expose().clone()));
+    channel.send(opening.expose(), IntKind::Scalar)?;
 
     Ok(())
 }
@@ -1,7 +1,7 @@
 use std::{io, net::TcpStream};
 
 use sigma::applications::commitments::CommitmentScheme;
-use sigma::netutil::{read_value_from_stream, write_value_to_stream};
+use sigma::netutil::{Channel, Codec, IntKind, Limits, Role};
 
 fn main() -> io::Result<()> {
     println!("Creating instance...");
@@ -12,15 +12,22 @@ fn main() -> io::Result<()> {
     io::stdin().read_line(&mut buf)?;
     let addr = buf.trim();
 
-    let mut stream = TcpStream::connect(addr)?;
+    // The prover decides when to open the commitment, which may take a while.
+    let limits = Limits {
+        read_timeout: None,
+        ..Limits::default()
+    };
+    let mut channel = Channel::with_limits(TcpStream::connect(addr)?, Codec::Json, limits);
 
-    write_value_to_stream(&mut stream, &instance)?;
+    channel.send(&instance, IntKind::Element)?;
+    channel.secure(&instance, Role::Initiator)?;
+    let session = channel.start_session(Role::Initiator, true)?;
     let scheme = CommitmentScheme::new(instance);
 
-    let a = read_value_from_stream(&mut stream)?;
-    let (e, z) = read_value_from_stream(&mut stream)?;
+    let a = channel.receive(IntKind::Element)?;
+    let (e, z) = channel.receive(IntKind::Scalar)?;
 
-    assert!(scheme.verify(&a, &e, &z));
+    assert!(scheme.verify_in_session(&a, &e, &z, &session));
 
     Ok(())
 }
@@ -0,0 +1,37 @@
+use std::{env, fs, process};
+
+use sigma::applications::voting::{verify_record, BulletinBoard};
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: election_verifier <record.json>");
+            process::exit(2);
+        }
+    };
+
+    let json = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Could not read {}: {}", path, e);
+        process::exit(2);
+    });
+    let board = BulletinBoard::from_json(&json).unwrap_or_else(|e| {
+        eprintln!("Could not parse the record: {}", e);
+        process::exit(2);
+    });
+
+    match verify_record(&board) {
+        Ok(counts) => {
+            let election = board.election();
+            println!("Election {}: {}", election.id(), election.question());
+            println!("{} ballots, all valid.", board.ballots().len());
+            for (option, count) in election.options().iter().zip(counts) {
+                println!("  {}: {}", option, count);
+            }
+        }
+        Err(e) => {
+            println!("The record is invalid: {:?}", e);
+            process::exit(1);
+        }
+    }
+}
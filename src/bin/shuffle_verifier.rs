@@ -0,0 +1,53 @@
+use std::{env, fs, process};
+
+use serde::de::DeserializeOwned;
+use sigma::applications::{elgamal::PublicKey, shuffle::ShuffleRecord};
+
+fn read_json<T: DeserializeOwned>(path: &str) -> T {
+    let json = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Could not read {}: {}", path, e);
+        process::exit(2);
+    });
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        eprintln!("Could not parse {}: {}", path, e);
+        process::exit(2);
+    })
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() < 2 {
+        eprintln!("Usage: shuffle_verifier <key.json> <record.json>...");
+        process::exit(2);
+    }
+
+    // The key of the mix-net, which every record must use. A record cannot be trusted to name
+    // its own key, as anyone could shuffle under a key of their choosing.
+    let key: PublicKey = read_json(&args[0]);
+    let paths = &args[1..];
+    let records = paths
+        .iter()
+        .map(|path| read_json::<ShuffleRecord>(path))
+        .collect::<Vec<_>>();
+
+    for (i, (path, record)) in paths.iter().zip(&records).enumerate() {
+        // Each shuffle of a mix-net must start from the outputs of the one before it.
+        if i > 0 && records[i - 1].outputs() != record.inputs() {
+            println!(
+                "{}: the inputs are not the outputs of the previous shuffle.",
+                path
+            );
+            process::exit(1);
+        }
+        if let Err(e) = record.verify_for(&key) {
+            println!("{}: the shuffle is invalid: {:?}", path, e);
+            process::exit(1);
+        }
+        println!(
+            "{}: shuffle of {} ciphertexts in context {:?} is valid.",
+            path,
+            record.inputs().len(),
+            record.context()
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! The Chaum-Pedersen protocol, for proving that two discrete logs are equal.
+//!
+//! The prover knows `w` such that `h1 = g1^w` and `h2 = g2^w`. This shows for example that an
+//! ElGamal ciphertext was decrypted correctly, without revealing the secret key.
+
+use num::{bigint::RandBigInt, BigInt, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    group::{PrimeOrderGroup, SchnorrGroup},
+    secret::Secret,
+    ChallengeSpace, SigmaProtocol,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DleqInstance {
+    group: SchnorrGroup,
+    g1: BigInt,
+    h1: BigInt,
+    g2: BigInt,
+    h2: BigInt,
+}
+
+impl DleqInstance {
+    pub fn new(group: SchnorrGroup, g1: BigInt, h1: BigInt, g2: BigInt, h2: BigInt) -> Self {
+        DleqInstance {
+            group,
+            g1,
+            h1,
+            g2,
+            h2,
+        }
+    }
+
+    pub fn group(&self) -> &SchnorrGroup {
+        &self.group
+    }
+
+    /// The number of bits in the challenges of [`DleqProtocol`].
+    pub fn challenge_bits(&self) -> usize {
+        self.group.order().bits() - 1
+    }
+}
+
+impl ChallengeSpace for DleqInstance {
+    fn challenge_bits(&self) -> usize {
+        Self::challenge_bits(self)
+    }
+}
+
+pub struct DleqProtocol {
+    instance: DleqInstance,
+    witness: Option<Secret<BigInt>>,
+    random_exponent: Option<Secret<BigInt>>,
+}
+
+#[derive(Debug)]
+pub enum DleqVerifierError {
+    /// A value in the instance or the initial message is not an element of the group.
+    NotInGroup,
+    FirstExpressionsNotEqual {
+        lhs: BigInt,
+        rhs: BigInt,
+    },
+    SecondExpressionsNotEqual {
+        lhs: BigInt,
+        rhs: BigInt,
+    },
+}
+
+impl SigmaProtocol<DleqInstance, BigInt, (BigInt, BigInt), BigInt, BigInt> for DleqProtocol {
+    type VerifierError = DleqVerifierError;
+
+    fn new(instance: DleqInstance, witness: Option<BigInt>) -> Self {
+        DleqProtocol {
+            instance,
+            witness: witness.map(Secret::new),
+            random_exponent: None,
+        }
+    }
+
+    fn initial_message(&mut self) -> (BigInt, BigInt) {
+        let group = &self.instance.group;
+        let r = Secret::new(group.random_scalar());
+        let a = (
+            group.exp_secret(&self.instance.g1, r.expose()),
+            group.exp_secret(&self.instance.g2, r.expose()),
+        );
+        self.random_exponent = Some(r);
+        a
+    }
+
+    fn challenge(&mut self) -> BigInt {
+        let ubound = BigInt::from(1) << self.instance.challenge_bits();
+        rand::thread_rng().gen_bigint_range(&BigInt::zero(), &ubound)
+    }
+
+    fn challenge_response(&mut self, challenge: &BigInt) -> BigInt {
+        let r = self
+            .random_exponent
+            .as_ref()
+            .expect("Random exponent 'r' is not yet defined.");
+        let w = self
+            .witness
+            .as_ref()
+            .expect("Witness 'w' is not yet defined.");
+
+        self.instance
+            .group
+            .response(r.expose(), challenge, w.expose())
+    }
+
+    fn check(
+        &mut self,
+        initial_msg: (BigInt, BigInt),
+        challenge: BigInt,
+        response: BigInt,
+    ) -> Result<(), Self::VerifierError> {
+        let DleqInstance {
+            group,
+            g1,
+            h1,
+            g2,
+            h2,
+        } = &self.instance;
+        let (a1, a2) = initial_msg;
+        if ![g1, h1, g2, h2, &a1, &a2]
+            .into_iter()
+            .all(|x| group.is_element(x))
+        {
+            return Err(DleqVerifierError::NotInGroup);
+        }
+
+        let lhs = group.exp(g1, &response);
+        let rhs = group.mul(&a1, &group.exp(h1, &challenge));
+        if lhs != rhs {
+            return Err(DleqVerifierError::FirstExpressionsNotEqual { lhs, rhs });
+        }
+
+        let lhs = group.exp(g2, &response);
+        let rhs = group.mul(&a2, &group.exp(h2, &challenge));
+        if lhs != rhs {
+            return Err(DleqVerifierError::SecondExpressionsNotEqual { lhs, rhs });
+        }
+
+        Ok(())
+    }
+
+    fn simulate(&mut self, challenge: &BigInt) -> ((BigInt, BigInt), BigInt) {
+        let group = &self.instance.group;
+        let z = group.random_scalar();
+        let a1 = group.mul(
+            &group.exp(&self.instance.g1, &z),
+            &group.exp(&self.instance.h1, &-challenge),
+        );
+        let a2 = group.mul(
+            &group.exp(&self.instance.g2, &z),
+            &group.exp(&self.instance.h2, &-challenge),
+        );
+        ((a1, a2), z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        group::{PrimeOrderGroup, SchnorrGroup},
+        SigmaProtocol,
+    };
+
+    use super::{DleqInstance, DleqProtocol, DleqVerifierError};
+
+    fn make_instance(h2_exponent: i32) -> (DleqInstance, BigInt) {
+        let group =
+            SchnorrGroup::new(BigInt::from(1907), BigInt::from(953), BigInt::from(343)).unwrap();
+        let w = BigInt::from(121);
+        let g1 = group.generator();
+        let g2 = group.exp(&g1, &BigInt::from(17));
+        let h1 = group.exp(&g1, &w);
+        let h2 = group.exp(&g2, &BigInt::from(h2_exponent));
+        (DleqInstance::new(group, g1, h1, g2, h2), w)
+    }
+
+    #[test]
+    fn honest_run_is_accepted() {
+        let (instance, w) = make_instance(121);
+        let mut protocol = DleqProtocol::new(instance, Some(w));
+        let res = protocol.run_protocol();
+        assert!(res.is_ok(), "Honest run not accepted: {:?}", res)
+    }
+
+    #[test]
+    fn simulator_is_accepted() {
+        let (instance, _) = make_instance(122);
+        let mut protocol = DleqProtocol::new(instance, None);
+
+        let e = protocol.challenge();
+        let (a, z) = protocol.simulate(&e);
+
+        let res = protocol.check(a, e, z);
+        assert!(res.is_ok(), "Simulator not accepted: {:?}", res)
+    }
+
+    #[test]
+    fn different_logs_are_rejected() {
+        let (instance, w) = make_instance(122);
+        let mut protocol = DleqProtocol::new(instance, Some(w));
+
+        let a = protocol.initial_message();
+        let e = BigInt::from(5);
+        let z = protocol.challenge_response(&e);
+
+        let res = protocol.check(a, e, z);
+        assert!(
+            matches!(
+                res,
+                Err(DleqVerifierError::SecondExpressionsNotEqual { .. })
+            ),
+            "{:?}",
+            res
+        )
+    }
+}
@@ -0,0 +1,691 @@
+//! Run protocols with any number of rounds, either over a [`Channel`] or in memory.
+//!
+//! Each party of a protocol is a state machine implementing [`Party`]. Whenever it is
+//! advanced, it says whether it wants to send a message, expects a message from its peer, or
+//! is done. The runners in this module take care of moving the messages between the parties,
+//! so a protocol does not need any glue of its own to run remotely.
+//!
+//! Protocols between more than two parties implement [`RoundParty`] instead, and proceed in
+//! synchronous rounds in which every party sends a message to every other party.
+
+use std::{collections::VecDeque, io, marker::PhantomData, mem};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    netutil::{BinaryEncode, Channel, IntKind, NetError, WireValue},
+    remote::{bind_challenge, challenge_contexts},
+    Challenge, SigmaProtocol,
+};
+
+/// What a party wants to do next.
+#[derive(Debug)]
+pub enum Step<M, O> {
+    /// Send this message to the peer. Its integers are of the given kind.
+    Send(M, IntKind),
+    /// Wait for the next message from the peer, whose integers are of the given kind.
+    Expect(IntKind),
+    /// The party is done, with this output.
+    Done(O),
+}
+
+/// One party of a protocol, as a state machine.
+pub trait Party {
+    type Message;
+    type Output;
+
+    /// Advance the state machine.
+    ///
+    /// `received` is the message from the peer if the last step was [`Step::Expect`], and
+    /// `None` otherwise, including on the very first call.
+    fn next(&mut self, received: Option<Self::Message>) -> Step<Self::Message, Self::Output>;
+
+    /// Called by [`run_remote`] after every message that crosses `channel`, so that the party
+    /// can bind its messages to the channel and its session. Does nothing by default.
+    fn bind_to_channel(&mut self, _channel: &Channel) {}
+}
+
+/// Run `party` against a peer on the other end of `channel`.
+pub fn run_remote<P>(party: &mut P, channel: &mut Channel) -> Result<P::Output, NetError>
+where
+    P: Party,
+    P::Message: WireValue,
+{
+    let mut received = None;
+    loop {
+        match party.next(received.take()) {
+            Step::Send(message, kind) => channel.send(&message, kind)?,
+            Step::Expect(kind) => received = Some(channel.receive(kind)?),
+            Step::Done(output) => return Ok(output),
+        }
+        party.bind_to_channel(channel);
+    }
+}
+
+/// Run two parties against each other in memory.
+///
+/// # Panics
+///
+/// If both parties wait for a message at the same time, since neither can ever continue.
+pub fn run_local<A, B>(a: &mut A, b: &mut B) -> (A::Output, B::Output)
+where
+    A: Party,
+    B: Party<Message = A::Message>,
+{
+    let (mut to_a, mut to_b) = (VecDeque::new(), VecDeque::new());
+    let (mut a_waiting, mut b_waiting) = (false, false);
+    let (mut a_output, mut b_output) = (None, None);
+
+    loop {
+        if a_output.is_none() {
+            a_output = advance(a, &mut a_waiting, &mut to_a, &mut to_b);
+        }
+        if b_output.is_none() {
+            b_output = advance(b, &mut b_waiting, &mut to_b, &mut to_a);
+        }
+
+        if let (Some(_), Some(_)) = (&a_output, &b_output) {
+            return (a_output.unwrap(), b_output.unwrap());
+        }
+
+        // A party that is not done is waiting, and can only continue once it has a message.
+        let a_stuck = a_output.is_some() || to_a.is_empty();
+        let b_stuck = b_output.is_some() || to_b.is_empty();
+        if a_stuck && b_stuck {
+            panic!("Both parties are waiting for a message that will never arrive.");
+        }
+    }
+}
+
+/// Advance `party` until it needs a message that has not arrived yet, or is done.
+fn advance<P: Party>(
+    party: &mut P,
+    waiting: &mut bool,
+    inbox: &mut VecDeque<P::Message>,
+    outbox: &mut VecDeque<P::Message>,
+) -> Option<P::Output> {
+    loop {
+        let received = if *waiting {
+            Some(inbox.pop_front()?)
+        } else {
+            None
+        };
+        *waiting = false;
+
+        match party.next(received) {
+            Step::Send(message, _) => outbox.push_back(message),
+            Step::Expect(_) => *waiting = true,
+            Step::Done(output) => return Some(output),
+        }
+    }
+}
+
+/// What a party of a multi-party protocol does in the next round.
+#[derive(Debug)]
+pub enum Round<M, O> {
+    /// Send these messages, each to the party with the given index. Their integers are of the
+    /// given kind, which is the same for every party in the round.
+    Send(Vec<(u32, M)>, IntKind),
+    /// The party is done, with this output.
+    Done(O),
+}
+
+/// One party of a protocol between any number of parties, which proceeds in synchronous
+/// rounds. In each round, every party sends one message to every other party, and then receives
+/// one from every other party.
+pub trait RoundParty {
+    type Message;
+    type Output;
+
+    /// The index of this party, by which the others address their messages to it.
+    fn index(&self) -> u32;
+
+    /// Advance to the next round.
+    ///
+    /// `received` has the messages of the other parties from the last round, with the indices
+    /// of their senders. It is empty on the very first call.
+    fn next_round(
+        &mut self,
+        received: Vec<(u32, Self::Message)>,
+    ) -> Round<Self::Message, Self::Output>;
+}
+
+/// Run `party` against the other parties, with one channel to each of them, by their index.
+///
+/// A peer whose channel fails, for example because it stays silent past the read timeout, is
+/// cut off for the rest of the run: the party gets no more messages from it, as if it had sent
+/// none, and it is sent no more messages. This lets the party deal with the peer itself rather
+/// than being aborted by it. The first failure of each peer is returned with the output.
+///
+/// # Panics
+///
+/// If the party sends a message to a party it has no channel to.
+pub fn run_rounds_remote<P>(
+    party: &mut P,
+    channels: &mut [(u32, Channel)],
+) -> (P::Output, Vec<(u32, NetError)>)
+where
+    P: RoundParty,
+    P::Message: WireValue,
+{
+    let mut failures: Vec<(u32, NetError)> = vec![];
+    let mut received = vec![];
+    loop {
+        match party.next_round(mem::take(&mut received)) {
+            Round::Send(messages, kind) => {
+                for (to, message) in messages {
+                    let (_, channel) = channels
+                        .iter_mut()
+                        .find(|(index, _)| *index == to)
+                        .unwrap_or_else(|| panic!("There is no channel to party {}.", to));
+                    if failures.iter().any(|(peer, _)| *peer == to) {
+                        continue;
+                    }
+                    if let Err(err) = channel.send(&message, kind) {
+                        failures.push((to, err));
+                    }
+                }
+                for (from, channel) in channels.iter_mut() {
+                    if failures.iter().any(|(peer, _)| peer == from) {
+                        continue;
+                    }
+                    match channel.receive(kind) {
+                        Ok(message) => received.push((*from, message)),
+                        Err(err) => failures.push((*from, err)),
+                    }
+                }
+            }
+            Round::Done(output) => return (output, failures),
+        }
+    }
+}
+
+/// Run all parties of a protocol in memory, returning their outputs in the same order.
+///
+/// A party that is done before the others no longer receives messages.
+///
+/// # Panics
+///
+/// If a party sends a message to an index that no party has.
+pub fn run_rounds_local<M, O>(
+    parties: &mut [&mut dyn RoundParty<Message = M, Output = O>],
+) -> Vec<O> {
+    let indices = parties.iter().map(|p| p.index()).collect::<Vec<_>>();
+    let mut inboxes = indices.iter().map(|_| vec![]).collect::<Vec<_>>();
+    let mut outputs = indices.iter().map(|_| None).collect::<Vec<_>>();
+
+    while outputs.iter().any(Option::is_none) {
+        let mut next_inboxes = indices.iter().map(|_| vec![]).collect::<Vec<_>>();
+        for (i, party) in parties.iter_mut().enumerate() {
+            if outputs[i].is_some() {
+                continue;
+            }
+            match party.next_round(mem::take(&mut inboxes[i])) {
+                Round::Send(messages, _) => {
+                    for (to, message) in messages {
+                        let recipient = indices
+                            .iter()
+                            .position(|&index| index == to)
+                            .unwrap_or_else(|| panic!("There is no party {}.", to));
+                        next_inboxes[recipient].push((indices[i], message));
+                    }
+                }
+                Round::Done(output) => outputs[i] = Some(output),
+            }
+        }
+        inboxes = next_inboxes;
+    }
+    outputs.into_iter().map(Option::unwrap).collect()
+}
+
+/// The messages of a sigma protocol, followed by the verifier's verdict.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SigmaMessage<A, E, Z> {
+    InitialMessage(A),
+    Challenge(E),
+    Response(Z),
+    Verdict(bool),
+}
+
+impl<A: BinaryEncode, E: BinaryEncode, Z: BinaryEncode> BinaryEncode for SigmaMessage<A, E, Z> {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            SigmaMessage::InitialMessage(a) => {
+                out.push(0);
+                a.encode(int_len, out)
+            }
+            SigmaMessage::Challenge(e) => {
+                out.push(1);
+                e.encode(int_len, out)
+            }
+            SigmaMessage::Response(z) => {
+                out.push(2);
+                z.encode(int_len, out)
+            }
+            SigmaMessage::Verdict(accepted) => {
+                out.push(3);
+                accepted.encode(int_len, out)
+            }
+        }
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let [tag] = <[u8; 1]>::decode(int_len, input)?;
+        match tag {
+            0 => Ok(SigmaMessage::InitialMessage(A::decode(int_len, input)?)),
+            1 => Ok(SigmaMessage::Challenge(E::decode(int_len, input)?)),
+            2 => Ok(SigmaMessage::Response(Z::decode(int_len, input)?)),
+            3 => Ok(SigmaMessage::Verdict(bool::decode(int_len, input)?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown message tag",
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DriverError<VError> {
+    /// The peer sent a message that does not fit the current state of the protocol.
+    UnexpectedMessage,
+    /// The verifier rejected the proof.
+    Rejected,
+    /// The proof did not pass the verifier's check.
+    Verifier(VError),
+}
+
+enum ProverState {
+    Start,
+    AwaitChallenge,
+    AwaitVerdict,
+}
+
+/// The prover of a sigma protocol, as a [`Party`].
+///
+/// When run over a [`Channel`], it binds the challenge to the channel and its session in the
+/// same way as [`RemoteVerifierProtocol`](crate::remote::RemoteVerifierProtocol).
+pub struct SigmaProver<P, X, W, A, E, Z> {
+    protocol: P,
+    state: ProverState,
+    challenge_bits: usize,
+    /// The contexts of the channel as of the last message, which is the challenge once it has
+    /// arrived.
+    contexts: Vec<[u8; 32]>,
+    types: PhantomData<(X, W, A, E, Z)>,
+}
+
+impl<P, X, W, A, E, Z> SigmaProver<P, X, W, A, E, Z>
+where
+    P: SigmaProtocol<X, W, A, E, Z>,
+{
+    /// The prover for `protocol`, whose challenges have `challenge_bits` bits.
+    pub fn new(protocol: P, challenge_bits: usize) -> Self {
+        SigmaProver {
+            protocol,
+            state: ProverState::Start,
+            challenge_bits,
+            contexts: Vec::new(),
+            types: PhantomData,
+        }
+    }
+}
+
+impl<P, X, W, A, E, Z> Party for SigmaProver<P, X, W, A, E, Z>
+where
+    P: SigmaProtocol<X, W, A, E, Z>,
+    E: Challenge,
+{
+    type Message = SigmaMessage<A, E, Z>;
+    type Output = Result<(), DriverError<P::VerifierError>>;
+
+    fn next(&mut self, received: Option<Self::Message>) -> Step<Self::Message, Self::Output> {
+        match (&self.state, received) {
+            (ProverState::Start, None) => {
+                let a = self.protocol.initial_message();
+                self.state = ProverState::AwaitChallenge;
+                Step::Send(SigmaMessage::InitialMessage(a), IntKind::Element)
+            }
+            (ProverState::AwaitChallenge, None) | (ProverState::AwaitVerdict, None) => {
+                Step::Expect(IntKind::Scalar)
+            }
+            (ProverState::AwaitChallenge, Some(SigmaMessage::Challenge(e))) => {
+                let e = bind_challenge(e, self.challenge_bits, &self.contexts);
+                let z = self.protocol.challenge_response(&e);
+                self.state = ProverState::AwaitVerdict;
+                Step::Send(SigmaMessage::Response(z), IntKind::Scalar)
+            }
+            (ProverState::AwaitVerdict, Some(SigmaMessage::Verdict(accepted))) => {
+                Step::Done(if accepted {
+                    Ok(())
+                } else {
+                    Err(DriverError::Rejected)
+                })
+            }
+            _ => Step::Done(Err(DriverError::UnexpectedMessage)),
+        }
+    }
+
+    fn bind_to_channel(&mut self, channel: &Channel) {
+        if let ProverState::AwaitChallenge = self.state {
+            self.contexts = challenge_contexts(channel);
+        }
+    }
+}
+
+enum VerifierState<A, E, VError> {
+    AwaitInitialMessage,
+    AwaitResponse(A, E),
+    Finished(Result<(), DriverError<VError>>),
+    Done,
+}
+
+/// The verifier of a sigma protocol, as a [`Party`].
+///
+/// When run over a [`Channel`], it binds the challenge to the channel and its session in the
+/// same way as [`RemoteProverProtocol`](crate::remote::RemoteProverProtocol).
+pub struct SigmaVerifier<P, X, W, A, E, Z>
+where
+    P: SigmaProtocol<X, W, A, E, Z>,
+{
+    protocol: P,
+    state: VerifierState<A, E, P::VerifierError>,
+    challenge_bits: usize,
+    /// The contexts of the channel right after the challenge was sent.
+    contexts: Option<Vec<[u8; 32]>>,
+    types: PhantomData<(X, W, Z)>,
+}
+
+impl<P, X, W, A, E, Z> SigmaVerifier<P, X, W, A, E, Z>
+where
+    P: SigmaProtocol<X, W, A, E, Z>,
+{
+    /// The verifier for `protocol`, whose challenges have `challenge_bits` bits.
+    pub fn new(protocol: P, challenge_bits: usize) -> Self {
+        SigmaVerifier {
+            protocol,
+            state: VerifierState::AwaitInitialMessage,
+            challenge_bits,
+            contexts: None,
+            types: PhantomData,
+        }
+    }
+}
+
+impl<P, X, W, A, E, Z> Party for SigmaVerifier<P, X, W, A, E, Z>
+where
+    P: SigmaProtocol<X, W, A, E, Z>,
+    E: Clone + Challenge,
+{
+    type Message = SigmaMessage<A, E, Z>;
+    type Output = Result<(), DriverError<P::VerifierError>>;
+
+    fn next(&mut self, received: Option<Self::Message>) -> Step<Self::Message, Self::Output> {
+        match (mem::replace(&mut self.state, VerifierState::Done), received) {
+            (VerifierState::AwaitInitialMessage, None) => {
+                self.state = VerifierState::AwaitInitialMessage;
+                Step::Expect(IntKind::Element)
+            }
+            (VerifierState::AwaitInitialMessage, Some(SigmaMessage::InitialMessage(a))) => {
+                let e = self.protocol.challenge();
+                self.state = VerifierState::AwaitResponse(a, e.clone());
+                Step::Send(SigmaMessage::Challenge(e), IntKind::Scalar)
+            }
+            (VerifierState::AwaitResponse(a, e), None) => {
+                self.state = VerifierState::AwaitResponse(a, e);
+                Step::Expect(IntKind::Scalar)
+            }
+            (VerifierState::AwaitResponse(a, e), Some(SigmaMessage::Response(z))) => {
+                let contexts = self.contexts.as_deref().unwrap_or_default();
+                let e = bind_challenge(e, self.challenge_bits, contexts);
+                let res = self.protocol.check(a, e, z).map_err(DriverError::Verifier);
+                let accepted = res.is_ok();
+                self.state = VerifierState::Finished(res);
+                Step::Send(SigmaMessage::Verdict(accepted), IntKind::Scalar)
+            }
+            (VerifierState::Finished(res), None) => Step::Done(res),
+            _ => Step::Done(Err(DriverError::UnexpectedMessage)),
+        }
+    }
+
+    fn bind_to_channel(&mut self, channel: &Channel) {
+        if let (VerifierState::AwaitResponse(..), None) = (&self.state, &self.contexts) {
+            self.contexts = Some(challenge_contexts(channel));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use num::BigInt;
+
+    use crate::{
+        netutil::{Channel, Codec, IntKind, Role},
+        or::OrProtocol,
+        schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol, SchnorrVerifierError},
+        SigmaProtocol,
+    };
+
+    use super::{
+        run_local, run_remote, run_rounds_local, DriverError, Party, Round, RoundParty,
+        SigmaMessage, SigmaProver, SigmaVerifier, Step,
+    };
+
+    fn make_instance() -> (SchnorrDiscreteLogInstance, BigInt) {
+        let p = BigInt::from(1907);
+        let q = BigInt::from(953);
+        let g = BigInt::from(343);
+        let w = BigInt::from(121);
+        let h = g.modpow(&w, &p);
+        (SchnorrDiscreteLogInstance::new(p, q, g, h), w)
+    }
+
+    #[test]
+    fn honest_run_is_accepted_locally() {
+        let (instance, w) = make_instance();
+        let bits = instance.challenge_bits();
+        let mut prover = SigmaProver::new(
+            SchnorrDiscreteLogProtocol::new(instance.clone(), Some(w)),
+            bits,
+        );
+        let mut verifier =
+            SigmaVerifier::new(SchnorrDiscreteLogProtocol::new(instance, None), bits);
+
+        let (prover_res, verifier_res) = run_local(&mut prover, &mut verifier);
+        assert!(prover_res.is_ok(), "{:?}", prover_res);
+        assert!(verifier_res.is_ok(), "{:?}", verifier_res);
+    }
+
+    #[test]
+    fn composed_protocol_runs_locally() {
+        let (instance, w) = make_instance();
+        let bits = instance.challenge_bits();
+        let other = SchnorrDiscreteLogInstance::new(
+            instance.p().clone(),
+            instance.q().clone(),
+            instance.g().clone(),
+            BigInt::from(862),
+        );
+        let instances = (instance, other);
+
+        type SchnorrOrProtocol = OrProtocol<SchnorrDiscreteLogProtocol, BigInt, BigInt>;
+        let mut prover = SigmaProver::new(SchnorrOrProtocol::new(instances.clone(), Some(w)), bits);
+        let mut verifier = SigmaVerifier::new(SchnorrOrProtocol::new(instances, None), bits);
+
+        let (prover_res, verifier_res) = run_local(&mut prover, &mut verifier);
+        assert!(prover_res.is_ok(), "{:?}", prover_res);
+        assert!(verifier_res.is_ok(), "{:?}", verifier_res);
+    }
+
+    #[test]
+    fn wrong_witness_is_rejected() {
+        let (instance, w) = make_instance();
+        let bits = instance.challenge_bits();
+        let mut prover = SigmaProver::new(
+            SchnorrDiscreteLogProtocol::new(instance.clone(), Some(w + 1)),
+            bits,
+        );
+        let mut verifier =
+            SigmaVerifier::new(SchnorrDiscreteLogProtocol::new(instance, None), bits);
+
+        let (prover_res, verifier_res) = run_local(&mut prover, &mut verifier);
+        assert!(matches!(prover_res, Err(DriverError::Rejected)));
+        assert!(matches!(verifier_res, Err(DriverError::Verifier(_))));
+    }
+
+    #[test]
+    fn unexpected_message_is_reported() {
+        let (instance, _) = make_instance();
+        let bits = instance.challenge_bits();
+        let mut verifier =
+            SigmaVerifier::new(SchnorrDiscreteLogProtocol::new(instance, None), bits);
+
+        assert!(matches!(
+            verifier.next(None),
+            Step::Expect(IntKind::Element)
+        ));
+        let step = verifier.next(Some(SigmaMessage::Challenge(BigInt::from(1))));
+        assert!(matches!(
+            step,
+            Step::Done(Err(DriverError::UnexpectedMessage))
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "never arrive")]
+    fn waiting_parties_are_detected() {
+        let (instance, _) = make_instance();
+        let bits = instance.challenge_bits();
+        let mut first = SigmaVerifier::new(
+            SchnorrDiscreteLogProtocol::new(instance.clone(), None),
+            bits,
+        );
+        let mut second = SigmaVerifier::new(SchnorrDiscreteLogProtocol::new(instance, None), bits);
+        let _ = run_local(&mut first, &mut second);
+    }
+
+    type Outcome = Result<(), DriverError<SchnorrVerifierError>>;
+
+    /// Run a proof over a secure channel with a session, which binds challenges on the prover's
+    /// and the verifier's side as given, returning the outputs of the prover and the verifier.
+    fn run_in_threads(prover_binds: bool, verifier_binds: bool) -> (Outcome, Outcome) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(512, 64);
+        let bits = instance.challenge_bits();
+        let codec = Codec::binary_for(&instance);
+        let prover_instance = instance.clone();
+
+        let prover_handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut channel = Channel::new(stream, codec);
+            channel.secure(&prover_instance, Role::Responder).unwrap();
+            channel
+                .start_session(Role::Responder, prover_binds)
+                .unwrap();
+            let protocol = SchnorrDiscreteLogProtocol::new(prover_instance, Some(w));
+            let mut prover = SigmaProver::new(protocol, bits);
+            run_remote(&mut prover, &mut channel).unwrap()
+        });
+
+        let mut channel = Channel::new(TcpStream::connect(addr).unwrap(), codec);
+        channel.secure(&instance, Role::Initiator).unwrap();
+        channel
+            .start_session(Role::Initiator, verifier_binds)
+            .unwrap();
+        let protocol = SchnorrDiscreteLogProtocol::new(instance, None);
+        let mut verifier = SigmaVerifier::new(protocol, bits);
+        let verifier_res = run_remote(&mut verifier, &mut channel).unwrap();
+
+        (prover_handle.join().unwrap(), verifier_res)
+    }
+
+    #[test]
+    fn honest_run_is_accepted_remotely() {
+        let (prover_res, verifier_res) = run_in_threads(true, true);
+        assert!(verifier_res.is_ok(), "{:?}", verifier_res);
+        assert!(prover_res.is_ok(), "{:?}", prover_res);
+    }
+
+    #[test]
+    fn challenges_are_bound_to_the_session() {
+        let (prover_res, verifier_res) = run_in_threads(false, true);
+        assert!(matches!(verifier_res, Err(DriverError::Verifier(_))));
+        assert!(matches!(prover_res, Err(DriverError::Rejected)));
+    }
+
+    /// Each party sends its value to all others, and outputs the sum of all values.
+    struct Summer {
+        index: u32,
+        parties: u32,
+        value: u32,
+        sent: bool,
+    }
+
+    impl RoundParty for Summer {
+        type Message = u32;
+        type Output = u32;
+
+        fn index(&self) -> u32 {
+            self.index
+        }
+
+        fn next_round(&mut self, received: Vec<(u32, u32)>) -> Round<u32, u32> {
+            if self.sent {
+                assert_eq!(received.len() as u32, self.parties - 1);
+                return Round::Done(self.value + received.iter().map(|(_, v)| v).sum::<u32>());
+            }
+            self.sent = true;
+            let messages = (1..=self.parties)
+                .filter(|&i| i != self.index)
+                .map(|i| (i, self.value))
+                .collect();
+            Round::Send(messages, IntKind::Scalar)
+        }
+    }
+
+    #[test]
+    fn rounds_run_locally() {
+        let mut parties = (1..=4)
+            .map(|index| Summer {
+                index,
+                parties: 4,
+                value: index * 10,
+                sent: false,
+            })
+            .collect::<Vec<_>>();
+        let mut parties = parties
+            .iter_mut()
+            .map(|p| p as &mut dyn RoundParty<Message = u32, Output = u32>)
+            .collect::<Vec<_>>();
+        assert_eq!(run_rounds_local(&mut parties), vec![100; 4]);
+    }
+
+    #[test]
+    fn messages_round_trip_in_binary() {
+        let codec = Codec::Binary {
+            element_len: 8,
+            scalar_len: 4,
+        };
+        let messages: Vec<SigmaMessage<BigInt, BigInt, BigInt>> = vec![
+            SigmaMessage::InitialMessage(BigInt::from(1) << 60),
+            SigmaMessage::Challenge(BigInt::from(7)),
+            SigmaMessage::Response(BigInt::from(9)),
+            SigmaMessage::Verdict(true),
+        ];
+        for message in messages {
+            let data = codec.encode(&message, IntKind::Element).unwrap();
+            assert_eq!(
+                codec
+                    .decode::<SigmaMessage<_, _, _>>(&data, IntKind::Element)
+                    .unwrap(),
+                message
+            );
+        }
+    }
+}
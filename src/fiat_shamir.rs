@@ -0,0 +1,145 @@
+//! Non-interactive proofs, made from sigma protocols with the Fiat-Shamir transform.
+//!
+//! The challenge is a hash of a context, the instance and the initial message, so a proof made
+//! in one context, such as for one voter in one election, is rejected in any other.
+
+use std::io;
+
+use num::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{netutil::BinaryEncode, transcript::Transcript, SigmaProtocol};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NonInteractiveProof<A, Z> {
+    initial_msg: A,
+    response: Z,
+}
+
+impl<A: Serialize, Z> NonInteractiveProof<A, Z> {
+    /// Prove knowledge of `witness` for `instance` with the protocol `P`, whose challenges have
+    /// `challenge_bits` bits.
+    pub fn prove<P, X, W>(context: &[u8], instance: X, witness: W, challenge_bits: usize) -> Self
+    where
+        P: SigmaProtocol<X, W, A, BigInt, Z>,
+        X: Serialize,
+    {
+        let encoded_instance =
+            serde_json::to_vec(&instance).expect("Value could not be serialized.");
+        let mut protocol = P::new(instance, Some(witness));
+        let initial_msg = protocol.initial_message();
+        let e = challenge(context, &encoded_instance, &initial_msg, challenge_bits);
+        let response = protocol.challenge_response(&e);
+        NonInteractiveProof {
+            initial_msg,
+            response,
+        }
+    }
+
+    pub fn verify<P, X, W>(
+        &self,
+        context: &[u8],
+        instance: X,
+        challenge_bits: usize,
+    ) -> Result<(), P::VerifierError>
+    where
+        P: SigmaProtocol<X, W, A, BigInt, Z>,
+        X: Serialize,
+        A: Clone,
+        Z: Clone,
+    {
+        let encoded_instance =
+            serde_json::to_vec(&instance).expect("Value could not be serialized.");
+        let e = challenge(
+            context,
+            &encoded_instance,
+            &self.initial_msg,
+            challenge_bits,
+        );
+        P::new(instance, None).check(self.initial_msg.clone(), e, self.response.clone())
+    }
+
+    pub fn initial_msg(&self) -> &A {
+        &self.initial_msg
+    }
+
+    pub fn response(&self) -> &Z {
+        &self.response
+    }
+
+    /// The challenge of this proof for `instance` in `context`, to check it in some other way
+    /// than with the protocol, such as in a batch.
+    pub fn challenge<X: Serialize>(
+        &self,
+        context: &[u8],
+        instance: &X,
+        challenge_bits: usize,
+    ) -> BigInt {
+        let encoded_instance =
+            serde_json::to_vec(instance).expect("Value could not be serialized.");
+        challenge(
+            context,
+            &encoded_instance,
+            &self.initial_msg,
+            challenge_bits,
+        )
+    }
+}
+
+impl<A: BinaryEncode, Z: BinaryEncode> BinaryEncode for NonInteractiveProof<A, Z> {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.initial_msg.encode(int_len, out)?;
+        self.response.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let (initial_msg, response) = BinaryEncode::decode(int_len, input)?;
+        Ok(NonInteractiveProof {
+            initial_msg,
+            response,
+        })
+    }
+}
+
+fn challenge<A: Serialize>(
+    context: &[u8],
+    instance: &[u8],
+    initial_msg: &A,
+    challenge_bits: usize,
+) -> BigInt {
+    let mut transcript = Transcript::new(b"fiat-shamir");
+    transcript.append(b"context", context);
+    transcript.append(b"instance", instance);
+    transcript.append_value(b"initial message", initial_msg);
+    transcript.challenge(b"challenge", challenge_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol};
+
+    use super::NonInteractiveProof;
+
+    #[test]
+    fn proof_is_bound_to_context_and_instance() {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let bits = instance.challenge_bits();
+        let proof = NonInteractiveProof::prove::<SchnorrDiscreteLogProtocol, _, _>(
+            b"context",
+            instance.clone(),
+            w,
+            bits,
+        );
+
+        let verify = |context: &[u8], instance: &SchnorrDiscreteLogInstance| {
+            proof.verify::<SchnorrDiscreteLogProtocol, _, BigInt>(context, instance.clone(), bits)
+        };
+        assert!(verify(b"context", &instance).is_ok());
+        assert!(verify(b"other context", &instance).is_err());
+
+        let (other, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        assert!(verify(b"context", &other).is_err());
+    }
+}
@@ -0,0 +1,407 @@
+//! Prime-order groups, written multiplicatively.
+//!
+//! Protocols that only need the group operations, rather than the details of the group, are
+//! written against [`PrimeOrderGroup`]. Scalars are always [`BigInt`]s, taken modulo the order.
+
+use std::{
+    fmt::{self, Debug, Display},
+    sync::{Arc, OnceLock},
+};
+
+use num::{bigint::RandBigInt, BigInt, Integer, One, ToPrimitive, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{montgomery::Modulus, multiexp, transcript::Transcript};
+
+pub trait PrimeOrderGroup {
+    type Element: Clone + PartialEq + Debug + Serialize;
+
+    /// The order of the group, which is prime.
+    fn order(&self) -> &BigInt;
+    fn generator(&self) -> Self::Element;
+    fn identity(&self) -> Self::Element;
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    /// Raise `a` to the power `e`, which may be any integer, including a negative one.
+    fn exp(&self, a: &Self::Element, e: &BigInt) -> Self::Element;
+    fn inverse(&self, a: &Self::Element) -> Self::Element;
+    /// Whether `a` is an element of the group, for checking values received from others.
+    fn is_element(&self, a: &Self::Element) -> bool;
+
+    /// Map `label` to an element whose discrete log to any other element is unknown.
+    fn hash_to_element(&self, label: &[u8]) -> Self::Element;
+
+    /// The product of `bases[i]^exponents[i]`, with the exponents reduced modulo the order.
+    fn multi_exp(&self, bases: &[Self::Element], exponents: &[BigInt]) -> Self::Element {
+        let exponents = exponents
+            .iter()
+            .map(|e| e.mod_floor(self.order()))
+            .collect::<Vec<_>>();
+        multiexp::multi_exp(self, bases, &exponents)
+    }
+
+    /// Raise `a` to a secret power `e`, such as a nonce or a key, in time that does not depend
+    /// on `e` modulo the order. Groups without such an exponentiation use
+    /// [`PrimeOrderGroup::exp`].
+    fn exp_secret(&self, a: &Self::Element, e: &BigInt) -> Self::Element {
+        self.exp(a, e)
+    }
+
+    /// The product of `bases[i]^exponents[i]` for secret exponents, which takes separate
+    /// exponentiations, as the work of a multi-exponentiation depends on the exponents.
+    fn multi_exp_secret(&self, bases: &[Self::Element], exponents: &[BigInt]) -> Self::Element {
+        assert_eq!(bases.len(), exponents.len());
+        bases
+            .iter()
+            .zip(exponents)
+            .fold(self.identity(), |acc, (b, e)| {
+                self.mul(&acc, &self.exp_secret(b, e))
+            })
+    }
+
+    /// The response `nonce + challenge * secret` modulo the order, as in a Schnorr proof, in
+    /// time that does not depend on the nonce and the secret.
+    fn response(&self, nonce: &BigInt, challenge: &BigInt, secret: &BigInt) -> BigInt {
+        (nonce + challenge * secret).mod_floor(self.order())
+    }
+
+    fn random_scalar(&self) -> BigInt {
+        rand::thread_rng().gen_bigint_range(&BigInt::zero(), self.order())
+    }
+
+    /// The inverse of `s` modulo the order, which must not be zero.
+    fn scalar_inverse(&self, s: &BigInt) -> BigInt {
+        let q = self.order();
+        let s = s.mod_floor(q);
+        assert!(!s.is_zero(), "Zero has no inverse.");
+        s.modpow(&(q - 2), q)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GroupError {
+    /// The modulus `p` is not an odd integer above one, so it cannot be an odd prime.
+    InvalidModulus,
+    /// The order `q` is not an odd integer above one, so it cannot be an odd prime.
+    InvalidOrder,
+}
+
+impl Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupError::InvalidModulus => write!(f, "the modulus is not an odd integer above one"),
+            GroupError::InvalidOrder => write!(f, "the order is not an odd integer above one"),
+        }
+    }
+}
+
+impl std::error::Error for GroupError {}
+
+/// Check that `p` and `q` can be used for Montgomery arithmetic, as all odd primes can.
+///
+/// This is far from checking that they are prime, but it is cheap, and enough for the group
+/// operations to be well defined, so they can be done on untrusted groups without panicking.
+pub(crate) fn check_moduli(p: &BigInt, q: &BigInt) -> Result<(), GroupError> {
+    let odd_above_one = |x: &BigInt| x > &BigInt::one() && x.is_odd();
+    if !odd_above_one(p) {
+        return Err(GroupError::InvalidModulus);
+    }
+    if !odd_above_one(q) {
+        return Err(GroupError::InvalidOrder);
+    }
+    Ok(())
+}
+
+/// The subgroup of order `q` of `Z_p^*`, generated by `g`, as in a
+/// [`SchnorrDiscreteLogInstance`](crate::schnorr::SchnorrDiscreteLogInstance).
+///
+/// Elements are plain integers in `[0, p)`, but the arithmetic on them is done in Montgomery
+/// form, with a [`Modulus`] that is set up on first use and shared by clones. Secret scalars are
+/// handled in Montgomery form modulo `q` in the same way.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "GroupParams")]
+pub struct SchnorrGroup {
+    p: BigInt,
+    q: BigInt,
+    g: BigInt,
+    #[serde(skip)]
+    arithmetic: Arc<OnceLock<Modulus>>,
+    #[serde(skip)]
+    scalars: Arc<OnceLock<Modulus>>,
+}
+
+/// The serialised form of a [`SchnorrGroup`], which is checked as it is deserialised.
+#[derive(Deserialize)]
+struct GroupParams {
+    p: BigInt,
+    q: BigInt,
+    g: BigInt,
+}
+
+impl TryFrom<GroupParams> for SchnorrGroup {
+    type Error = GroupError;
+
+    fn try_from(params: GroupParams) -> Result<Self, GroupError> {
+        SchnorrGroup::new(params.p, params.q, params.g)
+    }
+}
+
+impl SchnorrGroup {
+    /// The group of order `q` generated by `g` modulo `p`. Only the moduli are checked, so that
+    /// the arithmetic is well defined; see
+    /// [`SchnorrDiscreteLogInstance::is_valid`](crate::schnorr::SchnorrDiscreteLogInstance::is_valid)
+    /// for a full check.
+    pub fn new(p: BigInt, q: BigInt, g: BigInt) -> Result<Self, GroupError> {
+        check_moduli(&p, &q)?;
+        Ok(SchnorrGroup {
+            p,
+            q,
+            g,
+            arithmetic: Arc::default(),
+            scalars: Arc::default(),
+        })
+    }
+
+    pub fn modulus(&self) -> &BigInt {
+        &self.p
+    }
+
+    /// The Montgomery arithmetic modulo `p`, for computations that stay in Montgomery form.
+    pub fn arithmetic(&self) -> &Modulus {
+        self.arithmetic.get_or_init(|| Modulus::new(&self.p))
+    }
+
+    /// The Montgomery arithmetic modulo `q`, for secret scalars.
+    fn scalars(&self) -> &Modulus {
+        self.scalars.get_or_init(|| Modulus::new(&self.q))
+    }
+}
+
+impl PartialEq for SchnorrGroup {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.p, &self.q, &self.g) == (&other.p, &other.q, &other.g)
+    }
+}
+
+impl Debug for SchnorrGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SchnorrGroup")
+            .field("p", &self.p)
+            .field("q", &self.q)
+            .field("g", &self.g)
+            .finish()
+    }
+}
+
+impl PrimeOrderGroup for SchnorrGroup {
+    type Element = BigInt;
+
+    fn order(&self) -> &BigInt {
+        &self.q
+    }
+
+    fn generator(&self) -> BigInt {
+        self.g.clone()
+    }
+
+    fn identity(&self) -> BigInt {
+        BigInt::one()
+    }
+
+    fn mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        self.arithmetic().mul_integers(a, b)
+    }
+
+    fn exp(&self, a: &BigInt, e: &BigInt) -> BigInt {
+        self.arithmetic().pow_integer(a, &e.mod_floor(&self.q))
+    }
+
+    fn inverse(&self, a: &BigInt) -> BigInt {
+        self.exp(a, &(&self.q - 1))
+    }
+
+    /// For a safe prime `p = 2q + 1`, the subgroup is that of the quadratic residues, so the
+    /// Legendre symbol decides membership without an exponentiation.
+    fn is_element(&self, a: &BigInt) -> bool {
+        if a <= &BigInt::zero() || a >= &self.p {
+            return false;
+        }
+        if self.p == (&self.q << 1) + 1u32 {
+            return jacobi(a, &self.p) == 1;
+        }
+        self.arithmetic().pow_integer(a, &self.q).is_one()
+    }
+
+    /// A fixed window exponentiation over all the bits of `q`, with the powers of `a` read in
+    /// constant time.
+    fn exp_secret(&self, a: &BigInt, e: &BigInt) -> BigInt {
+        let arithmetic = self.arithmetic();
+        let power =
+            arithmetic.pow_secret(&arithmetic.residue(a), &e.mod_floor(&self.q), self.q.bits());
+        arithmetic.integer(&power)
+    }
+
+    fn response(&self, nonce: &BigInt, challenge: &BigInt, secret: &BigInt) -> BigInt {
+        let scalars = self.scalars();
+        let [nonce, challenge, secret] = [nonce, challenge, secret].map(|x| scalars.residue(x));
+        scalars.integer(&scalars.add(&nonce, &scalars.mul(&challenge, &secret)))
+    }
+
+    /// Multi-exponentiation in Montgomery form, converting each base and the result once.
+    fn multi_exp(&self, bases: &[BigInt], exponents: &[BigInt]) -> BigInt {
+        assert_eq!(bases.len(), exponents.len());
+        let arithmetic = self.arithmetic();
+        let bases = bases
+            .iter()
+            .map(|b| arithmetic.residue(b))
+            .collect::<Vec<_>>();
+        let exponents = exponents
+            .iter()
+            .map(|e| e.mod_floor(&self.q))
+            .collect::<Vec<_>>();
+        arithmetic.integer(&multiexp::multi_exp(arithmetic, &bases, &exponents))
+    }
+
+    /// Hash to an integer modulo `p`, and raise it to the cofactor `(p - 1) / q` to get into
+    /// the subgroup, trying again if that gives the identity.
+    fn hash_to_element(&self, label: &[u8]) -> BigInt {
+        let cofactor = (&self.p - 1u32) / &self.q;
+        let mut transcript = Transcript::new(b"hash to group");
+        transcript.append_value(b"group", self);
+        transcript.append(b"label", label);
+
+        (0u64..)
+            .map(|counter| {
+                let mut attempt = transcript.clone();
+                attempt.append(b"counter", &counter.to_be_bytes());
+                let x = attempt.challenge(b"element", self.p.bits() + 128) % &self.p;
+                self.arithmetic().pow_integer(&x, &cofactor)
+            })
+            .find(|x| x > &BigInt::one())
+            .unwrap()
+    }
+}
+
+/// The Jacobi symbol `(a / n)` for an odd positive `n`, which is the Legendre symbol when `n`
+/// is prime.
+fn jacobi(a: &BigInt, n: &BigInt) -> i8 {
+    let (mut a, mut n) = (a.mod_floor(n), n.clone());
+    let mut symbol = 1;
+    while !a.is_zero() {
+        while a.is_even() {
+            a >>= 1;
+            // `(2 / n)` is -1 exactly when `n` is 3 or 5 modulo 8.
+            let n_mod_8 = (&n & BigInt::from(7u32)).to_u32().unwrap();
+            if n_mod_8 == 3 || n_mod_8 == 5 {
+                symbol = -symbol;
+            }
+        }
+        // Quadratic reciprocity flips the sign when both are 3 modulo 4.
+        if (&a & BigInt::from(3u32)) == BigInt::from(3u32)
+            && (&n & BigInt::from(3u32)) == BigInt::from(3u32)
+        {
+            symbol = -symbol;
+        }
+        std::mem::swap(&mut a, &mut n);
+        a = a.mod_floor(&n);
+    }
+    if n.is_one() {
+        symbol
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{BigInt, Integer};
+
+    use super::{GroupError, PrimeOrderGroup, SchnorrGroup};
+
+    fn make_group() -> SchnorrGroup {
+        SchnorrGroup::new(BigInt::from(1907), BigInt::from(953), BigInt::from(343)).unwrap()
+    }
+
+    #[test]
+    fn operations_are_consistent() {
+        let group = make_group();
+        let g = group.generator();
+        let a = group.exp(&g, &BigInt::from(121));
+        let b = group.exp(&g, &BigInt::from(-121));
+
+        assert!(group.is_element(&a));
+        assert_eq!(group.mul(&a, &b), group.identity());
+        assert_eq!(group.inverse(&a), b);
+        assert_eq!(group.exp(&g, group.order()), group.identity());
+        assert_eq!(
+            group.multi_exp(
+                &[a.clone(), g.clone()],
+                &[BigInt::from(2), BigInt::from(10)]
+            ),
+            group.exp(&g, &BigInt::from(252))
+        );
+
+        let s = BigInt::from(77);
+        assert_eq!(
+            (group.scalar_inverse(&s) * s) % group.order(),
+            BigInt::from(1)
+        );
+    }
+
+    #[test]
+    fn membership_of_safe_prime_group_uses_legendre_symbol() {
+        let group = make_group();
+        let (p, q) = (group.modulus(), group.order());
+        for x in 0..=1907 {
+            let x = BigInt::from(x);
+            let expected = x > BigInt::from(0) && &x < p && x.modpow(q, p) == BigInt::from(1);
+            assert_eq!(group.is_element(&x), expected, "{}", x);
+        }
+    }
+
+    #[test]
+    fn secret_operations_match_public_ones() {
+        let group = make_group();
+        let g = group.generator();
+        for e in [-1, 0, 1, 121, 952, 953, 5000] {
+            let e = BigInt::from(e);
+            assert_eq!(group.exp_secret(&g, &e), group.exp(&g, &e));
+            assert_eq!(
+                group.response(&BigInt::from(500), &e, &BigInt::from(700)),
+                (&e * 700u32 + 500u32).mod_floor(group.order())
+            );
+        }
+        let (bases, exponents) = ([g.clone(), group.exp(&g, &BigInt::from(5))], [3, 4]);
+        let exponents = exponents.map(BigInt::from);
+        assert_eq!(
+            group.multi_exp_secret(&bases, &exponents),
+            group.multi_exp(&bases, &exponents)
+        );
+    }
+
+    #[test]
+    fn hashed_elements_are_distinct_elements() {
+        let group = make_group();
+        let a = group.hash_to_element(b"a");
+        let b = group.hash_to_element(b"b");
+        assert!(group.is_element(&a));
+        assert!(group.is_element(&b));
+        assert_ne!(a, b);
+        assert_eq!(a, group.hash_to_element(b"a"));
+        assert!(!group.is_element(&BigInt::from(1906)));
+    }
+
+    #[test]
+    fn invalid_moduli_are_rejected() {
+        let group =
+            |p: i64, q: i64| SchnorrGroup::new(BigInt::from(p), BigInt::from(q), BigInt::from(343));
+        assert_eq!(group(1908, 953).unwrap_err(), GroupError::InvalidModulus);
+        assert_eq!(group(1, 953).unwrap_err(), GroupError::InvalidModulus);
+        assert_eq!(group(1907, 954).unwrap_err(), GroupError::InvalidOrder);
+
+        // Untrusted groups are checked as they are deserialised.
+        let json = serde_json::to_string(&make_group()).unwrap();
+        assert!(serde_json::from_str::<SchnorrGroup>(&json).is_ok());
+        let even = json.replace("1907", "1908");
+        assert!(serde_json::from_str::<SchnorrGroup>(&even).is_err());
+    }
+}
@@ -1,8 +1,26 @@
 pub mod applications;
+pub mod batch;
+pub mod dleq;
+pub mod driver;
+pub mod fiat_shamir;
+pub mod group;
+pub mod montgomery;
+pub mod multiexp;
 pub mod netutil;
 pub mod or;
 pub mod remote;
+pub mod repeated;
+pub mod representation;
 pub mod schnorr;
+pub mod secret;
+pub mod secure;
+pub mod session;
+pub mod statement;
+pub mod transcript;
+pub mod zk;
+
+use num::{bigint::Sign, BigInt};
+use sha2::{Digest, Sha256};
 
 // TODO: Try to use immutable references for trait functions.
 pub trait SigmaProtocol<X, W, A, E, Z> {
@@ -27,3 +45,56 @@ pub trait SigmaProtocol<X, W, A, E, Z> {
         self.check(a, e, z)
     }
 }
+
+/// A challenge that can be bound to a context, such as the transcript of a secure channel.
+pub trait Challenge {
+    /// Derive a new challenge in `[0, 2^bits)` from this one and `context`.
+    ///
+    /// Both parties derive the same bound challenge from the same inputs. It should be
+    /// infeasible to find another challenge which gives the same bound challenge under a
+    /// different context, so that a man-in-the-middle cannot translate between two contexts.
+    fn bind(&self, context: &[u8], bits: usize) -> Self;
+
+    /// Whether this challenge is in `[0, 2^bits)`, where an honest verifier picks it.
+    fn in_space(&self, bits: usize) -> bool;
+}
+
+impl Challenge for BigInt {
+    /// Hash `context` and `self` onto the whole challenge space `[0, 2^bits)`, whatever the
+    /// size of `self`.
+    fn bind(&self, context: &[u8], bits: usize) -> Self {
+        let (_, bytes) = self.to_bytes_be();
+
+        let mut hash = Vec::new();
+        let mut counter = 0_u32;
+        while hash.len() * 8 < bits {
+            let mut hasher = Sha256::new();
+            hasher.update(b"sigma challenge binding: ");
+            hasher.update(counter.to_be_bytes());
+            hasher.update((bits as u64).to_be_bytes());
+            hasher.update((context.len() as u64).to_be_bytes());
+            hasher.update(context);
+            hasher.update(&bytes);
+            hash.extend_from_slice(&hasher.finalize());
+            counter += 1;
+        }
+
+        BigInt::from_bytes_be(Sign::Plus, &hash) % (BigInt::from(1) << bits)
+    }
+
+    fn in_space(&self, bits: usize) -> bool {
+        self.sign() != Sign::Minus && self.bits() <= bits
+    }
+}
+
+/// An instance whose challenges are drawn from `[0, 2^k)`, which bound challenges must cover.
+pub trait ChallengeSpace {
+    fn challenge_bits(&self) -> usize;
+}
+
+/// The instances of an [`or::OrProtocol`], whose challenges are those of the first one.
+impl<X: ChallengeSpace> ChallengeSpace for (X, X) {
+    fn challenge_bits(&self) -> usize {
+        self.0.challenge_bits()
+    }
+}
@@ -0,0 +1,338 @@
+//! Fixed-width arithmetic modulo an odd integer, with Montgomery multiplication.
+//!
+//! A [`Modulus`] keeps `p` as `n` 64-bit limbs along with the constants of Montgomery's
+//! reduction, and a [`Residue`] keeps `x R mod p` as exactly `n` limbs, where `R = 2^(64 n)`.
+//! Multiplying two residues then costs two `n` by `n` limb products and no division, unlike
+//! `BigInt` arithmetic, which divides by `p` after every product.
+//!
+//! This is the arithmetic behind [`SchnorrGroup`](crate::group::SchnorrGroup). Converting into
+//! and out of Montgomery form costs about one multiplication each, so long computations such as
+//! exponentiations should stay in it throughout.
+//!
+//! Multiplication and addition run in time that only depends on the size of the modulus, and
+//! [`Modulus::pow_secret`] and [`Modulus::select`] also hide the exponent and the index, for
+//! computations on secrets. The conversions from and to `BigInt` at either end cannot hide the
+//! number of limbs of their input, so secrets should be reduced to a fixed range first.
+
+use num::{bigint::Sign, BigInt, Integer, One};
+
+use crate::multiexp::{self, Multiply};
+
+/// An odd modulus, with the constants for Montgomery multiplication modulo it.
+#[derive(Clone, Debug)]
+pub struct Modulus {
+    p: BigInt,
+    /// The limbs of `p`, least significant first.
+    limbs: Vec<u64>,
+    /// `-p^-1 mod 2^64`.
+    inv: u64,
+    /// `R^2 mod p`, which takes integers into Montgomery form.
+    r2: Vec<u64>,
+    /// `R mod p`, the Montgomery form of one.
+    one: Residue,
+}
+
+/// An integer modulo a [`Modulus`], in Montgomery form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Residue(Vec<u64>);
+
+impl Modulus {
+    /// # Panics
+    ///
+    /// If `p` is even or not greater than one.
+    pub fn new(p: &BigInt) -> Self {
+        assert!(p > &BigInt::one() && p.is_odd(), "The modulus must be odd.");
+        let n = p.bits().div_ceil(64);
+        let limbs = to_limbs(p, n);
+
+        // Newton's iteration doubles the number of correct low bits of `p^-1` each time.
+        let mut inv = 1u64;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(limbs[0].wrapping_mul(inv)));
+        }
+
+        let r = BigInt::one() << (64 * n);
+        Modulus {
+            p: p.clone(),
+            r2: to_limbs(&(&r * &r).mod_floor(p), n),
+            one: Residue(to_limbs(&r.mod_floor(p), n)),
+            limbs,
+            inv: inv.wrapping_neg(),
+        }
+    }
+
+    pub fn modulus(&self) -> &BigInt {
+        &self.p
+    }
+
+    /// The residue of `x`, which may be any integer.
+    pub fn residue(&self, x: &BigInt) -> Residue {
+        Residue(self.mont_mul(&self.reduce(x), &self.r2))
+    }
+
+    /// The integer in `[0, p)` that `x` stands for.
+    pub fn integer(&self, x: &Residue) -> BigInt {
+        let mut one = vec![0; self.limbs.len()];
+        one[0] = 1;
+        from_limbs(&self.mont_mul(&x.0, &one))
+    }
+
+    pub fn one(&self) -> Residue {
+        self.one.clone()
+    }
+
+    pub fn mul(&self, a: &Residue, b: &Residue) -> Residue {
+        Residue(self.mont_mul(&a.0, &b.0))
+    }
+
+    /// `base^e`, where `e` must not be negative.
+    pub fn pow(&self, base: &Residue, e: &BigInt) -> Residue {
+        multiexp::straus(self, std::slice::from_ref(base), std::slice::from_ref(e))
+    }
+
+    /// `a + b`.
+    pub fn add(&self, a: &Residue, b: &Residue) -> Residue {
+        let mut carry = 0;
+        let mut sum =
+            a.0.iter()
+                .zip(&b.0)
+                .map(|(a, b)| {
+                    let (s, c1) = a.overflowing_add(*b);
+                    let (s, c2) = s.overflowing_add(carry);
+                    carry = (c1 | c2) as u64;
+                    s
+                })
+                .collect::<Vec<_>>();
+        sum.push(carry);
+        Residue(self.subtract_once(sum))
+    }
+
+    /// `base^e`, in time that does not depend on `e`, which must be below `2^bits`.
+    ///
+    /// This always goes through `bits / 4` windows, multiplies by a power of the base in each of
+    /// them, even for a zero digit, and reads that power with [`Modulus::select`].
+    pub fn pow_secret(&self, base: &Residue, e: &BigInt, bits: usize) -> Residue {
+        let mut table = Vec::with_capacity(16);
+        table.push(self.one());
+        for i in 1..16 {
+            table.push(self.mul(&table[i - 1], base));
+        }
+        let mut acc = self.one();
+        for digit in secret_digits(e, bits).into_iter().rev() {
+            for _ in 0..4 {
+                acc = self.mul(&acc, &acc);
+            }
+            acc = self.mul(&acc, &self.select(&table, digit));
+        }
+        acc
+    }
+
+    /// `table[index]`, reading every entry so that the memory accesses do not depend on
+    /// `index`.
+    pub fn select(&self, table: &[Residue], index: usize) -> Residue {
+        let mut selected = vec![0u64; self.limbs.len()];
+        for (i, entry) in table.iter().enumerate() {
+            let mask = eq_mask(i as u64, index as u64);
+            for (s, x) in selected.iter_mut().zip(&entry.0) {
+                *s |= x & mask;
+            }
+        }
+        Residue(selected)
+    }
+
+    /// `a b mod p`, for integers outside of Montgomery form.
+    pub fn mul_integers(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        // `(a b R^-1) R^2 R^-1 = a b`, which skips converting into and out of Montgomery form.
+        let ab = self.mont_mul(&self.reduce(a), &self.reduce(b));
+        from_limbs(&self.mont_mul(&ab, &self.r2))
+    }
+
+    /// `base^e mod p`, for an integer outside of Montgomery form, where `e` must not be
+    /// negative.
+    pub fn pow_integer(&self, base: &BigInt, e: &BigInt) -> BigInt {
+        self.integer(&self.pow(&self.residue(base), e))
+    }
+
+    /// The limbs of `x mod p`.
+    fn reduce(&self, x: &BigInt) -> Vec<u64> {
+        if x.sign() == Sign::Minus || x >= &self.p {
+            to_limbs(&x.mod_floor(&self.p), self.limbs.len())
+        } else {
+            to_limbs(x, self.limbs.len())
+        }
+    }
+
+    /// `a b R^-1 mod p`, for `a` and `b` below `p`, by coarsely integrated operand scanning:
+    /// each limb of `b` adds a multiple of `a`, and then a multiple of `p` that clears the lowest
+    /// limb, which is shifted out.
+    fn mont_mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let p = &self.limbs;
+        let n = p.len();
+        let mut t = vec![0u64; n + 2];
+        for &b_i in b {
+            let mut carry = 0;
+            for j in 0..n {
+                (t[j], carry) = mul_add(a[j], b_i, t[j], carry);
+            }
+            let (sum, overflow) = t[n].overflowing_add(carry);
+            t[n] = sum;
+            t[n + 1] = overflow as u64;
+
+            let m = t[0].wrapping_mul(self.inv);
+            let (_, mut carry) = mul_add(m, p[0], t[0], 0);
+            for j in 1..n {
+                (t[j - 1], carry) = mul_add(m, p[j], t[j], carry);
+            }
+            let (sum, overflow) = t[n].overflowing_add(carry);
+            t[n - 1] = sum;
+            t[n] = t[n + 1] + overflow as u64;
+        }
+        t.truncate(n + 1);
+        self.subtract_once(t)
+    }
+
+    /// `t mod p`, for `n + 1` limbs below `2p`, which are reduced with at most one subtraction.
+    /// Whether it is needed is kept in a mask rather than in a branch.
+    fn subtract_once(&self, t: Vec<u64>) -> Vec<u64> {
+        let n = self.limbs.len();
+        let mut diff = vec![0u64; n];
+        let mut borrow = 0;
+        for j in 0..n {
+            let (d, b1) = t[j].overflowing_sub(self.limbs[j]);
+            let (d, b2) = d.overflowing_sub(borrow);
+            diff[j] = d;
+            borrow = (b1 | b2) as u64;
+        }
+        // Keep `t` if the subtraction borrowed more than the top limb of `t` holds.
+        let keep = eq_mask(t[n], 0) & 0u64.wrapping_sub(borrow);
+        diff.iter()
+            .zip(&t)
+            .map(|(d, t)| (d & !keep) | (t & keep))
+            .collect()
+    }
+}
+
+impl Multiply for Modulus {
+    type Element = Residue;
+
+    fn identity(&self) -> Residue {
+        self.one()
+    }
+
+    fn mul(&self, a: &Residue, b: &Residue) -> Residue {
+        Modulus::mul(self, a, b)
+    }
+}
+
+/// `a b + c + d`, as its low and high limbs, which cannot overflow.
+fn mul_add(a: u64, b: u64, c: u64, d: u64) -> (u64, u64) {
+    let s = a as u128 * b as u128 + c as u128 + d as u128;
+    (s as u64, (s >> 64) as u64)
+}
+
+/// All ones if `a == b`, and zero otherwise, without a branch.
+fn eq_mask(a: u64, b: u64) -> u64 {
+    let x = a ^ b;
+    ((x | x.wrapping_neg()) >> 63).wrapping_sub(1)
+}
+
+/// The `bits / 4` digits of `e` in base 16, least significant first, without branching on
+/// them.
+///
+/// # Panics
+///
+/// If `e` is negative or not below `2^bits`.
+pub(crate) fn secret_digits(e: &BigInt, bits: usize) -> Vec<usize> {
+    assert!(
+        e.sign() != Sign::Minus && e.bits() <= bits,
+        "The exponent is out of range."
+    );
+    let limbs = to_limbs(e, bits.div_ceil(64));
+    (0..bits.div_ceil(4))
+        .map(|i| (limbs[i / 16] >> (4 * (i % 16)) & 0xf) as usize)
+        .collect()
+}
+
+/// The `n` limbs of `x`, which must not be negative and must fit.
+fn to_limbs(x: &BigInt, n: usize) -> Vec<u64> {
+    let (_, bytes) = x.to_bytes_le();
+    let mut limbs = vec![0; n];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+        let mut buf = [0; 8];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        *limb = u64::from_le_bytes(buf);
+    }
+    limbs
+}
+
+fn from_limbs(limbs: &[u64]) -> BigInt {
+    let bytes = limbs
+        .iter()
+        .flat_map(|limb| limb.to_le_bytes())
+        .collect::<Vec<_>>();
+    BigInt::from_bytes_le(Sign::Plus, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{bigint::RandBigInt, BigInt, Integer};
+
+    use super::Modulus;
+
+    #[test]
+    fn arithmetic_matches_bigint() {
+        let mut rng = rand::thread_rng();
+        // Moduli of one limb, of several, and with the top bit of the top limb set.
+        let moduli = [
+            BigInt::from(1907),
+            BigInt::from(rng.gen_biguint(299)) * 2 + 1,
+            (BigInt::from(1) << 256) - 189,
+        ];
+        for p in moduli {
+            let modulus = Modulus::new(&p);
+            for _ in 0..20 {
+                let a = rng.gen_bigint_range(&-&p, &(&p * 3));
+                let b = rng.gen_bigint_range(&BigInt::from(0), &p);
+                let e = BigInt::from(rng.gen_biguint(200));
+                let (ra, rb) = (modulus.residue(&a), modulus.residue(&b));
+
+                assert_eq!(modulus.integer(&ra), a.mod_floor(&p));
+                assert_eq!(
+                    modulus.integer(&modulus.mul(&ra, &rb)),
+                    (&a * &b).mod_floor(&p)
+                );
+                assert_eq!(modulus.mul_integers(&a, &b), (&a * &b).mod_floor(&p));
+                assert_eq!(modulus.pow_integer(&b, &e), b.modpow(&e, &p));
+            }
+            assert_eq!(modulus.integer(&modulus.one()), BigInt::from(1));
+        }
+    }
+
+    #[test]
+    fn secret_operations_match_public_ones() {
+        let mut rng = rand::thread_rng();
+        let p = (BigInt::from(1) << 256) - 189;
+        let modulus = Modulus::new(&p);
+        for e in [
+            BigInt::from(0),
+            BigInt::from(1),
+            BigInt::from(rng.gen_biguint(130)),
+        ] {
+            let a = rng.gen_bigint_range(&BigInt::from(0), &p);
+            let b = rng.gen_bigint_range(&BigInt::from(0), &p);
+            let (ra, rb) = (modulus.residue(&a), modulus.residue(&b));
+            assert_eq!(
+                modulus.pow_secret(&ra, &e, 130),
+                modulus.pow(&ra, &e),
+                "Wrong power for e = {e}"
+            );
+            assert_eq!(
+                modulus.integer(&modulus.add(&ra, &rb)),
+                (&a + &b).mod_floor(&p)
+            );
+            let table = [modulus.one(), ra.clone(), rb.clone()];
+            assert_eq!(modulus.select(&table, 1), ra);
+            assert_eq!(modulus.select(&table, 2), rb);
+        }
+    }
+}
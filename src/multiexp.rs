@@ -0,0 +1,332 @@
+//! Multi-exponentiation and fixed-base exponentiation in any [`PrimeOrderGroup`], or anything
+//! else that can [`Multiply`].
+//!
+//! A product `b_1^e_1 ... b_n^e_n` computed with `n` separate exponentiations costs `n` times
+//! as many squarings as a single one. [`straus`] (Shamir's trick, with windows) shares the
+//! squarings among all bases, and [`pippenger`] also sorts the bases into buckets by their
+//! digits, which is cheaper for many bases. [`multi_exp`] picks between the two.
+//!
+//! When the same base is raised to many exponents, such as the generator by every prover, a
+//! [`FixedBaseTable`] of its powers replaces all squarings by table lookups.
+//!
+//! All exponents here are used as they are, and must not be negative. Reducing them modulo the
+//! order is left to the caller, as it is only valid for elements of the group.
+
+use num::{bigint::Sign, BigInt};
+
+use crate::{
+    group::PrimeOrderGroup,
+    montgomery::{secret_digits, Modulus, Residue},
+};
+
+/// The operations that multi-exponentiation needs, which every [`PrimeOrderGroup`] has.
+pub trait Multiply {
+    type Element: Clone;
+
+    fn identity(&self) -> Self::Element;
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+}
+
+impl<G: PrimeOrderGroup + ?Sized> Multiply for G {
+    type Element = G::Element;
+
+    fn identity(&self) -> G::Element {
+        PrimeOrderGroup::identity(self)
+    }
+
+    fn mul(&self, a: &G::Element, b: &G::Element) -> G::Element {
+        PrimeOrderGroup::mul(self, a, b)
+    }
+}
+
+/// The number of bits per window of [`straus`] and [`FixedBaseTable`], which the secret digits
+/// of [`FixedBaseTable::exp_secret`] also assume.
+const WINDOW: usize = 4;
+
+/// From this number of bases on, [`multi_exp`] uses [`pippenger`] rather than [`straus`].
+const PIPPENGER_THRESHOLD: usize = 64;
+
+/// The product of `bases[i]^exponents[i]`, with the faster of [`straus`] and [`pippenger`].
+pub fn multi_exp<G: Multiply + ?Sized>(
+    group: &G,
+    bases: &[G::Element],
+    exponents: &[BigInt],
+) -> G::Element {
+    if bases.len() < PIPPENGER_THRESHOLD {
+        straus(group, bases, exponents)
+    } else {
+        pippenger(group, bases, exponents)
+    }
+}
+
+/// The product of `bases[i]^exponents[i]`, going through the exponents together, a window of
+/// bits at a time.
+pub fn straus<G: Multiply + ?Sized>(
+    group: &G,
+    bases: &[G::Element],
+    exponents: &[BigInt],
+) -> G::Element {
+    assert_eq!(bases.len(), exponents.len());
+    let digits = exponents
+        .iter()
+        .map(|e| Digits::new(e, WINDOW))
+        .collect::<Vec<_>>();
+    let windows = digits.iter().map(Digits::len).max().unwrap_or(0);
+    // `tables[i][d]` is `bases[i]^d`, for every digit `d`.
+    let tables = bases
+        .iter()
+        .map(|base| powers(group, base, 1 << WINDOW))
+        .collect::<Vec<_>>();
+
+    let mut acc: Option<G::Element> = None;
+    for i in (0..windows).rev() {
+        if let Some(x) = acc.as_mut() {
+            for _ in 0..WINDOW {
+                *x = group.mul(x, x);
+            }
+        }
+        for (table, digits) in tables.iter().zip(&digits) {
+            let d = digits.get(i);
+            if d != 0 {
+                acc = Some(mul_or_set(group, acc, &table[d]));
+            }
+        }
+    }
+    acc.unwrap_or_else(|| group.identity())
+}
+
+/// The product of `bases[i]^exponents[i]`, with the bucket method of Pippenger.
+pub fn pippenger<G: Multiply + ?Sized>(
+    group: &G,
+    bases: &[G::Element],
+    exponents: &[BigInt],
+) -> G::Element {
+    assert_eq!(bases.len(), exponents.len());
+    // Windows of about `log n` bits balance the work of filling and of summing the buckets.
+    let window = ((bases.len() as f64).ln() as usize).max(WINDOW);
+    let digits = exponents
+        .iter()
+        .map(|e| Digits::new(e, window))
+        .collect::<Vec<_>>();
+    let windows = digits.iter().map(Digits::len).max().unwrap_or(0);
+
+    let mut acc: Option<G::Element> = None;
+    for i in (0..windows).rev() {
+        if let Some(x) = acc.as_mut() {
+            for _ in 0..window {
+                *x = group.mul(x, x);
+            }
+        }
+
+        // `buckets[d - 1]` is the product of the bases whose digit is `d`.
+        let mut buckets: Vec<Option<G::Element>> = vec![None; (1 << window) - 1];
+        for (base, digits) in bases.iter().zip(&digits) {
+            let d = digits.get(i);
+            if d != 0 {
+                buckets[d - 1] = Some(mul_or_set(group, buckets[d - 1].take(), base));
+            }
+        }
+
+        // The sum of `d` times bucket `d`, as the sum of the running sums from the top.
+        let mut running = None;
+        let mut sum = None;
+        for bucket in buckets.into_iter().rev() {
+            if let Some(bucket) = bucket {
+                running = Some(mul_or_set(group, running, &bucket));
+            }
+            if let Some(running) = &running {
+                sum = Some(mul_or_set(group, sum, running));
+            }
+        }
+        if let Some(sum) = sum {
+            acc = Some(mul_or_set(group, acc, &sum));
+        }
+    }
+    acc.unwrap_or_else(|| group.identity())
+}
+
+/// The powers `base^(d 2^(w i))` of a base, for every digit `d` of `w` bits and every window
+/// `i`, so that raising the base to an exponent only takes one multiplication per window.
+#[derive(Clone, Debug)]
+pub struct FixedBaseTable<E> {
+    base: E,
+    bits: usize,
+    table: Vec<Vec<E>>,
+}
+
+impl<E: Clone> FixedBaseTable<E> {
+    /// The table for exponents of up to `bits` bits.
+    pub fn new<G: Multiply<Element = E> + ?Sized>(group: &G, base: &E, bits: usize) -> Self {
+        let mut table = Vec::with_capacity(bits.div_ceil(WINDOW));
+        let mut current = base.clone();
+        for _ in 0..bits.div_ceil(WINDOW) {
+            let row = powers(group, &current, 1 << WINDOW);
+            // The next row starts from `current^(2^w)`.
+            current = group.mul(&row[(1 << WINDOW) - 1], &current);
+            table.push(row);
+        }
+        FixedBaseTable {
+            base: base.clone(),
+            bits,
+            table,
+        }
+    }
+
+    pub fn base(&self) -> &E {
+        &self.base
+    }
+
+    /// The base raised to `exponent`, which falls back to [`straus`] if the exponent has more
+    /// bits than the table.
+    pub fn exp<G: Multiply<Element = E> + ?Sized>(&self, group: &G, exponent: &BigInt) -> E {
+        if exponent.bits() > self.bits {
+            return straus(
+                group,
+                std::slice::from_ref(&self.base),
+                std::slice::from_ref(exponent),
+            );
+        }
+        let digits = Digits::new(exponent, WINDOW);
+        let mut acc = None;
+        for (i, row) in self.table.iter().enumerate().take(digits.len()) {
+            let d = digits.get(i);
+            if d != 0 {
+                acc = Some(mul_or_set(group, acc, &row[d]));
+            }
+        }
+        acc.unwrap_or_else(|| group.identity())
+    }
+}
+
+impl FixedBaseTable<Residue> {
+    /// The base raised to a secret `exponent`, which must be below `2^bits` for the `bits` of
+    /// the table.
+    ///
+    /// Unlike [`FixedBaseTable::exp`], this multiplies by an entry of every row, even for a zero
+    /// digit, and reads it with [`Modulus::select`], so that neither the time nor the memory
+    /// accesses depend on the exponent.
+    pub fn exp_secret(&self, modulus: &Modulus, exponent: &BigInt) -> Residue {
+        secret_digits(exponent, self.bits)
+            .into_iter()
+            .zip(&self.table)
+            .fold(modulus.one(), |acc, (d, row)| {
+                modulus.mul(&acc, &modulus.select(row, d))
+            })
+    }
+}
+
+/// `base^0, base^1, ..., base^(n-1)`.
+fn powers<G: Multiply + ?Sized>(group: &G, base: &G::Element, n: usize) -> Vec<G::Element> {
+    let mut powers = Vec::with_capacity(n);
+    powers.push(group.identity());
+    for i in 1..n {
+        powers.push(group.mul(&powers[i - 1], base));
+    }
+    powers
+}
+
+/// `acc * x`, where `None` stands for the identity, which saves a multiplication.
+fn mul_or_set<G: Multiply + ?Sized>(
+    group: &G,
+    acc: Option<G::Element>,
+    x: &G::Element,
+) -> G::Element {
+    match acc {
+        Some(acc) => group.mul(&acc, x),
+        None => x.clone(),
+    }
+}
+
+/// The digits of an exponent in base `2^window`, from the least significant one.
+struct Digits {
+    bytes: Vec<u8>,
+    window: usize,
+    len: usize,
+}
+
+impl Digits {
+    fn new(exponent: &BigInt, window: usize) -> Self {
+        let (sign, bytes) = exponent.to_bytes_le();
+        assert!(sign != Sign::Minus, "Exponents must not be negative.");
+        Digits {
+            bytes,
+            window,
+            len: exponent.bits().div_ceil(window),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The digit at position `i`, or zero past the end.
+    fn get(&self, i: usize) -> usize {
+        let start = i * self.window;
+        (0..self.window)
+            .map(|j| start + j)
+            .filter(|bit| {
+                self.bytes
+                    .get(bit / 8)
+                    .is_some_and(|byte| byte >> (bit % 8) & 1 == 1)
+            })
+            .fold(0, |acc, bit| acc | 1 << (bit - start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::group::{PrimeOrderGroup, SchnorrGroup};
+
+    use super::{pippenger, straus, FixedBaseTable};
+
+    fn make_group() -> SchnorrGroup {
+        SchnorrGroup::new(BigInt::from(1907), BigInt::from(953), BigInt::from(343)).unwrap()
+    }
+
+    /// The product of `bases[i]^exponents[i]`, with one exponentiation each.
+    fn naive(group: &SchnorrGroup, bases: &[BigInt], exponents: &[BigInt]) -> BigInt {
+        let p = group.modulus();
+        bases
+            .iter()
+            .zip(exponents)
+            .fold(BigInt::from(1), |acc, (b, e)| acc * b.modpow(e, p) % p)
+    }
+
+    #[test]
+    fn multi_exponentiations_match_naive_one() {
+        let group = make_group();
+        for n in [0, 1, 2, 7, 100] {
+            let bases = (0..n)
+                .map(|_| group.exp(&group.generator(), &group.random_scalar()))
+                .collect::<Vec<_>>();
+            // Include zero and exponents above the order, which are not reduced.
+            let exponents = (0..n)
+                .map(|i| match i % 3 {
+                    0 => BigInt::from(0),
+                    1 => group.random_scalar() * 12345,
+                    _ => group.random_scalar(),
+                })
+                .collect::<Vec<_>>();
+            let expected = naive(&group, &bases, &exponents);
+            assert_eq!(straus(&group, &bases, &exponents), expected);
+            assert_eq!(pippenger(&group, &bases, &exponents), expected);
+            assert_eq!(group.multi_exp(&bases, &exponents), expected);
+        }
+    }
+
+    #[test]
+    fn fixed_base_table_matches_exponentiation() {
+        let group = make_group();
+        let g = group.generator();
+        let table = FixedBaseTable::new(&group, &g, 10);
+        for e in [0, 1, 15, 16, 952, 1023, 1024, 123456] {
+            let e = BigInt::from(e);
+            assert_eq!(
+                table.exp(&group, &e),
+                naive(&group, std::slice::from_ref(&g), &[e])
+            );
+        }
+    }
+}
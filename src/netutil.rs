@@ -1,30 +1,869 @@
 use std::{
+    fmt::{self, Display},
     io::{self, Read, Write},
     net::TcpStream,
+    time::{Duration, Instant},
 };
 
+use num::{bigint::Sign, BigInt};
 use serde::{de::DeserializeOwned, Serialize};
 
-pub fn read_value_from_stream<T: DeserializeOwned>(stream: &mut TcpStream) -> io::Result<T> {
-    // Each value is prefixed by 4 bytes specifying the length.
-    let mut buf = [0; 4];
-    stream.read_exact(&mut buf)?;
-    let length = u32::from_be_bytes(buf);
+use crate::{
+    schnorr::SchnorrDiscreteLogInstance,
+    secure::{EphemeralKey, SecureChannelError, SecureSession, TAG_LEN},
+    session::{random_contribution, Session, SessionId},
+};
+
+/// The largest frame accepted by default, which is plenty for a few 2048-bit numbers.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 1 << 20;
+
+/// An error from sending or receiving a frame.
+#[derive(Debug)]
+pub enum NetError {
+    /// The frame is longer than the maximum allowed length.
+    FrameTooLarge {
+        length: usize,
+        max: usize,
+    },
+    /// The stream ended before the whole frame was received.
+    Truncated {
+        expected: usize,
+        received: usize,
+    },
+    /// The frame was not received before the read deadline.
+    ReadTimeout,
+    /// The frame was not sent before the write deadline.
+    WriteTimeout,
+    /// The frame was received, but its contents could not be decoded.
+    Malformed(io::Error),
+    /// The secure channel could not be set up, or a frame failed authentication.
+    Secure(SecureChannelError),
+    Io(io::Error),
+}
 
-    let mut data = vec![0; length as usize];
-    stream.read_exact(&mut data)?;
+impl Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::FrameTooLarge { length, max } => {
+                write!(f, "frame of {} bytes exceeds the limit of {}", length, max)
+            }
+            NetError::Truncated { expected, received } => {
+                write!(f, "stream ended after {} of {} bytes", received, expected)
+            }
+            NetError::ReadTimeout => write!(f, "read deadline exceeded"),
+            NetError::WriteTimeout => write!(f, "write deadline exceeded"),
+            NetError::Malformed(err) => write!(f, "malformed frame: {}", err),
+            NetError::Secure(err) => write!(f, "secure channel: {:?}", err),
+            NetError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<NetError> for io::Error {
+    fn from(err: NetError) -> Self {
+        let kind = match &err {
+            NetError::FrameTooLarge { .. } | NetError::Malformed(_) | NetError::Secure(_) => {
+                io::ErrorKind::InvalidData
+            }
+            NetError::Truncated { .. } => io::ErrorKind::UnexpectedEof,
+            NetError::ReadTimeout | NetError::WriteTimeout => io::ErrorKind::TimedOut,
+            NetError::Io(err) => return io::Error::new(err.kind(), err.to_string()),
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Limits on the frames sent and received over a [`Channel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The largest payload, in bytes, that may be sent or received.
+    pub max_frame_len: usize,
+    /// How long receiving a whole frame may take, if limited.
+    pub read_timeout: Option<Duration>,
+    /// How long sending a whole frame may take, if limited.
+    pub write_timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            read_timeout: Some(Duration::from_secs(30)),
+            write_timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+pub fn read_value_from_stream<T: DeserializeOwned>(stream: &mut impl Read) -> io::Result<T> {
+    let data = read_frame(stream, DEFAULT_MAX_FRAME_LEN)?;
     let s = String::from_utf8(data).map_err(|r| io::Error::new(io::ErrorKind::InvalidData, r))?;
 
     let val: T = serde_json::from_str(&s)?;
     Ok(val)
 }
 
-pub fn write_value_to_stream<T: Serialize>(stream: &mut TcpStream, value: &T) -> io::Result<()> {
+pub fn write_value_to_stream<T: Serialize>(stream: &mut impl Write, value: &T) -> io::Result<()> {
     let s = serde_json::to_string(value)?;
-    let data = s.as_bytes();
+    write_frame(stream, s.as_bytes(), DEFAULT_MAX_FRAME_LEN)?;
+    Ok(())
+}
+
+/// Read a single frame, without interpreting its contents.
+///
+/// The length prefix is checked against `max_len` before anything is allocated.
+pub fn read_frame(stream: &mut impl Read, max_len: usize) -> Result<Vec<u8>, NetError> {
+    // Each frame is prefixed by 4 bytes specifying the length.
+    let mut buf = [0; 4];
+    read_full(stream, &mut buf)?;
+    let length = u32::from_be_bytes(buf) as usize;
+    if length > max_len {
+        return Err(NetError::FrameTooLarge {
+            length,
+            max: max_len,
+        });
+    }
+
+    let mut data = vec![0; length];
+    read_full(stream, &mut data)?;
+    Ok(data)
+}
+
+/// Write `data` as a single frame, refusing to send more than `max_len` bytes.
+pub fn write_frame(stream: &mut impl Write, data: &[u8], max_len: usize) -> Result<(), NetError> {
+    let max = max_len.min(u32::MAX as usize);
+    if data.len() > max {
+        return Err(NetError::FrameTooLarge {
+            length: data.len(),
+            max,
+        });
+    }
     let length = data.len() as u32;
 
-    stream.write_all(&length.to_be_bytes())?;
-    stream.write_all(data)?;
+    let res = stream
+        .write_all(&length.to_be_bytes())
+        .and_then(|_| stream.write_all(data))
+        .and_then(|_| stream.flush());
+    match res {
+        Ok(()) => Ok(()),
+        Err(err) if is_timeout(&err) => Err(NetError::WriteTimeout),
+        Err(err) => Err(NetError::Io(err)),
+    }
+}
+
+/// Like `read_exact`, but keeps track of how much was read before the stream ended.
+fn read_full(stream: &mut impl Read, buf: &mut [u8]) -> Result<(), NetError> {
+    let mut received = 0;
+    while received < buf.len() {
+        match stream.read(&mut buf[received..]) {
+            Ok(0) => {
+                return Err(NetError::Truncated {
+                    expected: buf.len(),
+                    received,
+                })
+            }
+            Ok(n) => received += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) if is_timeout(&err) => return Err(NetError::ReadTimeout),
+            Err(err) => return Err(NetError::Io(err)),
+        }
+    }
     Ok(())
 }
+
+fn is_timeout(err: &io::Error) -> bool {
+    // Depending on the platform, socket timeouts are reported as either of these.
+    matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+/// A stream whose reads and writes must all finish before a common deadline.
+///
+/// Socket timeouts only bound each individual call, so the remaining time is applied before
+/// every call. This stops a peer from stalling us by trickling out a frame one byte at a time.
+struct DeadlineStream<'a> {
+    stream: &'a mut TcpStream,
+    deadline: Option<Instant>,
+}
+
+impl<'a> DeadlineStream<'a> {
+    fn new(stream: &'a mut TcpStream, timeout: Option<Duration>) -> Self {
+        DeadlineStream {
+            stream,
+            deadline: timeout.map(|t| Instant::now() + t),
+        }
+    }
+
+    fn remaining(&self) -> io::Result<Option<Duration>> {
+        match self.deadline {
+            None => Ok(None),
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => Ok(Some(remaining)),
+                _ => Err(io::Error::new(io::ErrorKind::TimedOut, "deadline exceeded")),
+            },
+        }
+    }
+}
+
+impl Read for DeadlineStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.remaining()?;
+        self.stream.set_read_timeout(remaining)?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let remaining = self.remaining()?;
+        self.stream.set_write_timeout(remaining)?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// The encoding used for the values sent over a [`Channel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codec {
+    /// Human-readable JSON. This is the default, and is useful for debugging.
+    Json,
+    /// Compact binary encoding, where every integer is written big-endian with a fixed width.
+    ///
+    /// Group elements take up `element_len` bytes and scalars take up `scalar_len` bytes.
+    Binary {
+        element_len: usize,
+        scalar_len: usize,
+    },
+}
+
+impl Codec {
+    /// The binary codec with widths fitting the group of `instance`.
+    pub fn binary_for(instance: &SchnorrDiscreteLogInstance) -> Self {
+        Codec::Binary {
+            element_len: byte_len(instance.p()),
+            scalar_len: byte_len(instance.q()),
+        }
+    }
+
+    /// Encode `value` as the payload of a frame.
+    pub fn encode<T: WireValue>(&self, value: &T, kind: IntKind) -> io::Result<Vec<u8>> {
+        match *self {
+            Codec::Json => Ok(serde_json::to_vec(value)?),
+            Codec::Binary {
+                element_len,
+                scalar_len,
+            } => {
+                let mut out = Vec::new();
+                value.encode(kind.select(element_len, scalar_len), &mut out)?;
+                Ok(out)
+            }
+        }
+    }
+
+    /// Decode a value from the payload of a frame.
+    pub fn decode<T: WireValue>(&self, data: &[u8], kind: IntKind) -> io::Result<T> {
+        match *self {
+            Codec::Json => Ok(serde_json::from_slice(data)?),
+            Codec::Binary {
+                element_len,
+                scalar_len,
+            } => {
+                let mut input = data;
+                let val = T::decode(kind.select(element_len, scalar_len), &mut input)?;
+                if !input.is_empty() {
+                    return Err(invalid_data("trailing bytes after value"));
+                }
+                Ok(val)
+            }
+        }
+    }
+}
+
+/// Whether the integers of a message are group elements or scalars.
+///
+/// This only matters for [`Codec::Binary`], which needs to know the width of each integer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntKind {
+    Element,
+    Scalar,
+}
+
+impl IntKind {
+    fn select(self, element_len: usize, scalar_len: usize) -> usize {
+        match self {
+            IntKind::Element => element_len,
+            IntKind::Scalar => scalar_len,
+        }
+    }
+}
+
+/// A value that can be encoded in the binary wire format.
+///
+/// All integers in the value are written with the same fixed width of `int_len` bytes.
+pub trait BinaryEncode: Sized {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()>;
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self>;
+}
+
+/// A value that can be sent over a [`Channel`] with any [`Codec`].
+pub trait WireValue: Serialize + DeserializeOwned + BinaryEncode {}
+
+impl<T: Serialize + DeserializeOwned + BinaryEncode> WireValue for T {}
+
+/// Which side of a connection a party is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// The party that opened the connection.
+    Initiator,
+    /// The party that accepted the connection.
+    Responder,
+}
+
+/// A connection to a peer, which sends and receives framed values.
+pub struct Channel {
+    stream: TcpStream,
+    codec: Codec,
+    limits: Limits,
+    secure: Option<SecureSession>,
+    session: Option<Session>,
+}
+
+impl Channel {
+    pub fn new(stream: TcpStream, codec: Codec) -> Self {
+        Self::with_limits(stream, codec, Limits::default())
+    }
+
+    pub fn with_limits(stream: TcpStream, codec: Codec, limits: Limits) -> Self {
+        Channel {
+            stream,
+            codec,
+            limits,
+            secure: None,
+            session: None,
+        }
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Set up a secure channel in the group of `group`, and encrypt every frame from now on.
+    ///
+    /// Both parties must call this at the same point, with the same group and opposite roles.
+    pub fn secure(
+        &mut self,
+        group: &SchnorrDiscreteLogInstance,
+        role: Role,
+    ) -> Result<(), NetError> {
+        let key = EphemeralKey::generate(group);
+        let peer_public: BigInt = match role {
+            Role::Initiator => {
+                self.send(key.public(), IntKind::Element)?;
+                self.receive(IntKind::Element)?
+            }
+            Role::Responder => {
+                let peer_public = self.receive(IntKind::Element)?;
+                self.send(key.public(), IntKind::Element)?;
+                peer_public
+            }
+        };
+
+        let session =
+            SecureSession::derive(group, role, &key, &peer_public).map_err(NetError::Secure)?;
+        self.secure = Some(session);
+        Ok(())
+    }
+
+    /// The binding of the secure channel, if one has been set up.
+    pub fn binding(&self) -> Option<&[u8; 32]> {
+        self.secure.as_ref().map(SecureSession::binding)
+    }
+
+    /// Agree on a fresh session id with the peer, and record every message from now on.
+    ///
+    /// See [`Session::new`] for the meaning of `binds_challenges`.
+    pub fn start_session(
+        &mut self,
+        role: Role,
+        binds_challenges: bool,
+    ) -> Result<SessionId, NetError> {
+        let own = random_contribution();
+        let id = match role {
+            Role::Initiator => {
+                self.send(&own, IntKind::Scalar)?;
+                let peer = self.receive(IntKind::Scalar)?;
+                SessionId::from_contributions(&own, &peer)
+            }
+            Role::Responder => {
+                let peer = self.receive(IntKind::Scalar)?;
+                self.send(&own, IntKind::Scalar)?;
+                SessionId::from_contributions(&peer, &own)
+            }
+        };
+
+        self.session = Some(Session::new(id, role, binds_challenges));
+        Ok(id)
+    }
+
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub fn session_mut(&mut self) -> Option<&mut Session> {
+        self.session.as_mut()
+    }
+
+    pub fn send<T: WireValue>(&mut self, value: &T, kind: IntKind) -> Result<(), NetError> {
+        let mut data = self.codec.encode(value, kind).map_err(NetError::Io)?;
+        if let Some(session) = &mut self.session {
+            session.record_sent(&data);
+        }
+        let mut max_len = self.limits.max_frame_len;
+        if let Some(session) = &mut self.secure {
+            if data.len() > max_len {
+                return Err(NetError::FrameTooLarge {
+                    length: data.len(),
+                    max: max_len,
+                });
+            }
+            data = session.seal(&data).map_err(NetError::Secure)?;
+            max_len += TAG_LEN;
+        }
+
+        let mut stream = DeadlineStream::new(&mut self.stream, self.limits.write_timeout);
+        write_frame(&mut stream, &data, max_len)
+    }
+
+    pub fn receive<T: WireValue>(&mut self, kind: IntKind) -> Result<T, NetError> {
+        let mut max_len = self.limits.max_frame_len;
+        if self.secure.is_some() {
+            max_len += TAG_LEN;
+        }
+
+        let mut stream = DeadlineStream::new(&mut self.stream, self.limits.read_timeout);
+        let mut data = read_frame(&mut stream, max_len)?;
+        if let Some(session) = &mut self.secure {
+            data = session.open(&data).map_err(NetError::Secure)?;
+        }
+        if let Some(session) = &mut self.session {
+            session.record_received(&data);
+        }
+        self.codec.decode(&data, kind).map_err(NetError::Malformed)
+    }
+}
+
+impl From<TcpStream> for Channel {
+    fn from(stream: TcpStream) -> Self {
+        Channel::new(stream, Codec::Json)
+    }
+}
+
+fn byte_len(n: &BigInt) -> usize {
+    n.bits().div_ceil(8)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
+    if input.len() < n {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "value is truncated",
+        ));
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+impl BinaryEncode for BigInt {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        let (sign, bytes) = self.to_bytes_be();
+        if sign == Sign::Minus {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot encode a negative integer",
+            ));
+        }
+
+        // Zero is encoded as a single zero byte by `to_bytes_be`.
+        let bytes = if sign == Sign::NoSign {
+            &[][..]
+        } else {
+            &bytes
+        };
+        if bytes.len() > int_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("integer does not fit in {} bytes", int_len),
+            ));
+        }
+
+        out.resize(out.len() + int_len - bytes.len(), 0);
+        out.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let bytes = take(input, int_len)?;
+        Ok(BigInt::from_bytes_be(Sign::Plus, bytes))
+    }
+}
+
+impl BinaryEncode for bool {
+    fn encode(&self, _: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        out.push(*self as u8);
+        Ok(())
+    }
+
+    fn decode(_: usize, input: &mut &[u8]) -> io::Result<Self> {
+        match take(input, 1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid boolean")),
+        }
+    }
+}
+
+impl BinaryEncode for u32 {
+    fn encode(&self, _: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        out.extend_from_slice(&self.to_be_bytes());
+        Ok(())
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        Ok(u32::from_be_bytes(<[u8; 4]>::decode(int_len, input)?))
+    }
+}
+
+impl<T: BinaryEncode> BinaryEncode for Vec<T> {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        out.extend_from_slice(&(self.len() as u32).to_be_bytes());
+        for item in self {
+            item.encode(int_len, out)?;
+        }
+        Ok(())
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(take(input, 4)?);
+        let length = u32::from_be_bytes(buf) as usize;
+
+        // Don't trust the length for the allocation, since every item takes up at least a byte.
+        let mut items = Vec::with_capacity(length.min(input.len()));
+        for _ in 0..length {
+            items.push(T::decode(int_len, input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<const N: usize> BinaryEncode for [u8; N] {
+    fn encode(&self, _: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        out.extend_from_slice(self);
+        Ok(())
+    }
+
+    fn decode(_: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(take(input, N)?);
+        Ok(bytes)
+    }
+}
+
+macro_rules! impl_binary_encode_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: BinaryEncode),+> BinaryEncode for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+                let ($($name,)+) = self;
+                $($name.encode(int_len, out)?;)+
+                Ok(())
+            }
+
+            fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+                Ok(($($name::decode(int_len, input)?,)+))
+            }
+        }
+    };
+}
+
+impl_binary_encode_for_tuple!(A, B);
+impl_binary_encode_for_tuple!(A, B, C);
+impl_binary_encode_for_tuple!(A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        net::{TcpListener, TcpStream},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use num::{bigint::RandBigInt, BigInt};
+    use rand::thread_rng;
+
+    use crate::schnorr::SchnorrDiscreteLogInstance;
+
+    use super::{
+        read_frame, read_value_from_stream, write_frame, write_value_to_stream, BinaryEncode,
+        Channel, Codec, IntKind, Limits, NetError, WireValue,
+    };
+
+    const CODEC_2048: Codec = Codec::Binary {
+        element_len: 256,
+        scalar_len: 32,
+    };
+
+    fn round_trip<T: WireValue>(codec: Codec, value: &T, kind: IntKind) -> T {
+        let data = codec.encode(value, kind).unwrap();
+        codec.decode(&data, kind).unwrap()
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut rng = thread_rng();
+        let a = rng.gen_bigint_range(&BigInt::from(0), &(BigInt::from(1) << 2048));
+        let e = rng.gen_bigint_range(&BigInt::from(0), &(BigInt::from(1) << 255));
+        let z = BigInt::from(0);
+
+        assert_eq!(round_trip(CODEC_2048, &a, IntKind::Element), a);
+        let response = (e.clone(), z.clone(), e, z);
+        assert_eq!(round_trip(CODEC_2048, &response, IntKind::Scalar), response);
+        assert!(round_trip(CODEC_2048, &true, IntKind::Scalar));
+        assert_eq!(
+            round_trip(CODEC_2048, &vec![7u32, 1 << 31], IntKind::Scalar),
+            vec![7, 1 << 31]
+        );
+    }
+
+    #[test]
+    fn binary_round_trip_instance() {
+        let (instance, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let codec = Codec::binary_for(&instance);
+        let decoded = round_trip(codec, &instance, IntKind::Element);
+        assert_eq!(format!("{:?}", instance), format!("{:?}", decoded));
+    }
+
+    #[test]
+    fn binary_integers_have_fixed_width() {
+        let small = CODEC_2048.encode(&BigInt::from(1), IntKind::Element);
+        assert_eq!(small.unwrap().len(), 256);
+        let small = CODEC_2048.encode(&BigInt::from(1), IntKind::Scalar);
+        assert_eq!(small.unwrap().len(), 32);
+    }
+
+    #[test]
+    fn binary_rejects_unrepresentable_integers() {
+        let mut out = Vec::new();
+        assert!(BigInt::from(-1).encode(32, &mut out).is_err());
+        assert!((BigInt::from(1) << 256).encode(32, &mut out).is_err());
+    }
+
+    #[test]
+    fn binary_rejects_truncated_and_trailing_data() {
+        let data = CODEC_2048
+            .encode(&BigInt::from(7), IntKind::Scalar)
+            .unwrap();
+        let res: std::io::Result<BigInt> = CODEC_2048.decode(&data[1..], IntKind::Scalar);
+        assert!(res.is_err());
+
+        let mut data = data;
+        data.push(0);
+        let res: std::io::Result<BigInt> = CODEC_2048.decode(&data, IntKind::Scalar);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn binary_frames_are_smaller_than_json_frames() {
+        let mut rng = thread_rng();
+        let a = rng.gen_bigint_range(&BigInt::from(0), &(BigInt::from(1) << 2048));
+
+        let mut json_frame = Vec::new();
+        write_value_to_stream(&mut json_frame, &a).unwrap();
+        let binary = CODEC_2048.encode(&a, IntKind::Element).unwrap();
+
+        assert!(
+            binary.len() + 4 < json_frame.len() / 2,
+            "binary: {}, json: {}",
+            binary.len() + 4,
+            json_frame.len()
+        );
+
+        // The JSON frames are still readable the same way as before.
+        let decoded: BigInt = read_value_from_stream(&mut &json_frame[..]).unwrap();
+        assert_eq!(decoded, a);
+    }
+
+    fn channel_pair(limits: Limits) -> (Channel, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        (Channel::with_limits(stream, Codec::Json, limits), peer)
+    }
+
+    #[test]
+    fn rejects_oversized_frame() {
+        let data = u32::MAX.to_be_bytes();
+        let res = read_frame(&mut &data[..], 1024);
+        assert!(
+            matches!(
+                res,
+                Err(NetError::FrameTooLarge {
+                    length: 0xFFFF_FFFF,
+                    max: 1024
+                })
+            ),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn refuses_to_send_oversized_frame() {
+        let mut out = Vec::new();
+        let res = write_frame(&mut out, &[0; 11], 10);
+        assert!(matches!(
+            res,
+            Err(NetError::FrameTooLarge {
+                length: 11,
+                max: 10
+            })
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_frame() {
+        let mut data = 10_u32.to_be_bytes().to_vec();
+        data.extend_from_slice(&[1, 2, 3]);
+        let res = read_frame(&mut &data[..], 1024);
+        assert!(
+            matches!(
+                res,
+                Err(NetError::Truncated {
+                    expected: 10,
+                    received: 3
+                })
+            ),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_length_prefix() {
+        let data = [0, 0];
+        let res = read_frame(&mut &data[..], 1024);
+        assert!(
+            matches!(
+                res,
+                Err(NetError::Truncated {
+                    expected: 4,
+                    received: 2
+                })
+            ),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn channel_rejects_oversized_frame_from_peer() {
+        let limits = Limits {
+            max_frame_len: 16,
+            ..Limits::default()
+        };
+        let (mut channel, mut peer) = channel_pair(limits);
+        write_value_to_stream(&mut peer, &vec![true; 100]).unwrap();
+
+        let res = channel.receive::<Vec<bool>>(IntKind::Scalar);
+        assert!(matches!(res, Err(NetError::FrameTooLarge { max: 16, .. })));
+    }
+
+    #[test]
+    fn channel_rejects_malformed_frame() {
+        let (mut channel, mut peer) = channel_pair(Limits::default());
+        write_frame(&mut peer, b"not json", 1024).unwrap();
+
+        let res = channel.receive::<bool>(IntKind::Scalar);
+        assert!(matches!(res, Err(NetError::Malformed(_))), "{:?}", res);
+    }
+
+    #[test]
+    fn read_times_out_on_silent_peer() {
+        let limits = Limits {
+            read_timeout: Some(Duration::from_millis(100)),
+            ..Limits::default()
+        };
+        let (mut channel, _peer) = channel_pair(limits);
+
+        let res = channel.receive::<bool>(IntKind::Scalar);
+        assert!(matches!(res, Err(NetError::ReadTimeout)), "{:?}", res);
+    }
+
+    #[test]
+    fn read_deadline_covers_the_whole_frame() {
+        let limits = Limits {
+            read_timeout: Some(Duration::from_millis(300)),
+            ..Limits::default()
+        };
+        let (mut channel, mut peer) = channel_pair(limits);
+
+        // Send a byte every 100 ms, so that no single read ever times out.
+        let handle = thread::spawn(move || {
+            peer.write_all(&8_u32.to_be_bytes()).unwrap();
+            for _ in 0..8 {
+                thread::sleep(Duration::from_millis(100));
+                if peer.write_all(b" ").is_err() {
+                    break;
+                }
+            }
+        });
+
+        let start = Instant::now();
+        let res = channel.receive::<bool>(IntKind::Scalar);
+        assert!(matches!(res, Err(NetError::ReadTimeout)), "{:?}", res);
+        assert!(start.elapsed() < Duration::from_millis(600));
+        drop(channel);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn write_times_out_on_peer_that_does_not_read() {
+        let limits = Limits {
+            max_frame_len: 1 << 28,
+            write_timeout: Some(Duration::from_millis(200)),
+            ..Limits::default()
+        };
+        let (mut channel, _peer) = channel_pair(limits);
+        channel.set_codec(CODEC_2048);
+
+        // Far more than the socket buffers can hold.
+        let value = vec![true; 1 << 26];
+        let res = channel.send(&value, IntKind::Scalar);
+        assert!(matches!(res, Err(NetError::WriteTimeout)), "{:?}", res);
+    }
+}
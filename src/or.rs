@@ -1,6 +1,6 @@
 use std::{fmt::Debug, ops::BitXor};
 
-use crate::SigmaProtocol;
+use crate::{Challenge, ChallengeSpace, SigmaProtocol};
 
 pub struct OrProtocol<P, E, Z> {
     protocols: (P, P),
@@ -11,6 +11,13 @@ pub struct OrProtocol<P, E, Z> {
 pub enum OrProtocolVerifierError<VError> {
     SubProtocolError(VError),
     ChallengeXorNotEqual(String),
+    /// The challenge of the statement at this index is outside the challenge space.
+    ChallengeOutOfRange(usize),
+    /// A message does not have one part for each statement.
+    WrongLength {
+        expected: usize,
+        actual: usize,
+    },
 }
 
 impl<P, X, W, A, E, Z> SigmaProtocol<(X, X), W, (A, A), E, (E, Z, E, Z)> for OrProtocol<P, E, Z>
@@ -94,6 +101,172 @@ where
     }
 }
 
+/// An OR of any number of statements, proving knowledge of a witness for one of them without
+/// revealing which.
+///
+/// The witness is the index of the statement it belongs to, together with the witness itself.
+/// Every other statement gets a simulated transcript, and the challenges of all statements must
+/// XOR to the verifier's challenge.
+pub struct NaryOrProtocol<P, E, Z> {
+    protocols: Vec<P>,
+    /// The challenge bits of the first statement, which bound the challenge of every statement.
+    challenge_bits: usize,
+    known: Option<usize>,
+    chosen_values: Vec<Option<(E, Z)>>,
+}
+
+impl<P, X, W, A, E, Z> SigmaProtocol<Vec<X>, (usize, W), Vec<A>, E, Vec<(E, Z)>>
+    for NaryOrProtocol<P, E, Z>
+where
+    P: SigmaProtocol<X, W, A, E, Z>,
+    X: ChallengeSpace,
+    E: BitXor<Output = E> + Challenge + PartialEq + Clone + Debug,
+    Z: Clone,
+{
+    type VerifierError = OrProtocolVerifierError<P::VerifierError>;
+
+    fn new(instance: Vec<X>, witness: Option<(usize, W)>) -> Self {
+        assert!(!instance.is_empty(), "Expected at least one statement.");
+        let challenge_bits = instance[0].challenge_bits();
+        let known = witness.as_ref().map(|(index, _)| *index);
+        if let Some(index) = known {
+            assert!(index < instance.len(), "Witness index out of range.");
+        }
+
+        let mut witness = witness.map(|(_, w)| w);
+        let protocols = instance
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let w = if Some(i) == known {
+                    witness.take()
+                } else {
+                    None
+                };
+                P::new(x, w)
+            })
+            .collect::<Vec<_>>();
+        let chosen_values = protocols.iter().map(|_| None).collect();
+
+        NaryOrProtocol {
+            protocols,
+            challenge_bits,
+            known,
+            chosen_values,
+        }
+    }
+
+    fn initial_message(&mut self) -> Vec<A> {
+        let known = self.known.expect("Witness is not yet defined.");
+        self.protocols
+            .iter_mut()
+            .zip(self.chosen_values.iter_mut())
+            .enumerate()
+            .map(|(i, (protocol, chosen))| {
+                if i == known {
+                    protocol.initial_message()
+                } else {
+                    let e = protocol.challenge();
+                    let (a, z) = protocol.simulate(&e);
+                    *chosen = Some((e, z));
+                    a
+                }
+            })
+            .collect()
+    }
+
+    fn challenge(&mut self) -> E {
+        self.protocols[0].challenge()
+    }
+
+    fn challenge_response(&mut self, challenge: &E) -> Vec<(E, Z)> {
+        let known = self.known.expect("Witness is not yet defined.");
+        let e_known = self
+            .chosen_values
+            .iter()
+            .flatten()
+            .fold(challenge.clone(), |acc, (e, _)| acc ^ e.clone());
+        let z_known = self.protocols[known].challenge_response(&e_known);
+
+        let mut response = Some((e_known, z_known));
+        self.chosen_values
+            .iter()
+            .map(|chosen| match chosen {
+                Some(values) => values.clone(),
+                None => response.take().expect("Chosen values are not yet defined."),
+            })
+            .collect()
+    }
+
+    fn check(
+        &mut self,
+        initial_msg: Vec<A>,
+        challenge: E,
+        response: Vec<(E, Z)>,
+    ) -> Result<(), Self::VerifierError> {
+        let expected = self.protocols.len();
+        for actual in [initial_msg.len(), response.len()] {
+            if actual != expected {
+                return Err(Self::VerifierError::WrongLength { expected, actual });
+            }
+        }
+
+        // Each statement only uses its challenge modulo the group order, so a challenge outside
+        // the space would give the prover more than one way to make the XOR work.
+        if let Some(index) = response
+            .iter()
+            .position(|(e, _)| !e.in_space(self.challenge_bits))
+        {
+            return Err(Self::VerifierError::ChallengeOutOfRange(index));
+        }
+
+        let e = response
+            .iter()
+            .map(|(e, _)| e.clone())
+            .reduce(|acc, e| acc ^ e)
+            .expect("Expected at least one statement.");
+        if challenge != e {
+            return Err(Self::VerifierError::ChallengeXorNotEqual(format!(
+                "e = {:?}, challenge = {:?}",
+                &e, &challenge
+            )));
+        }
+
+        for (protocol, (a, (e, z))) in self
+            .protocols
+            .iter_mut()
+            .zip(initial_msg.into_iter().zip(response))
+        {
+            protocol
+                .check(a, e, z)
+                .map_err(Self::VerifierError::SubProtocolError)?;
+        }
+
+        Ok(())
+    }
+
+    fn simulate(&mut self, challenge: &E) -> (Vec<A>, Vec<(E, Z)>) {
+        let last = self.protocols.len() - 1;
+        let mut e_last = challenge.clone();
+        let mut challenges = Vec::with_capacity(last + 1);
+        for protocol in &mut self.protocols[..last] {
+            let e = protocol.challenge();
+            e_last = e_last ^ e.clone();
+            challenges.push(e);
+        }
+        challenges.push(e_last);
+
+        self.protocols
+            .iter_mut()
+            .zip(challenges)
+            .map(|(protocol, e)| {
+                let (a, z) = protocol.simulate(&e);
+                (a, (e, z))
+            })
+            .unzip()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use num::BigInt;
@@ -103,7 +276,7 @@ mod tests {
         SigmaProtocol,
     };
 
-    use super::OrProtocol;
+    use super::{NaryOrProtocol, OrProtocol, OrProtocolVerifierError};
 
     type SchnorrOrProtocol = OrProtocol<SchnorrDiscreteLogProtocol, BigInt, BigInt>;
 
@@ -147,4 +320,78 @@ mod tests {
         let res = protocol.check(a, e, z);
         assert!(res.is_ok(), "Simulator not accepted: {:?}", res)
     }
+
+    type SchnorrNaryOrProtocol = NaryOrProtocol<SchnorrDiscreteLogProtocol, BigInt, BigInt>;
+
+    fn make_nary_instance() -> Vec<SchnorrDiscreteLogInstance> {
+        let ((x0, x1), _) = make_instance();
+        let x2 = SchnorrDiscreteLogInstance::new(
+            x0.p().clone(),
+            x0.q().clone(),
+            x0.g().clone(),
+            BigInt::from(1000),
+        );
+        vec![x1, x0, x2]
+    }
+
+    #[test]
+    fn nary_honest_run_is_accepted() {
+        let (_, witness) = make_instance();
+        let mut protocol = SchnorrNaryOrProtocol::new(make_nary_instance(), Some((1, witness)));
+        let res = protocol.run_protocol();
+        assert!(res.is_ok(), "Honest run not accepted: {:?}", res)
+    }
+
+    #[test]
+    fn nary_simulator_is_accepted() {
+        let mut protocol = SchnorrNaryOrProtocol::new(make_nary_instance(), None);
+
+        let e = BigInt::from(275);
+        let (a, z) = protocol.simulate(&e);
+
+        let res = protocol.check(a, e, z);
+        assert!(res.is_ok(), "Simulator not accepted: {:?}", res)
+    }
+
+    #[test]
+    fn nary_challenge_out_of_range_is_rejected() {
+        let (_, witness) = make_instance();
+        let mut protocol = SchnorrNaryOrProtocol::new(make_nary_instance(), Some((1, witness)));
+        let a = protocol.initial_message();
+        let e = protocol.challenge();
+        let mut response = protocol.challenge_response(&e);
+
+        // `e_0 + q` gives the same check for its statement, but changes the XOR of the
+        // challenges, which a cheating prover could use to match any challenge.
+        let q = make_nary_instance()[0].q().clone();
+        response[0].0 += q;
+        let e = response
+            .iter()
+            .fold(BigInt::from(0), |acc, (e_i, _)| acc ^ e_i);
+        let res = protocol.check(a, e, response);
+        assert!(
+            matches!(res, Err(OrProtocolVerifierError::ChallengeOutOfRange(0))),
+            "{:?}",
+            res
+        )
+    }
+
+    #[test]
+    fn nary_wrong_witness_index_is_rejected() {
+        // The test group has 9 bit challenges, which a cheating prover guesses too often.
+        let (x0, witness) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let x1 = SchnorrDiscreteLogInstance::new(
+            x0.p().clone(),
+            x0.q().clone(),
+            x0.g().clone(),
+            x0.g().modpow(&BigInt::from(5), x0.p()),
+        );
+        let mut protocol = SchnorrNaryOrProtocol::new(vec![x1, x0], Some((0, witness)));
+        let res = protocol.run_protocol();
+        assert!(
+            matches!(res, Err(OrProtocolVerifierError::SubProtocolError(_))),
+            "{:?}",
+            res
+        )
+    }
 }
@@ -1,15 +1,70 @@
-use std::net::TcpStream;
-
-use serde::{de::DeserializeOwned, Serialize};
+use serde::Serialize;
 
 use crate::{
-    netutil::{read_value_from_stream, write_value_to_stream},
-    SigmaProtocol,
+    netutil::{Channel, IntKind, WireValue},
+    Challenge, ChallengeSpace, SigmaProtocol,
 };
 
+/// The contexts that challenges on `channel` are bound to: the binding of the secure channel
+/// and the session transcript, if there are any.
+pub(crate) fn challenge_contexts(channel: &Channel) -> Vec<[u8; 32]> {
+    let mut contexts = Vec::new();
+    if let Some(binding) = channel.binding() {
+        contexts.push(*binding);
+    }
+    if let Some(session) = channel.session().filter(|s| s.binds_challenges()) {
+        contexts.push(session.transcript().hash());
+    }
+    contexts
+}
+
+/// Bind the challenge to each of `contexts`, from [`challenge_contexts`].
+///
+/// A prover on one channel then answers a different challenge than a verifier on another, so
+/// a proof cannot be relayed between them. Likewise, a transcript recorded in one session
+/// answers a different challenge than the one expected in any other session.
+///
+/// Bound challenges are spread over the whole challenge space of `bits` bits, so that binding
+/// is as strong for a short challenge as for a long one.
+pub(crate) fn bind_challenge<E: Challenge>(challenge: E, bits: usize, contexts: &[[u8; 32]]) -> E {
+    contexts.iter().fold(challenge, |challenge, context| {
+        challenge.bind(context, bits)
+    })
+}
+
+/// Record the instance in the session transcript, if there is a session.
+fn record_instance<X: Serialize>(instance: &X, channel: &mut Channel) {
+    if let Some(session) = channel.session_mut() {
+        session.record_instance(instance);
+    }
+}
+
+/// The transcript hash to put in the accept message, if the session asks for it.
+fn accept_binding(channel: &Channel) -> Option<[u8; 32]> {
+    channel
+        .session()
+        .filter(|s| s.binds_challenges())
+        .map(|s| s.transcript().hash())
+}
+
 pub struct RemoteVerifierProtocol<P> {
     protocol: P,
-    stream: TcpStream,
+    channel: Channel,
+    challenge_bits: usize,
+}
+
+impl<P> RemoteVerifierProtocol<P> {
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    pub(crate) fn channel_mut(&mut self) -> &mut Channel {
+        &mut self.channel
+    }
+
+    pub(crate) fn challenge_bits(&self) -> usize {
+        self.challenge_bits
+    }
 }
 
 #[derive(Debug)]
@@ -17,42 +72,54 @@ pub enum RemoteVerifierProtocolError<VError> {
     SubProtocolError(VError),
 }
 
-impl<P, X, W, A, E, Z> SigmaProtocol<(X, TcpStream), W, A, E, Z> for RemoteVerifierProtocol<P>
+impl<P, X, W, A, E, Z> SigmaProtocol<(X, Channel), W, A, E, Z> for RemoteVerifierProtocol<P>
 where
     P: SigmaProtocol<X, W, A, E, Z>,
-    A: Serialize + DeserializeOwned,
-    E: Serialize + DeserializeOwned,
-    Z: Serialize + DeserializeOwned,
+    X: Serialize + ChallengeSpace,
+    A: WireValue,
+    E: WireValue + Challenge,
+    Z: WireValue,
 {
     // TODO: Would be nice if this was the actual error
     type VerifierError = ();
 
-    fn new(instance: (X, TcpStream), witness: Option<W>) -> Self {
-        // TODO: I don't like having the stream be part of the instance. Is there another way?
+    fn new(instance: (X, Channel), witness: Option<W>) -> Self {
+        // TODO: I don't like having the channel be part of the instance. Is there another way?
+        let (instance, mut channel) = instance;
+        record_instance(&instance, &mut channel);
         RemoteVerifierProtocol {
-            protocol: P::new(instance.0, witness),
-            stream: instance.1,
+            challenge_bits: instance.challenge_bits(),
+            protocol: P::new(instance, witness),
+            channel,
         }
     }
 
     fn initial_message(&mut self) -> A {
         let a = self.protocol.initial_message();
-        write_value_to_stream(&mut self.stream, &a).unwrap();
+        self.channel.send(&a, IntKind::Element).unwrap();
         a
     }
 
     fn challenge(&mut self) -> E {
-        read_value_from_stream(&mut self.stream).unwrap()
+        let e = self.channel.receive(IntKind::Scalar).unwrap();
+        bind_challenge(e, self.challenge_bits, &challenge_contexts(&self.channel))
     }
 
     fn challenge_response(&mut self, challenge: &E) -> Z {
         let z = self.protocol.challenge_response(challenge);
-        write_value_to_stream(&mut self.stream, &z).unwrap();
+        self.channel.send(&z, IntKind::Scalar).unwrap();
         z
     }
 
     fn check(&mut self, _: A, _: E, _: Z) -> Result<(), Self::VerifierError> {
-        let accepted: bool = read_value_from_stream(&mut self.stream).unwrap();
+        let accepted = match accept_binding(&self.channel) {
+            Some(expected) => {
+                let (accepted, hash): (bool, [u8; 32]) =
+                    self.channel.receive(IntKind::Scalar).unwrap();
+                accepted && hash == expected
+            }
+            None => self.channel.receive(IntKind::Scalar).unwrap(),
+        };
         if accepted {
             Ok(())
         } else {
@@ -67,7 +134,26 @@ where
 
 pub struct RemoteProverProtocol<P> {
     protocol: P,
-    stream: TcpStream,
+    channel: Channel,
+    challenge_bits: usize,
+}
+
+impl<P> RemoteProverProtocol<P> {
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    pub(crate) fn channel_mut(&mut self) -> &mut Channel {
+        &mut self.channel
+    }
+
+    pub(crate) fn protocol_mut(&mut self) -> &mut P {
+        &mut self.protocol
+    }
+
+    pub(crate) fn challenge_bits(&self) -> usize {
+        self.challenge_bits
+    }
 }
 
 #[derive(Debug)]
@@ -75,34 +161,38 @@ pub enum RemoteProverProtocolError<VError> {
     SubProtocolError(VError),
 }
 
-impl<P, X, W, A, E, Z> SigmaProtocol<(X, TcpStream), W, A, E, Z> for RemoteProverProtocol<P>
+impl<P, X, W, A, E, Z> SigmaProtocol<(X, Channel), W, A, E, Z> for RemoteProverProtocol<P>
 where
     P: SigmaProtocol<X, W, A, E, Z>,
-    A: Serialize + DeserializeOwned,
-    E: Serialize + DeserializeOwned,
-    Z: Serialize + DeserializeOwned,
+    X: Serialize + ChallengeSpace,
+    A: WireValue,
+    E: WireValue + Challenge,
+    Z: WireValue,
 {
     type VerifierError = RemoteProverProtocolError<P::VerifierError>;
 
-    fn new(instance: (X, TcpStream), _: Option<W>) -> Self {
+    fn new(instance: (X, Channel), _: Option<W>) -> Self {
+        let (instance, mut channel) = instance;
+        record_instance(&instance, &mut channel);
         RemoteProverProtocol {
-            protocol: P::new(instance.0, None),
-            stream: instance.1,
+            challenge_bits: instance.challenge_bits(),
+            protocol: P::new(instance, None),
+            channel,
         }
     }
 
     fn initial_message(&mut self) -> A {
-        read_value_from_stream(&mut self.stream).unwrap()
+        self.channel.receive(IntKind::Element).unwrap()
     }
 
     fn challenge(&mut self) -> E {
         let e = self.protocol.challenge();
-        write_value_to_stream(&mut self.stream, &e).unwrap();
-        e
+        self.channel.send(&e, IntKind::Scalar).unwrap();
+        bind_challenge(e, self.challenge_bits, &challenge_contexts(&self.channel))
     }
 
     fn challenge_response(&mut self, _: &E) -> Z {
-        read_value_from_stream(&mut self.stream).unwrap()
+        self.channel.receive(IntKind::Scalar).unwrap()
     }
 
     fn check(
@@ -115,7 +205,11 @@ where
             .protocol
             .check(initial_msg, challenge, response)
             .map_err(RemoteProverProtocolError::SubProtocolError);
-        write_value_to_stream(&mut self.stream, &res.is_ok()).unwrap();
+        match accept_binding(&self.channel) {
+            Some(hash) => self.channel.send(&(res.is_ok(), hash), IntKind::Scalar),
+            None => self.channel.send(&res.is_ok(), IntKind::Scalar),
+        }
+        .unwrap();
         res
     }
 
@@ -132,25 +226,48 @@ mod tests {
         thread,
     };
 
+    use num::BigInt;
+
     use crate::{
-        schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol},
+        netutil::{Channel, Codec, IntKind, Role},
+        schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol, SchnorrVerifierError},
         SigmaProtocol,
     };
 
     use super::{RemoteProverProtocol, RemoteVerifierProtocol};
 
-    fn perform_honest_run_in_threads(p_size: usize, q_size: usize) -> io::Result<()> {
+    type Setup = fn(&mut Channel, &SchnorrDiscreteLogInstance, Role);
+
+    fn no_setup(_: &mut Channel, _: &SchnorrDiscreteLogInstance, _: Role) {}
+
+    fn secure_setup(channel: &mut Channel, instance: &SchnorrDiscreteLogInstance, role: Role) {
+        channel.secure(instance, role).unwrap();
+    }
+
+    fn session_setup(channel: &mut Channel, _: &SchnorrDiscreteLogInstance, role: Role) {
+        channel.start_session(role, true).unwrap();
+    }
+
+    fn perform_honest_run_in_threads(
+        p_size: usize,
+        q_size: usize,
+        make_codec: fn(&SchnorrDiscreteLogInstance) -> Codec,
+        setup: Setup,
+    ) -> io::Result<()> {
         let listener = TcpListener::bind("127.0.0.1:0")?;
         let listener_addr = listener.local_addr().unwrap();
 
         let (instance, witness) = SchnorrDiscreteLogInstance::generate(p_size, q_size);
         let instance_clone = instance.clone();
+        let codec = make_codec(&instance);
 
         // Start thread to handle listener/prover
         let prover_handle = thread::spawn(move || {
             let (stream, _) = listener.accept().unwrap();
+            let mut channel = Channel::new(stream, codec);
+            setup(&mut channel, &instance_clone, Role::Responder);
             let mut protocol: RemoteVerifierProtocol<SchnorrDiscreteLogProtocol> =
-                RemoteVerifierProtocol::new((instance_clone, stream), Some(witness));
+                RemoteVerifierProtocol::new((instance_clone, channel), Some(witness));
 
             protocol.run_protocol().unwrap();
         });
@@ -158,8 +275,10 @@ mod tests {
         // Start thread to handle verifier
         let verifier_handle = thread::spawn(move || {
             let stream = TcpStream::connect(listener_addr).unwrap();
+            let mut channel = Channel::new(stream, codec);
+            setup(&mut channel, &instance, Role::Initiator);
             let mut protocol: RemoteProverProtocol<SchnorrDiscreteLogProtocol> =
-                RemoteProverProtocol::new((instance, stream), None);
+                RemoteProverProtocol::new((instance, channel), None);
 
             protocol.run_protocol().unwrap();
         });
@@ -172,12 +291,188 @@ mod tests {
 
     #[test]
     fn honest_run_works_locally() -> io::Result<()> {
-        perform_honest_run_in_threads(2 << 8, 2 << 5)
+        perform_honest_run_in_threads(2 << 8, 2 << 5, |_| Codec::Json, no_setup)
+    }
+
+    #[test]
+    fn honest_run_works_with_binary_codec() -> io::Result<()> {
+        perform_honest_run_in_threads(2 << 8, 2 << 5, Codec::binary_for, no_setup)
     }
 
     #[test]
     #[ignore = "slow"]
     fn works_with_secure_params() -> io::Result<()> {
-        perform_honest_run_in_threads(2 << 10, 2 << 7)
+        perform_honest_run_in_threads(2 << 10, 2 << 7, |_| Codec::Json, secure_setup)
+    }
+
+    #[test]
+    fn honest_run_works_over_secure_channel() -> io::Result<()> {
+        perform_honest_run_in_threads(2 << 8, 2 << 5, Codec::binary_for, secure_setup)
+    }
+
+    #[test]
+    fn relayed_proof_is_rejected() -> io::Result<()> {
+        let prover_listener = TcpListener::bind("127.0.0.1:0")?;
+        let prover_addr = prover_listener.local_addr()?;
+        let relay_listener = TcpListener::bind("127.0.0.1:0")?;
+        let relay_addr = relay_listener.local_addr()?;
+
+        let (instance, witness) = SchnorrDiscreteLogInstance::generate(2 << 8, 2 << 5);
+        let (prover_instance, relay_instance) = (instance.clone(), instance.clone());
+
+        let prover_handle = thread::spawn(move || {
+            let (stream, _) = prover_listener.accept().unwrap();
+            let mut channel = Channel::from(stream);
+            channel.secure(&prover_instance, Role::Responder).unwrap();
+            let mut protocol: RemoteVerifierProtocol<SchnorrDiscreteLogProtocol> =
+                RemoteVerifierProtocol::new((prover_instance, channel), Some(witness));
+
+            protocol.run_protocol()
+        });
+
+        // The relay terminates both secure channels, and passes every message along unchanged.
+        let relay_handle = thread::spawn(move || {
+            let (stream, _) = relay_listener.accept().unwrap();
+            let mut verifier_side = Channel::from(stream);
+            verifier_side
+                .secure(&relay_instance, Role::Responder)
+                .unwrap();
+            let mut prover_side = Channel::from(TcpStream::connect(prover_addr).unwrap());
+            prover_side
+                .secure(&relay_instance, Role::Initiator)
+                .unwrap();
+
+            let a: BigInt = prover_side.receive(IntKind::Element).unwrap();
+            verifier_side.send(&a, IntKind::Element).unwrap();
+            let e: BigInt = verifier_side.receive(IntKind::Scalar).unwrap();
+            prover_side.send(&e, IntKind::Scalar).unwrap();
+            let z: BigInt = prover_side.receive(IntKind::Scalar).unwrap();
+            verifier_side.send(&z, IntKind::Scalar).unwrap();
+            let accepted: bool = verifier_side.receive(IntKind::Scalar).unwrap();
+            prover_side.send(&accepted, IntKind::Scalar).unwrap();
+        });
+
+        let verifier_handle = thread::spawn(move || {
+            let mut channel = Channel::from(TcpStream::connect(relay_addr).unwrap());
+            channel.secure(&instance, Role::Initiator).unwrap();
+            let mut protocol: RemoteProverProtocol<SchnorrDiscreteLogProtocol> =
+                RemoteProverProtocol::new((instance, channel), None);
+
+            protocol.run_protocol()
+        });
+
+        assert!(verifier_handle.join().unwrap().is_err());
+        assert!(prover_handle.join().unwrap().is_err());
+        relay_handle.join().unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn honest_run_works_in_binding_session() -> io::Result<()> {
+        perform_honest_run_in_threads(2 << 8, 2 << 5, Codec::binary_for, session_setup)
+    }
+
+    /// Schnorr's protocol, except that the verifier always picks the same challenge.
+    struct FixedChallengeProtocol(SchnorrDiscreteLogProtocol);
+
+    impl SigmaProtocol<SchnorrDiscreteLogInstance, BigInt, BigInt, BigInt, BigInt>
+        for FixedChallengeProtocol
+    {
+        type VerifierError = SchnorrVerifierError;
+
+        fn new(instance: SchnorrDiscreteLogInstance, witness: Option<BigInt>) -> Self {
+            FixedChallengeProtocol(SchnorrDiscreteLogProtocol::new(instance, witness))
+        }
+
+        fn initial_message(&mut self) -> BigInt {
+            self.0.initial_message()
+        }
+
+        fn challenge(&mut self) -> BigInt {
+            BigInt::from(1) << 40
+        }
+
+        fn challenge_response(&mut self, challenge: &BigInt) -> BigInt {
+            self.0.challenge_response(challenge)
+        }
+
+        fn check(
+            &mut self,
+            initial_msg: BigInt,
+            challenge: BigInt,
+            response: BigInt,
+        ) -> Result<(), Self::VerifierError> {
+            self.0.check(initial_msg, challenge, response)
+        }
+
+        fn simulate(&mut self, challenge: &BigInt) -> (BigInt, BigInt) {
+            self.0.simulate(challenge)
+        }
+    }
+
+    fn channel_pair() -> io::Result<(Channel, Channel)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let initiator = TcpStream::connect(listener.local_addr()?)?;
+        let (responder, _) = listener.accept()?;
+        Ok((initiator.into(), responder.into()))
+    }
+
+    /// Record an honest run, and replay the prover's messages to the verifier in a new session.
+    fn replay_is_accepted(binds_challenges: bool) -> io::Result<bool> {
+        let (instance, witness) = SchnorrDiscreteLogInstance::generate(2 << 8, 2 << 5);
+
+        let (mut verifier_channel, mut prover_channel) = channel_pair()?;
+        let verifier_instance = instance.clone();
+        let verifier_handle = thread::spawn(move || {
+            verifier_channel
+                .start_session(Role::Initiator, binds_challenges)
+                .unwrap();
+            let mut protocol: RemoteProverProtocol<FixedChallengeProtocol> =
+                RemoteProverProtocol::new((verifier_instance, verifier_channel), None);
+            protocol.run_protocol().unwrap();
+        });
+
+        prover_channel
+            .start_session(Role::Responder, binds_challenges)
+            .unwrap();
+        let mut prover: RemoteVerifierProtocol<FixedChallengeProtocol> =
+            RemoteVerifierProtocol::new((instance.clone(), prover_channel), Some(witness));
+        let a = prover.initial_message();
+        let e = prover.challenge();
+        let z = prover.challenge_response(&e);
+        assert!(prover.check(a.clone(), e, z.clone()).is_ok());
+        verifier_handle.join().unwrap();
+
+        let (mut verifier_channel, mut replay_channel) = channel_pair()?;
+        let verifier_handle = thread::spawn(move || {
+            verifier_channel
+                .start_session(Role::Initiator, binds_challenges)
+                .unwrap();
+            let mut protocol: RemoteProverProtocol<FixedChallengeProtocol> =
+                RemoteProverProtocol::new((instance, verifier_channel), None);
+            protocol.run_protocol().is_ok()
+        });
+
+        replay_channel
+            .start_session(Role::Responder, binds_challenges)
+            .unwrap();
+        replay_channel.send(&a, IntKind::Element).unwrap();
+        let _: BigInt = replay_channel.receive(IntKind::Scalar).unwrap();
+        replay_channel.send(&z, IntKind::Scalar).unwrap();
+
+        Ok(verifier_handle.join().unwrap())
+    }
+
+    #[test]
+    fn replay_is_accepted_without_binding() -> io::Result<()> {
+        assert!(replay_is_accepted(false)?);
+        Ok(())
+    }
+
+    #[test]
+    fn replay_is_rejected_with_binding() -> io::Result<()> {
+        assert!(!replay_is_accepted(true)?);
+        Ok(())
     }
 }
@@ -0,0 +1,245 @@
+//! Parallel repetition of a sigma protocol, to make its soundness error smaller.
+//!
+//! A protocol whose challenges have `t` bits lets a cheating prover succeed with probability
+//! `2^-t`. Running `k` independent copies in parallel, and accepting only if every copy is
+//! accepted, brings this down to `2^-(k * t)`.
+
+use crate::{Challenge, SigmaProtocol};
+
+/// The number of repetitions needed to get the soundness error below `2^-security_bits`, for a
+/// protocol with `challenge_bits` bits of challenge.
+///
+/// This is a `const fn`, so it can be used to pick the `N` of [`Repeated`].
+pub const fn repetitions_for(challenge_bits: usize, security_bits: usize) -> usize {
+    assert!(
+        challenge_bits > 0,
+        "The challenge must have at least one bit."
+    );
+    security_bits.div_ceil(challenge_bits)
+}
+
+/// `N` copies of the protocol `P`, all run in parallel on the same instance.
+pub struct Repeated<P, const N: usize> {
+    protocols: Vec<P>,
+}
+
+#[derive(Debug)]
+pub enum RepeatedVerifierError<VError> {
+    /// A message does not have one part for each copy.
+    WrongLength { expected: usize, actual: usize },
+    /// The copy at `index` was rejected.
+    SubProtocolError { index: usize, error: VError },
+}
+
+impl<P, X, W, A, E, Z, const N: usize> SigmaProtocol<X, W, Vec<A>, Vec<E>, Vec<Z>>
+    for Repeated<P, N>
+where
+    P: SigmaProtocol<X, W, A, E, Z>,
+    X: Clone,
+    W: Clone,
+{
+    type VerifierError = RepeatedVerifierError<P::VerifierError>;
+
+    fn new(instance: X, witness: Option<W>) -> Self {
+        let protocols = (0..N)
+            .map(|_| P::new(instance.clone(), witness.clone()))
+            .collect();
+        Repeated { protocols }
+    }
+
+    fn initial_message(&mut self) -> Vec<A> {
+        self.protocols.iter_mut().map(P::initial_message).collect()
+    }
+
+    fn challenge(&mut self) -> Vec<E> {
+        self.protocols.iter_mut().map(P::challenge).collect()
+    }
+
+    /// The responses of every copy, or none at all if the challenge, which comes from the peer,
+    /// does not have one part for each copy. [`Self::check`] then rejects the empty response.
+    fn challenge_response(&mut self, challenge: &Vec<E>) -> Vec<Z> {
+        if challenge.len() != N {
+            return Vec::new();
+        }
+        self.protocols
+            .iter_mut()
+            .zip(challenge)
+            .map(|(protocol, e)| protocol.challenge_response(e))
+            .collect()
+    }
+
+    fn check(
+        &mut self,
+        initial_msg: Vec<A>,
+        challenge: Vec<E>,
+        response: Vec<Z>,
+    ) -> Result<(), Self::VerifierError> {
+        for actual in [initial_msg.len(), challenge.len(), response.len()] {
+            if actual != N {
+                return Err(RepeatedVerifierError::WrongLength {
+                    expected: N,
+                    actual,
+                });
+            }
+        }
+
+        let messages = initial_msg.into_iter().zip(challenge).zip(response);
+        for (index, (protocol, ((a, e), z))) in self.protocols.iter_mut().zip(messages).enumerate()
+        {
+            protocol
+                .check(a, e, z)
+                .map_err(|error| RepeatedVerifierError::SubProtocolError { index, error })?;
+        }
+
+        Ok(())
+    }
+
+    /// A simulated transcript, which is empty like the response if the challenge does not have
+    /// one part for each copy.
+    fn simulate(&mut self, challenge: &Vec<E>) -> (Vec<A>, Vec<Z>) {
+        if challenge.len() != N {
+            return (Vec::new(), Vec::new());
+        }
+        self.protocols
+            .iter_mut()
+            .zip(challenge)
+            .map(|(protocol, e)| protocol.simulate(e))
+            .unzip()
+    }
+}
+
+impl<E: Challenge> Challenge for Vec<E> {
+    /// Bind every challenge to the context and its own position.
+    fn bind(&self, context: &[u8], bits: usize) -> Self {
+        self.iter()
+            .enumerate()
+            .map(|(index, e)| {
+                let mut indexed = context.to_vec();
+                indexed.extend_from_slice(&(index as u64).to_be_bytes());
+                e.bind(&indexed, bits)
+            })
+            .collect()
+    }
+
+    fn in_space(&self, bits: usize) -> bool {
+        self.iter().all(|e| e.in_space(bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol},
+        SigmaProtocol,
+    };
+
+    use super::{repetitions_for, Repeated, RepeatedVerifierError};
+
+    fn make_instance() -> (SchnorrDiscreteLogInstance, BigInt) {
+        let p = BigInt::from(1907);
+        let q = BigInt::from(953);
+        let g = BigInt::from(343);
+        let w = BigInt::from(121);
+        let h = g.modpow(&w, &p);
+        (SchnorrDiscreteLogInstance::new(p, q, g, h), w)
+    }
+
+    // The test group has 9 bit challenges, so this is the number needed for 128 bits.
+    const K: usize = repetitions_for(9, 128);
+    type RepeatedSchnorr = Repeated<SchnorrDiscreteLogProtocol, K>;
+
+    #[test]
+    fn repetitions_reach_target() {
+        assert_eq!(K, 15);
+        assert_eq!(repetitions_for(1, 128), 128);
+        assert_eq!(repetitions_for(255, 128), 1);
+        assert_eq!(repetitions_for(64, 128), 2);
+
+        let (instance, _) = make_instance();
+        assert_eq!(instance.challenge_bits(), 9);
+    }
+
+    #[test]
+    fn honest_run_is_accepted() {
+        let (instance, w) = make_instance();
+        let mut protocol = RepeatedSchnorr::new(instance, Some(w));
+        let res = protocol.run_protocol();
+        assert!(res.is_ok(), "Honest run not accepted: {:?}", res)
+    }
+
+    #[test]
+    fn simulator_is_accepted() {
+        let (instance, _) = make_instance();
+        let mut protocol = RepeatedSchnorr::new(instance, None);
+
+        let e = protocol.challenge();
+        let (a, z) = protocol.simulate(&e);
+
+        let res = protocol.check(a, e, z);
+        assert!(res.is_ok(), "Simulator not accepted: {:?}", res)
+    }
+
+    #[test]
+    fn single_bad_copy_is_rejected() {
+        let (instance, w) = make_instance();
+        let mut protocol = RepeatedSchnorr::new(instance, Some(w));
+
+        let a = protocol.initial_message();
+        let e = protocol.challenge();
+        let mut z = protocol.challenge_response(&e);
+        z[3] += 1;
+
+        let res = protocol.check(a, e, z);
+        assert!(
+            matches!(
+                res,
+                Err(RepeatedVerifierError::SubProtocolError { index: 3, .. })
+            ),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn wrong_number_of_copies_is_rejected() {
+        let (instance, w) = make_instance();
+        let mut protocol = RepeatedSchnorr::new(instance, Some(w));
+
+        let mut a = protocol.initial_message();
+        let e = protocol.challenge();
+        let z = protocol.challenge_response(&e);
+        a.pop();
+
+        let res = protocol.check(a, e, z);
+        assert!(matches!(
+            res,
+            Err(RepeatedVerifierError::WrongLength {
+                expected: K,
+                actual: 14
+            })
+        ));
+    }
+
+    #[test]
+    fn challenge_of_wrong_length_is_rejected() {
+        let (instance, w) = make_instance();
+        let mut protocol = RepeatedSchnorr::new(instance, Some(w));
+
+        let a = protocol.initial_message();
+        let mut e = protocol.challenge();
+        e.push(BigInt::from(1));
+        let z = protocol.challenge_response(&e);
+        assert!(z.is_empty());
+
+        let res = protocol.check(a, e, z);
+        assert!(matches!(
+            res,
+            Err(RepeatedVerifierError::WrongLength {
+                expected: K,
+                actual: 16
+            })
+        ));
+    }
+}
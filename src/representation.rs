@@ -0,0 +1,322 @@
+//! Proofs of knowledge of a representation, for conjunctions of linear discrete-log relations.
+//!
+//! The prover knows exponents `w_0, ..., w_(n-1)` such that each of several equations
+//! `y = b_1^w_(i_1) b_2^w_(i_2) ...` holds. An exponent may appear in any number of equations,
+//! and the proof then also shows that it is the same in all of them. This is the AND of the
+//! equations, with one challenge for all of them and one response per exponent, and contains
+//! the Schnorr, Chaum-Pedersen and Okamoto protocols as special cases.
+
+use num::{bigint::RandBigInt, BigInt, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    group::{PrimeOrderGroup, SchnorrGroup},
+    secret::Secret,
+    ChallengeSpace, SigmaProtocol,
+};
+
+/// The equation `target = product of bases[j]^w[index_j]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equation {
+    target: BigInt,
+    terms: Vec<(BigInt, usize)>,
+}
+
+impl Equation {
+    /// The equation with the left-hand side `target` and the terms `(base, index)` on the
+    /// right-hand side, where `index` is the position of the exponent in the witness.
+    pub fn new(target: BigInt, terms: Vec<(BigInt, usize)>) -> Self {
+        Equation { target, terms }
+    }
+
+    pub fn target(&self) -> &BigInt {
+        &self.target
+    }
+
+    pub fn terms(&self) -> &[(BigInt, usize)] {
+        &self.terms
+    }
+
+    /// The right-hand side for the exponents `w`.
+    fn evaluate(&self, group: &SchnorrGroup, w: &[BigInt]) -> BigInt {
+        let (bases, exponents) = self.bases_and_exponents(w);
+        group.multi_exp(&bases, &exponents)
+    }
+
+    /// The right-hand side for secret exponents `w`, in time that does not depend on them.
+    fn evaluate_secret(&self, group: &SchnorrGroup, w: &[BigInt]) -> BigInt {
+        let (bases, exponents) = self.bases_and_exponents(w);
+        group.multi_exp_secret(&bases, Secret::new(exponents).expose())
+    }
+
+    fn bases_and_exponents(&self, w: &[BigInt]) -> (Vec<BigInt>, Vec<BigInt>) {
+        self.terms
+            .iter()
+            .map(|(base, index)| (base.clone(), w[*index].clone()))
+            .unzip()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RepresentationInstance {
+    group: SchnorrGroup,
+    witness_len: usize,
+    equations: Vec<Equation>,
+}
+
+impl RepresentationInstance {
+    /// The conjunction of `equations`, over a witness of `witness_len` exponents.
+    ///
+    /// # Panics
+    ///
+    /// If an equation refers to an exponent outside the witness.
+    pub fn new(group: SchnorrGroup, witness_len: usize, equations: Vec<Equation>) -> Self {
+        assert!(
+            equations
+                .iter()
+                .flat_map(|equation| &equation.terms)
+                .all(|(_, index)| *index < witness_len),
+            "An equation refers to an exponent outside the witness."
+        );
+        RepresentationInstance {
+            group,
+            witness_len,
+            equations,
+        }
+    }
+
+    pub fn group(&self) -> &SchnorrGroup {
+        &self.group
+    }
+
+    pub fn witness_len(&self) -> usize {
+        self.witness_len
+    }
+
+    pub fn equations(&self) -> &[Equation] {
+        &self.equations
+    }
+
+    /// The number of bits in the challenges of [`RepresentationProtocol`].
+    pub fn challenge_bits(&self) -> usize {
+        self.group.order().bits() - 1
+    }
+
+    /// Whether `w` satisfies all equations.
+    pub fn is_satisfied_by(&self, w: &[BigInt]) -> bool {
+        w.len() == self.witness_len
+            && self
+                .equations
+                .iter()
+                .all(|equation| equation.evaluate(&self.group, w) == equation.target)
+    }
+}
+
+impl ChallengeSpace for RepresentationInstance {
+    fn challenge_bits(&self) -> usize {
+        Self::challenge_bits(self)
+    }
+}
+
+pub struct RepresentationProtocol {
+    instance: RepresentationInstance,
+    witness: Option<Secret<Vec<BigInt>>>,
+    random_exponents: Option<Secret<Vec<BigInt>>>,
+}
+
+#[derive(Debug)]
+pub enum RepresentationVerifierError {
+    /// A value in the instance or the initial message is not an element of the group.
+    NotInGroup,
+    /// The initial message does not have one element per equation, or the response does not
+    /// have one scalar per exponent.
+    WrongLength { expected: usize, actual: usize },
+    /// The equation at `index` does not hold for the response.
+    ExpressionsNotEqual {
+        index: usize,
+        lhs: BigInt,
+        rhs: BigInt,
+    },
+}
+
+impl SigmaProtocol<RepresentationInstance, Vec<BigInt>, Vec<BigInt>, BigInt, Vec<BigInt>>
+    for RepresentationProtocol
+{
+    type VerifierError = RepresentationVerifierError;
+
+    fn new(instance: RepresentationInstance, witness: Option<Vec<BigInt>>) -> Self {
+        RepresentationProtocol {
+            instance,
+            witness: witness.map(Secret::new),
+            random_exponents: None,
+        }
+    }
+
+    fn initial_message(&mut self) -> Vec<BigInt> {
+        let group = &self.instance.group;
+        let r = Secret::new(
+            (0..self.instance.witness_len)
+                .map(|_| group.random_scalar())
+                .collect::<Vec<_>>(),
+        );
+        let a = self
+            .instance
+            .equations
+            .iter()
+            .map(|equation| equation.evaluate_secret(group, r.expose()))
+            .collect();
+        self.random_exponents = Some(r);
+        a
+    }
+
+    fn challenge(&mut self) -> BigInt {
+        let ubound = BigInt::from(1) << self.instance.challenge_bits();
+        rand::thread_rng().gen_bigint_range(&BigInt::zero(), &ubound)
+    }
+
+    fn challenge_response(&mut self, challenge: &BigInt) -> Vec<BigInt> {
+        let r = self
+            .random_exponents
+            .as_ref()
+            .expect("Random exponents 'r' are not yet defined.");
+        let w = self
+            .witness
+            .as_ref()
+            .expect("Witness 'w' is not yet defined.");
+        assert_eq!(w.expose().len(), self.instance.witness_len);
+
+        let group = &self.instance.group;
+        r.expose()
+            .iter()
+            .zip(w.expose())
+            .map(|(r, w)| group.response(r, challenge, w))
+            .collect()
+    }
+
+    fn check(
+        &mut self,
+        initial_msg: Vec<BigInt>,
+        challenge: BigInt,
+        response: Vec<BigInt>,
+    ) -> Result<(), Self::VerifierError> {
+        let RepresentationInstance {
+            group,
+            witness_len,
+            equations,
+        } = &self.instance;
+        for (expected, actual) in [
+            (equations.len(), initial_msg.len()),
+            (*witness_len, response.len()),
+        ] {
+            if expected != actual {
+                return Err(RepresentationVerifierError::WrongLength { expected, actual });
+            }
+        }
+        if !equations
+            .iter()
+            .flat_map(|equation| {
+                std::iter::once(&equation.target).chain(equation.terms.iter().map(|(b, _)| b))
+            })
+            .chain(&initial_msg)
+            .all(|x| group.is_element(x))
+        {
+            return Err(RepresentationVerifierError::NotInGroup);
+        }
+
+        for (index, (equation, a)) in equations.iter().zip(&initial_msg).enumerate() {
+            let lhs = equation.evaluate(group, &response);
+            let rhs = group.mul(a, &group.exp(&equation.target, &challenge));
+            if lhs != rhs {
+                return Err(RepresentationVerifierError::ExpressionsNotEqual { index, lhs, rhs });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn simulate(&mut self, challenge: &BigInt) -> (Vec<BigInt>, Vec<BigInt>) {
+        let group = &self.instance.group;
+        let z = (0..self.instance.witness_len)
+            .map(|_| group.random_scalar())
+            .collect::<Vec<_>>();
+        let a = self
+            .instance
+            .equations
+            .iter()
+            .map(|equation| {
+                group.mul(
+                    &equation.evaluate(group, &z),
+                    &group.exp(&equation.target, &-challenge),
+                )
+            })
+            .collect();
+        (a, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        group::{PrimeOrderGroup, SchnorrGroup},
+        SigmaProtocol,
+    };
+
+    use super::{
+        Equation, RepresentationInstance, RepresentationProtocol, RepresentationVerifierError,
+    };
+
+    /// The instance `A = g^x` and `B = g^r h^x`, with `B` off by `b_offset` in the exponent of
+    /// `g`.
+    fn make_instance(b_offset: i32) -> (RepresentationInstance, Vec<BigInt>) {
+        let group =
+            SchnorrGroup::new(BigInt::from(1907), BigInt::from(953), BigInt::from(343)).unwrap();
+        let g = group.generator();
+        let h = group.exp(&g, &BigInt::from(29));
+        let w = vec![BigInt::from(121), BigInt::from(400)];
+        let a = group.exp(&g, &w[0]);
+        let b = group.multi_exp(&[g.clone(), h.clone()], &[&w[1] + b_offset, w[0].clone()]);
+        let equations = vec![
+            Equation::new(a, vec![(g.clone(), 0)]),
+            Equation::new(b, vec![(g, 1), (h, 0)]),
+        ];
+        (RepresentationInstance::new(group, 2, equations), w)
+    }
+
+    #[test]
+    fn honest_run_and_simulator_are_accepted() {
+        let (instance, w) = make_instance(0);
+        assert!(instance.is_satisfied_by(&w));
+        let res = RepresentationProtocol::new(instance.clone(), Some(w)).run_protocol();
+        assert!(res.is_ok(), "Honest run not accepted: {:?}", res);
+
+        let mut protocol = RepresentationProtocol::new(instance, None);
+        let e = protocol.challenge();
+        let (a, z) = protocol.simulate(&e);
+        let res = protocol.check(a, e, z);
+        assert!(res.is_ok(), "Simulator not accepted: {:?}", res);
+    }
+
+    #[test]
+    fn false_statement_is_rejected() {
+        let (instance, w) = make_instance(1);
+        assert!(!instance.is_satisfied_by(&w));
+        let mut protocol = RepresentationProtocol::new(instance, Some(w));
+        let a = protocol.initial_message();
+        // A non-zero challenge, so that the error in the second equation shows.
+        let e = BigInt::from(5);
+        let z = protocol.challenge_response(&e);
+        assert!(matches!(
+            protocol.check(a.clone(), e.clone(), z.clone()),
+            Err(RepresentationVerifierError::ExpressionsNotEqual { index: 1, .. })
+        ));
+        assert!(matches!(
+            protocol.check(a, e, z[..1].to_vec()),
+            Err(RepresentationVerifierError::WrongLength {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
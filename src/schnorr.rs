@@ -1,6 +1,17 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    io,
+    sync::{Arc, OnceLock},
+};
 
-use crate::SigmaProtocol;
+use crate::{
+    group::{check_moduli, GroupError, PrimeOrderGroup, SchnorrGroup},
+    montgomery::{Modulus, Residue},
+    multiexp::FixedBaseTable,
+    netutil::BinaryEncode,
+    secret::Secret,
+    ChallengeSpace, SigmaProtocol,
+};
 
 use num::{
     bigint::{BigInt, RandBigInt},
@@ -12,16 +23,67 @@ use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "InstanceParams")]
 pub struct SchnorrDiscreteLogInstance {
     p: BigInt,
     q: BigInt,
     g: BigInt,
     h: BigInt,
+    /// Values derived from the others, which clones share.
+    #[serde(skip)]
+    cache: Arc<Cache>,
+}
+
+#[derive(Default)]
+struct Cache {
+    group: OnceLock<SchnorrGroup>,
+    /// Set once [`SchnorrDiscreteLogInstance::precompute`] is called.
+    tables: OnceLock<Tables>,
 }
 
+/// The fixed-base tables of `g` and `h`, in Montgomery form.
+struct Tables {
+    g: FixedBaseTable<Residue>,
+    h: FixedBaseTable<Residue>,
+}
+
+/// The serialised form of a [`SchnorrDiscreteLogInstance`], whose moduli are checked as it is
+/// deserialised, so that the group operations on it cannot panic.
+#[derive(Deserialize)]
+struct InstanceParams {
+    p: BigInt,
+    q: BigInt,
+    g: BigInt,
+    h: BigInt,
+}
+
+impl TryFrom<InstanceParams> for SchnorrDiscreteLogInstance {
+    type Error = GroupError;
+
+    fn try_from(params: InstanceParams) -> Result<Self, GroupError> {
+        check_moduli(&params.p, &params.q)?;
+        Ok(Self::new(params.p, params.q, params.g, params.h))
+    }
+}
+
+/// Instances are equal when their numbers are, whatever has been cached for them.
+impl PartialEq for SchnorrDiscreteLogInstance {
+    fn eq(&self, other: &Self) -> bool {
+        self.p == other.p && self.q == other.q && self.g == other.g && self.h == other.h
+    }
+}
+
+impl Eq for SchnorrDiscreteLogInstance {}
+
 impl SchnorrDiscreteLogInstance {
     pub fn new(p: BigInt, q: BigInt, g: BigInt, h: BigInt) -> Self {
-        SchnorrDiscreteLogInstance { p, q, g, h }
+        SchnorrDiscreteLogInstance {
+            p,
+            q,
+            g,
+            h,
+            cache: Arc::default(),
+        }
     }
 
     /// Generate a Schnorr protocol instance and a corresponding witness.
@@ -58,9 +120,140 @@ impl SchnorrDiscreteLogInstance {
         (Self::new(p.into(), q.into(), g.into(), h.into()), w.into())
     }
 
+    pub fn p(&self) -> &BigInt {
+        &self.p
+    }
+
+    pub fn q(&self) -> &BigInt {
+        &self.q
+    }
+
+    pub fn g(&self) -> &BigInt {
+        &self.g
+    }
+
+    pub fn h(&self) -> &BigInt {
+        &self.h
+    }
+
+    /// The number of bits in the challenges of [`SchnorrDiscreteLogProtocol`].
+    ///
+    /// A cheating prover convinces the verifier with probability `2^-challenge_bits`.
+    pub fn challenge_bits(&self) -> usize {
+        self.q.bits() - 1
+    }
+
+    /// Build fixed-base tables for `g` and `h`, which make [`Self::pow_g`] and [`Self::pow_h`]
+    /// several times faster, for an instance that is used for many proofs.
+    ///
+    /// The tables reduce exponents modulo `q`, so nothing is built unless `g` and `h` are in the
+    /// subgroup of order `q`.
+    pub fn precompute(&self) {
+        if self.cache.tables.get().is_some()
+            || !self.is_element(&self.g)
+            || !self.is_element(&self.h)
+        {
+            return;
+        }
+        let arithmetic = self.arithmetic();
+        let bits = self.q.bits();
+        self.cache.tables.get_or_init(|| Tables {
+            g: FixedBaseTable::new(arithmetic, &arithmetic.residue(&self.g), bits),
+            h: FixedBaseTable::new(arithmetic, &arithmetic.residue(&self.h), bits),
+        });
+    }
+
+    /// `g^e`, with `e` reduced modulo `q`.
+    pub fn pow_g(&self, e: &BigInt) -> BigInt {
+        self.pow(&self.g, e, |tables| &tables.g)
+    }
+
+    /// `h^e`, with `e` reduced modulo `q`.
+    pub fn pow_h(&self, e: &BigInt) -> BigInt {
+        self.pow(&self.h, e, |tables| &tables.h)
+    }
+
+    fn pow(
+        &self,
+        base: &BigInt,
+        e: &BigInt,
+        table: impl Fn(&Tables) -> &FixedBaseTable<Residue>,
+    ) -> BigInt {
+        let arithmetic = self.arithmetic();
+        let e = e.mod_floor(&self.q);
+        match self.cache.tables.get() {
+            Some(tables) => arithmetic.integer(&table(tables).exp(arithmetic, &e)),
+            None => arithmetic.pow_integer(base, &e),
+        }
+    }
+
+    /// `g^e` for a secret `e`, such as a nonce, in time that does not depend on `e` modulo `q`.
+    pub fn pow_g_secret(&self, e: &BigInt) -> BigInt {
+        self.pow_secret(&self.g, e, |tables| &tables.g)
+    }
+
+    /// `h^e` for a secret `e`, in time that does not depend on `e` modulo `q`.
+    pub fn pow_h_secret(&self, e: &BigInt) -> BigInt {
+        self.pow_secret(&self.h, e, |tables| &tables.h)
+    }
+
+    fn pow_secret(
+        &self,
+        base: &BigInt,
+        e: &BigInt,
+        table: impl Fn(&Tables) -> &FixedBaseTable<Residue>,
+    ) -> BigInt {
+        let arithmetic = self.arithmetic();
+        let e = e.mod_floor(&self.q);
+        let power = match self.cache.tables.get() {
+            Some(tables) => table(tables).exp_secret(arithmetic, &e),
+            None => arithmetic.pow_secret(&arithmetic.residue(base), &e, self.q.bits()),
+        };
+        arithmetic.integer(&power)
+    }
+
+    /// The group this instance lives in, which shares its arithmetic with the instance.
+    ///
+    /// # Panics
+    ///
+    /// If `p` or `q` is even or at most one. Instances that were deserialised or passed
+    /// [`Self::is_valid`] never are.
+    pub fn group(&self) -> SchnorrGroup {
+        self.valid_group().clone()
+    }
+
+    /// The group this instance lives in, or why there is none.
+    pub fn try_group(&self) -> Result<SchnorrGroup, GroupError> {
+        self.cached_group().cloned()
+    }
+
+    fn cached_group(&self) -> Result<&SchnorrGroup, GroupError> {
+        if let Some(group) = self.cache.group.get() {
+            return Ok(group);
+        }
+        let group = SchnorrGroup::new(self.p.clone(), self.q.clone(), self.g.clone())?;
+        Ok(self.cache.group.get_or_init(|| group))
+    }
+
+    fn valid_group(&self) -> &SchnorrGroup {
+        self.cached_group()
+            .expect("The moduli of the instance are invalid.")
+    }
+
+    fn arithmetic(&self) -> &Modulus {
+        self.valid_group().arithmetic()
+    }
+
+    /// Whether `x` is an element of the subgroup of order `q`. Nothing is, if the moduli are
+    /// invalid.
+    pub fn is_element(&self, x: &BigInt) -> bool {
+        self.cached_group().is_ok_and(|group| group.is_element(x))
+    }
+
     /// Check whether this instance is valid.
     pub fn is_valid(&self) -> bool {
-        Verification::is_prime(&self.p.to_biguint().unwrap())
+        self.cached_group().is_ok()
+            && Verification::is_prime(&self.p.to_biguint().unwrap())
             && Verification::is_prime(&self.q.to_biguint().unwrap())
             && (&self.p - BigInt::from(1)).is_multiple_of(&self.q)
             && self.g < self.p
@@ -68,6 +261,27 @@ impl SchnorrDiscreteLogInstance {
     }
 }
 
+impl ChallengeSpace for SchnorrDiscreteLogInstance {
+    fn challenge_bits(&self) -> usize {
+        Self::challenge_bits(self)
+    }
+}
+
+impl BinaryEncode for SchnorrDiscreteLogInstance {
+    fn encode(&self, int_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        self.p.encode(int_len, out)?;
+        self.q.encode(int_len, out)?;
+        self.g.encode(int_len, out)?;
+        self.h.encode(int_len, out)
+    }
+
+    fn decode(int_len: usize, input: &mut &[u8]) -> io::Result<Self> {
+        let (p, q, g, h) = BinaryEncode::decode(int_len, input)?;
+        check_moduli(&p, &q).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::new(p, q, g, h))
+    }
+}
+
 impl Debug for SchnorrDiscreteLogInstance {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -79,13 +293,18 @@ impl Debug for SchnorrDiscreteLogInstance {
 
 pub struct SchnorrDiscreteLogProtocol {
     instance: SchnorrDiscreteLogInstance,
-    witness: Option<BigInt>,
-    random_exponent: Option<BigInt>,
+    witness: Option<Secret<BigInt>>,
+    random_exponent: Option<Secret<BigInt>>,
 }
 
 #[derive(Debug)]
 pub enum SchnorrVerifierError {
-    ExpressionsNotEqual { lhs: BigInt, rhs: BigInt },
+    /// The key or the initial message is not an element of the group.
+    NotInGroup,
+    ExpressionsNotEqual {
+        lhs: BigInt,
+        rhs: BigInt,
+    },
 }
 
 impl SigmaProtocol<SchnorrDiscreteLogInstance, BigInt, BigInt, BigInt, BigInt>
@@ -96,23 +315,23 @@ impl SigmaProtocol<SchnorrDiscreteLogInstance, BigInt, BigInt, BigInt, BigInt>
     fn new(instance: SchnorrDiscreteLogInstance, witness: Option<BigInt>) -> Self {
         SchnorrDiscreteLogProtocol {
             instance,
-            witness,
+            witness: witness.map(Secret::new),
             random_exponent: None,
         }
     }
 
     fn initial_message(&mut self) -> BigInt {
         let mut rng = rand::thread_rng();
-        let r = rng.gen_bigint_range(&BigInt::from(0_i32), &self.instance.p);
+        let r = Secret::new(rng.gen_bigint_range(&BigInt::zero(), &self.instance.q));
 
-        let a = self.instance.g.modpow(&r, &self.instance.p);
+        let a = self.instance.pow_g_secret(r.expose());
 
         self.random_exponent = Some(r);
         a
     }
 
     fn challenge(&mut self) -> BigInt {
-        let t = BigInt::from(self.instance.q.bits() - 1);
+        let t = BigInt::from(self.instance.challenge_bits());
         // TODO: Maybe check that this is valid
         let ubound = BigInt::from(2).modpow(&t, &self.instance.q);
 
@@ -130,7 +349,9 @@ impl SigmaProtocol<SchnorrDiscreteLogInstance, BigInt, BigInt, BigInt, BigInt>
             .as_ref()
             .expect("Witness 'w' is not yet defined.");
 
-        (r + challenge * w).modpow(&BigInt::from(1), &self.instance.q)
+        self.instance
+            .valid_group()
+            .response(r.expose(), challenge, w.expose())
     }
 
     fn check(
@@ -139,9 +360,8 @@ impl SigmaProtocol<SchnorrDiscreteLogInstance, BigInt, BigInt, BigInt, BigInt>
         challenge: BigInt,
         response: BigInt,
     ) -> Result<(), Self::VerifierError> {
-        let lhs = self.instance.g.modpow(&response, &self.instance.p);
-        let rhs = (&initial_msg * self.instance.h.modpow(&challenge, &self.instance.p))
-            .mod_floor(&self.instance.p);
+        let lhs = self.instance.pow_g(&response);
+        let rhs = (&initial_msg * self.instance.pow_h(&challenge)).mod_floor(&self.instance.p);
 
         if lhs == rhs {
             Ok(())
@@ -153,15 +373,17 @@ impl SigmaProtocol<SchnorrDiscreteLogInstance, BigInt, BigInt, BigInt, BigInt>
     fn simulate(&mut self, challenge: &BigInt) -> (BigInt, BigInt) {
         let p = &self.instance.p;
 
+        // Honest responses are reduced modulo `q`, so simulated ones are drawn from the same
+        // range, which also keeps them within the scalar width of the binary codec when they
+        // are sent as commitment openings.
         let mut rng = rand::thread_rng();
-        let z = rng.gen_bigint_range(&BigInt::zero(), p);
+        let z = rng.gen_bigint_range(&BigInt::zero(), &self.instance.q);
 
         // Calculate h^{-e} as (h^{-1})^{e}
         let h_inv = self.instance.h.extended_gcd(p).x;
         let h_pow_neg_e = h_inv.modpow(challenge, p);
 
-        let g = &self.instance.g;
-        let a = (g.modpow(&z, p) * h_pow_neg_e).mod_floor(&self.instance.p);
+        let a = (self.instance.pow_g(&z) * h_pow_neg_e).mod_floor(&self.instance.p);
 
         (a, z)
     }
@@ -169,7 +391,7 @@ impl SigmaProtocol<SchnorrDiscreteLogInstance, BigInt, BigInt, BigInt, BigInt>
 
 #[cfg(test)]
 mod tests {
-    use crate::SigmaProtocol;
+    use crate::{netutil::BinaryEncode, SigmaProtocol};
 
     use super::{BigInt, SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol};
 
@@ -181,7 +403,7 @@ mod tests {
         let w = BigInt::from(121);
 
         let h = g.modpow(&w, &p);
-        let instance = SchnorrDiscreteLogInstance { p, q, g, h };
+        let instance = SchnorrDiscreteLogInstance::new(p, q, g, h);
 
         let mut protocol = SchnorrDiscreteLogProtocol::new(instance, Some(w));
         assert!(protocol.run_protocol().is_ok())
@@ -193,7 +415,7 @@ mod tests {
         let q = BigInt::from(953);
         let g = BigInt::from(343);
         let h = BigInt::from(862);
-        let instance = SchnorrDiscreteLogInstance { p, q, g, h };
+        let instance = SchnorrDiscreteLogInstance::new(p, q, g, h);
 
         let e = BigInt::from(675);
         let mut protocol = SchnorrDiscreteLogProtocol::new(instance, None);
@@ -202,6 +424,38 @@ mod tests {
         assert!(protocol.check(a, e, z).is_ok())
     }
 
+    #[test]
+    fn precomputed_instance_is_accepted() {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let e = BigInt::from(675);
+        let expected = (instance.pow_g(&e), instance.pow_h(&e));
+        instance.precompute();
+        assert_eq!((instance.pow_g(&e), instance.pow_h(&e)), expected);
+
+        let mut protocol = SchnorrDiscreteLogProtocol::new(instance.clone(), Some(w));
+        assert!(protocol.run_protocol().is_ok());
+        let (a, z) = protocol.simulate(&e);
+        assert!(protocol.check(a.clone(), e.clone(), z.clone()).is_ok());
+        assert!(protocol.check(a, e, z + 1).is_err());
+    }
+
+    #[test]
+    fn negative_responses_are_rejected() {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        for precompute in [false, true] {
+            if precompute {
+                instance.precompute();
+            }
+            let mut protocol = SchnorrDiscreteLogProtocol::new(instance.clone(), Some(w.clone()));
+            let a = protocol.initial_message();
+            let e = BigInt::from(675);
+            let z = protocol.challenge_response(&e);
+            assert!(protocol.check(a.clone(), e.clone(), -&z).is_err());
+            assert_eq!(instance.pow_g(&-&z), instance.pow_g(&(instance.q() - &z)));
+            assert!(protocol.check(a, e, z).is_ok());
+        }
+    }
+
     #[test]
     fn generated_honest_run_is_accepted() {
         let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
@@ -219,4 +473,44 @@ mod tests {
 
         assert!(protocol.check(a, e, z).is_ok())
     }
+
+    #[test]
+    fn simulated_responses_are_reduced() {
+        let (instance, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let q = instance.q().clone();
+        let mut protocol = SchnorrDiscreteLogProtocol::new(instance, None);
+        for e in 0..20 {
+            let (_, z) = protocol.simulate(&BigInt::from(e));
+            assert!(z >= BigInt::from(0) && z < q);
+        }
+    }
+
+    #[test]
+    fn invalid_moduli_are_rejected_without_panicking() {
+        let instance = |p: i64, q: i64| {
+            SchnorrDiscreteLogInstance::new(
+                BigInt::from(p),
+                BigInt::from(q),
+                BigInt::from(343),
+                BigInt::from(343),
+            )
+        };
+        for instance in [
+            instance(1908, 953),
+            instance(1907, 954),
+            instance(-1907, 953),
+        ] {
+            assert!(!instance.is_element(&BigInt::from(343)));
+            assert!(!instance.is_valid());
+            assert!(instance.try_group().is_err());
+
+            let json = serde_json::to_string(&instance).unwrap();
+            assert!(serde_json::from_str::<SchnorrDiscreteLogInstance>(&json).is_err());
+            // Negative integers cannot even be encoded.
+            let mut bytes = vec![];
+            if instance.encode(8, &mut bytes).is_ok() {
+                assert!(SchnorrDiscreteLogInstance::decode(8, &mut &bytes[..]).is_err());
+            }
+        }
+    }
 }
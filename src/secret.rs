@@ -0,0 +1,139 @@
+//! Secret values that are wiped from memory when they are dropped.
+//!
+//! Witnesses, nonces, secret keys and commitment openings are kept in a [`Secret`], which
+//! overwrites its value with zeros when it goes out of scope. It has no `Debug` and no
+//! `Serialize`, so that a secret cannot end up in a log or a message by accident, and its value
+//! is only reached through [`Secret::expose`]. The `expose-secrets` feature adds both, for
+//! debugging.
+//!
+//! `num` does not give access to the buffer behind a `BigInt`, so wiping one overwrites the
+//! digits it currently uses. Spare capacity from earlier arithmetic on the same value, and the
+//! temporaries of arithmetic with it, are out of reach. Wrapping secrets limits how many copies
+//! of them are left behind, but cannot rule them out.
+
+use std::hint::black_box;
+
+use num::{bigint::Sign, BigInt};
+use zeroize::Zeroize;
+
+/// A value that can be overwritten in place.
+pub trait Wipe {
+    fn wipe(&mut self);
+}
+
+impl Wipe for BigInt {
+    fn wipe(&mut self) {
+        // Assigning as many zero digits as the value has writes over its digits without
+        // reallocating.
+        let digits = self.bits().div_ceil(32);
+        self.assign_from_slice(Sign::Plus, &vec![0; digits]);
+        // Keep the writes from being optimized away as dead stores.
+        black_box(&*self);
+    }
+}
+
+impl Wipe for usize {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Wipe for String {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<T: Wipe> Wipe for Vec<T> {
+    fn wipe(&mut self) {
+        self.iter_mut().for_each(Wipe::wipe);
+    }
+}
+
+impl<A: Wipe, B: Wipe> Wipe for (A, B) {
+    fn wipe(&mut self) {
+        self.0.wipe();
+        self.1.wipe();
+    }
+}
+
+/// A secret value, which is wiped when dropped.
+pub struct Secret<T: Wipe>(T);
+
+impl<T: Wipe> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn expose_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Wipe> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl<T: Wipe + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Secret(self.0.clone())
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+#[cfg(feature = "expose-secrets")]
+impl<T: Wipe + std::fmt::Debug> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Secret").field(&self.0).finish()
+    }
+}
+
+#[cfg(feature = "expose-secrets")]
+impl<T: Wipe + serde::Serialize> serde::Serialize for Secret<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "expose-secrets")]
+impl<'de, T: Wipe + serde::Deserialize<'de>> serde::Deserialize<'de> for Secret<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{BigInt, Zero};
+
+    use super::{Secret, Wipe};
+
+    #[test]
+    fn wiped_values_are_zero() {
+        let mut x: BigInt = (BigInt::from(-123456789) << 200) + 17;
+        x.wipe();
+        assert!(x.is_zero());
+
+        let mut values = (
+            vec![BigInt::from(5), BigInt::from(6)],
+            String::from("secret"),
+        );
+        values.wipe();
+        assert!(values.0.iter().all(Zero::is_zero));
+        assert!(values.1.is_empty());
+
+        let secret = Secret::new(BigInt::from(42));
+        assert_eq!(secret.clone().expose(), &BigInt::from(42));
+    }
+}
@@ -0,0 +1,171 @@
+//! Sessions, which tie a protocol run to a single connection.
+//!
+//! Both parties contribute randomness to the [`SessionId`], so neither of them can make a new
+//! session look like an old one. Each session keeps a [`Transcript`] of the instance and every
+//! message, which can be mixed into the challenge and the final accept message to stop a
+//! recorded run from being replayed in another session.
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{netutil::Role, transcript::Transcript};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SessionId([u8; 32]);
+
+impl SessionId {
+    /// Combine the random contributions of both parties.
+    pub fn from_contributions(initiator: &[u8; 32], responder: &[u8; 32]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"sigma session id: ");
+        hasher.update(initiator);
+        hasher.update(responder);
+        SessionId(hasher.finalize().into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Generate our random contribution to a session id.
+pub fn random_contribution() -> [u8; 32] {
+    let mut contribution = [0; 32];
+    rand::thread_rng().fill_bytes(&mut contribution);
+    contribution
+}
+
+pub struct Session {
+    id: SessionId,
+    role: Role,
+    transcript: Transcript,
+    binds_challenges: bool,
+}
+
+impl Session {
+    /// Start a session. If `binds_challenges` is set, the remote protocols include the session
+    /// in their challenges and accept messages, so both parties must agree on it.
+    pub fn new(id: SessionId, role: Role, binds_challenges: bool) -> Self {
+        let mut transcript = Transcript::new(b"sigma session");
+        transcript.append(b"session id", id.as_bytes());
+        Session {
+            id,
+            role,
+            transcript,
+            binds_challenges,
+        }
+    }
+
+    pub fn id(&self) -> &SessionId {
+        &self.id
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn binds_challenges(&self) -> bool {
+        self.binds_challenges
+    }
+
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    pub fn record_instance<X: Serialize>(&mut self, instance: &X) {
+        self.transcript.append_value(b"instance", instance);
+    }
+
+    /// Record a message, labelled by the party that sent it, so both parties agree on the
+    /// transcript.
+    pub fn record_message(&mut self, sender: Role, data: &[u8]) {
+        let label: &[u8] = match sender {
+            Role::Initiator => b"initiator message",
+            Role::Responder => b"responder message",
+        };
+        self.transcript.append(label, data);
+    }
+
+    pub fn record_sent(&mut self, data: &[u8]) {
+        self.record_message(self.role, data);
+    }
+
+    pub fn record_received(&mut self, data: &[u8]) {
+        let sender = match self.role {
+            Role::Initiator => Role::Responder,
+            Role::Responder => Role::Initiator,
+        };
+        self.record_message(sender, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{netutil::Role, Challenge};
+
+    use super::{random_contribution, Session, SessionId};
+
+    fn session_pair() -> (Session, Session) {
+        let id = SessionId::from_contributions(&random_contribution(), &random_contribution());
+        (
+            Session::new(id, Role::Initiator, true),
+            Session::new(id, Role::Responder, true),
+        )
+    }
+
+    #[test]
+    fn both_parties_agree_on_transcript() {
+        let (mut initiator, mut responder) = session_pair();
+        initiator.record_instance(&BigInt::from(42));
+        responder.record_instance(&BigInt::from(42));
+
+        initiator.record_sent(b"a");
+        responder.record_received(b"a");
+        responder.record_sent(b"e");
+        initiator.record_received(b"e");
+
+        assert_eq!(initiator.transcript().hash(), responder.transcript().hash());
+    }
+
+    #[test]
+    fn transcript_covers_instance_and_direction() {
+        let (mut initiator, mut responder) = session_pair();
+        initiator.record_instance(&BigInt::from(42));
+        responder.record_instance(&BigInt::from(43));
+        assert_ne!(initiator.transcript().hash(), responder.transcript().hash());
+
+        let (mut initiator, mut responder) = session_pair();
+        initiator.record_sent(b"a");
+        responder.record_sent(b"a");
+        assert_ne!(initiator.transcript().hash(), responder.transcript().hash());
+    }
+
+    #[test]
+    fn sessions_bind_challenges_differently() {
+        let (first, _) = session_pair();
+        let (second, _) = session_pair();
+        assert_ne!(first.id(), second.id());
+
+        let e = BigInt::from(1) << 100;
+        let first_e = e.bind(&first.transcript().hash(), 128);
+        let second_e = e.bind(&second.transcript().hash(), 128);
+        assert_ne!(first_e, second_e);
+    }
+
+    #[test]
+    fn short_challenges_are_bound_over_whole_space() {
+        let (first, _) = session_pair();
+        let (second, _) = session_pair();
+        let bound = BigInt::from(1) << 128;
+        for e in [0, 1, 2] {
+            let e = BigInt::from(e);
+            let first_e = e.bind(&first.transcript().hash(), 128);
+            let second_e = e.bind(&second.transcript().hash(), 128);
+            assert_ne!(first_e, second_e);
+            assert!(first_e < bound && first_e.bits() > 64);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! A declarative way to write proofs of linear discrete-log relations.
+//!
+//! A statement such as "I know `x` and `r` such that `A = g^x` and `B = g^r h^x`" is described
+//! by naming its secrets and listing its equations, with a [`StatementBuilder`] or with the
+//! [`statement!`](crate::statement!) macro:
+//!
+//! ```text
+//! let statement = statement! { group;
+//!     secrets x, r;
+//!     A = g^x;
+//!     B = g^r * h^x;
+//! };
+//! ```
+//!
+//! where `group`, `A`, `B`, `g` and `h` are variables in scope, and `x` and `r` are only names.
+//! The resulting [`Statement`] runs as a [`RepresentationProtocol`], and so gives the prover,
+//! the verifier and the simulator of the sigma protocol, and proves and verifies
+//! non-interactively with the Fiat-Shamir transform.
+
+use std::collections::HashMap;
+
+use num::BigInt;
+
+use crate::{
+    fiat_shamir::NonInteractiveProof,
+    group::SchnorrGroup,
+    representation::{
+        Equation, RepresentationInstance, RepresentationProtocol, RepresentationVerifierError,
+    },
+    SigmaProtocol,
+};
+
+/// A non-interactive proof of a [`Statement`].
+pub type StatementProof = NonInteractiveProof<Vec<BigInt>, Vec<BigInt>>;
+
+#[derive(Debug)]
+pub enum StatementError {
+    /// No value was given for the secret with this name.
+    MissingSecret(String),
+    /// A value was given for a secret that the statement does not have.
+    UnknownSecret(String),
+    /// The witness does not satisfy the equation with this name.
+    Unsatisfied(String),
+    ProofError(RepresentationVerifierError),
+}
+
+/// A secret of a statement, to use in its equations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Secret(usize);
+
+pub struct StatementBuilder {
+    group: SchnorrGroup,
+    secrets: Vec<String>,
+    equation_names: Vec<String>,
+    equations: Vec<Equation>,
+}
+
+impl StatementBuilder {
+    pub fn new(group: SchnorrGroup) -> Self {
+        StatementBuilder {
+            group,
+            secrets: Vec::new(),
+            equation_names: Vec::new(),
+            equations: Vec::new(),
+        }
+    }
+
+    /// The secret called `name`, which is added if it is not there yet.
+    pub fn secret(&mut self, name: &str) -> Secret {
+        match self.secrets.iter().position(|secret| secret == name) {
+            Some(index) => Secret(index),
+            None => {
+                self.secrets.push(name.to_owned());
+                Secret(self.secrets.len() - 1)
+            }
+        }
+    }
+
+    /// Add the equation `target = base_1^secret_1 base_2^secret_2 ...`, called `name`.
+    pub fn equation(
+        &mut self,
+        name: &str,
+        target: BigInt,
+        terms: Vec<(BigInt, Secret)>,
+    ) -> &mut Self {
+        let terms = terms
+            .into_iter()
+            .map(|(base, Secret(index))| (base, index))
+            .collect();
+        self.equation_names.push(name.to_owned());
+        self.equations.push(Equation::new(target, terms));
+        self
+    }
+
+    pub fn build(self) -> Statement {
+        Statement {
+            instance: RepresentationInstance::new(self.group, self.secrets.len(), self.equations),
+            secrets: self.secrets,
+            equation_names: self.equation_names,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Statement {
+    instance: RepresentationInstance,
+    secrets: Vec<String>,
+    equation_names: Vec<String>,
+}
+
+impl Statement {
+    pub fn instance(&self) -> &RepresentationInstance {
+        &self.instance
+    }
+
+    /// The names of the secrets, in the order of the witness.
+    pub fn secrets(&self) -> &[String] {
+        &self.secrets
+    }
+
+    /// The witness for the values of the secrets, given by name in any order.
+    pub fn witness(&self, values: &[(&str, BigInt)]) -> Result<Vec<BigInt>, StatementError> {
+        let mut by_name = HashMap::new();
+        for (name, value) in values {
+            if !self.secrets.iter().any(|secret| secret == name) {
+                return Err(StatementError::UnknownSecret(name.to_string()));
+            }
+            by_name.insert(*name, value.clone());
+        }
+        self.secrets
+            .iter()
+            .map(|secret| {
+                by_name
+                    .remove(secret.as_str())
+                    .ok_or_else(|| StatementError::MissingSecret(secret.clone()))
+            })
+            .collect()
+    }
+
+    /// Check that `witness` satisfies every equation.
+    ///
+    /// # Panics
+    ///
+    /// If the witness does not have one value per secret.
+    pub fn check_witness(&self, witness: &[BigInt]) -> Result<(), StatementError> {
+        assert_eq!(witness.len(), self.secrets.len());
+        for (name, equation) in self.equation_names.iter().zip(self.instance.equations()) {
+            let single = RepresentationInstance::new(
+                self.instance.group().clone(),
+                witness.len(),
+                vec![equation.clone()],
+            );
+            if !single.is_satisfied_by(witness) {
+                return Err(StatementError::Unsatisfied(name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The prover of the interactive protocol.
+    pub fn prover(&self, witness: Vec<BigInt>) -> RepresentationProtocol {
+        RepresentationProtocol::new(self.instance.clone(), Some(witness))
+    }
+
+    /// The verifier of the interactive protocol, which can also simulate transcripts.
+    pub fn verifier(&self) -> RepresentationProtocol {
+        RepresentationProtocol::new(self.instance.clone(), None)
+    }
+
+    /// A transcript with the challenge `challenge`, made without the witness.
+    pub fn simulate(&self, challenge: &BigInt) -> (Vec<BigInt>, Vec<BigInt>) {
+        self.verifier().simulate(challenge)
+    }
+
+    /// Prove the statement non-interactively, in `context`.
+    pub fn prove(
+        &self,
+        context: &[u8],
+        witness: Vec<BigInt>,
+    ) -> Result<StatementProof, StatementError> {
+        self.check_witness(&witness)?;
+        Ok(NonInteractiveProof::prove::<RepresentationProtocol, _, _>(
+            context,
+            self.instance.clone(),
+            witness,
+            self.instance.challenge_bits(),
+        ))
+    }
+
+    pub fn verify(&self, context: &[u8], proof: &StatementProof) -> Result<(), StatementError> {
+        proof
+            .verify::<RepresentationProtocol, _, _>(
+                context,
+                self.instance.clone(),
+                self.instance.challenge_bits(),
+            )
+            .map_err(StatementError::ProofError)
+    }
+}
+
+/// Build a [`Statement`](crate::statement::Statement) from its secrets and equations.
+///
+/// The group is an expression, and the targets and the bases are variables in scope, which are
+/// cloned. Each equation is written as `target = base^secret * base^secret ...;`, with every
+/// secret declared in `secrets` first.
+#[macro_export]
+macro_rules! statement {
+    (
+        $group:expr;
+        secrets $($secret:ident),+ $(,)?;
+        $(
+            $target:ident =
+            $first_base:ident ^ $first_exponent:ident $(* $base:ident ^ $exponent:ident)*;
+        )+
+    ) => {{
+        let group: $crate::group::SchnorrGroup = ::std::clone::Clone::clone(&$group);
+        let mut builder = $crate::statement::StatementBuilder::new(group);
+        $(let $secret = builder.secret(stringify!($secret));)+
+        $(
+            builder.equation(
+                stringify!($target),
+                ::std::clone::Clone::clone(&$target),
+                vec![
+                    (::std::clone::Clone::clone(&$first_base), $first_exponent),
+                    $((::std::clone::Clone::clone(&$base), $exponent)),*
+                ],
+            );
+        )+
+        builder.build()
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use crate::{
+        group::{PrimeOrderGroup, SchnorrGroup},
+        schnorr::SchnorrDiscreteLogInstance,
+        SigmaProtocol,
+    };
+
+    use super::{Statement, StatementBuilder, StatementError};
+
+    /// `A = g^x` and `B = g^r h^x`, with `B` off by `b_offset` in the exponent of `g`.
+    #[allow(non_snake_case)]
+    fn make_statement(group: &SchnorrGroup, b_offset: i32) -> (Statement, Vec<BigInt>) {
+        let g = group.generator();
+        let h = group.hash_to_element(b"h");
+        let (x, r) = (group.random_scalar(), group.random_scalar());
+        let A = group.exp(&g, &x);
+        let B = group.multi_exp(&[g.clone(), h.clone()], &[&r + b_offset, x.clone()]);
+
+        let statement = statement! { group;
+            secrets x, r;
+            A = g^x;
+            B = g^r * h^x;
+        };
+        let witness = statement.witness(&[("r", r), ("x", x)]).unwrap();
+        (statement, witness)
+    }
+
+    #[test]
+    fn macro_statement_is_proven() {
+        let group = SchnorrDiscreteLogInstance::generate(256, 64).0.group();
+        let (statement, witness) = make_statement(&group, 0);
+        assert_eq!(statement.secrets(), ["x", "r"]);
+
+        let res = statement.prover(witness.clone()).run_protocol();
+        assert!(res.is_ok(), "Honest run not accepted: {:?}", res);
+
+        let mut verifier = statement.verifier();
+        let e = verifier.challenge();
+        let (a, z) = statement.simulate(&e);
+        assert!(verifier.check(a, e, z).is_ok());
+
+        let proof = statement.prove(b"context", witness).unwrap();
+        assert!(statement.verify(b"context", &proof).is_ok());
+        assert!(matches!(
+            statement.verify(b"other context", &proof),
+            Err(StatementError::ProofError(_))
+        ));
+    }
+
+    #[test]
+    fn builder_matches_macro() {
+        let group = SchnorrDiscreteLogInstance::generate(256, 64).0.group();
+        let (statement, witness) = make_statement(&group, 0);
+        let equations = statement.instance().equations();
+
+        let mut builder = StatementBuilder::new(group);
+        let x = builder.secret("x");
+        let r = builder.secret("r");
+        assert_eq!(builder.secret("x"), x);
+        let (g, h) = (&equations[1].terms()[0].0, &equations[1].terms()[1].0);
+        builder
+            .equation("A", equations[0].target().clone(), vec![(g.clone(), x)])
+            .equation(
+                "B",
+                equations[1].target().clone(),
+                vec![(g.clone(), r), (h.clone(), x)],
+            );
+        let built = builder.build();
+        assert_eq!(built.instance(), statement.instance());
+
+        let proof = built.prove(b"context", witness).unwrap();
+        assert!(statement.verify(b"context", &proof).is_ok());
+    }
+
+    #[test]
+    fn wrong_witness_is_caught() {
+        let group = SchnorrDiscreteLogInstance::generate(256, 64).0.group();
+        let (statement, witness) = make_statement(&group, 1);
+        assert!(matches!(
+            statement.prove(b"context", witness),
+            Err(StatementError::Unsatisfied(name)) if name == "B"
+        ));
+        assert!(matches!(
+            statement.witness(&[("x", BigInt::from(1))]),
+            Err(StatementError::MissingSecret(name)) if name == "r"
+        ));
+        assert!(matches!(
+            statement.witness(&[("y", BigInt::from(1))]),
+            Err(StatementError::UnknownSecret(name)) if name == "y"
+        ));
+    }
+}
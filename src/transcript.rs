@@ -0,0 +1,100 @@
+//! A running hash over everything that happened in a protocol run.
+
+use num::{bigint::Sign, BigInt};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// A hash of a sequence of labelled messages.
+///
+/// Every message is prefixed by its label and length, so two different sequences of messages
+/// never produce the same input to the hash function.
+#[derive(Clone)]
+pub struct Transcript {
+    hasher: Sha256,
+}
+
+impl Transcript {
+    pub fn new(label: &[u8]) -> Self {
+        let mut transcript = Transcript {
+            hasher: Sha256::new(),
+        };
+        transcript.append(b"transcript", label);
+        transcript
+    }
+
+    pub fn append(&mut self, label: &[u8], data: &[u8]) {
+        self.hasher.update((label.len() as u64).to_be_bytes());
+        self.hasher.update(label);
+        self.hasher.update((data.len() as u64).to_be_bytes());
+        self.hasher.update(data);
+    }
+
+    /// Append the JSON encoding of `value`.
+    pub fn append_value<T: Serialize>(&mut self, label: &[u8], value: &T) {
+        let data = serde_json::to_vec(value).expect("Value could not be serialized.");
+        self.append(label, &data);
+    }
+
+    /// The hash of all messages so far. More messages can still be appended afterwards.
+    pub fn hash(&self) -> [u8; 32] {
+        self.hasher.clone().finalize().into()
+    }
+
+    /// Derive a challenge of `bits` bits from the messages so far, for the Fiat-Shamir
+    /// transform.
+    pub fn challenge(&self, label: &[u8], bits: usize) -> BigInt {
+        let mut transcript = self.clone();
+        transcript.append(b"challenge", label);
+
+        let len = bits.div_ceil(8);
+        let mut bytes = Vec::with_capacity(len + 32);
+        let mut counter = 0u64;
+        while bytes.len() < len {
+            let mut block = transcript.clone();
+            block.append(b"block", &counter.to_be_bytes());
+            bytes.extend_from_slice(&block.hash());
+            counter += 1;
+        }
+        bytes.truncate(len);
+
+        BigInt::from_bytes_be(Sign::Plus, &bytes) >> (len * 8 - bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use super::Transcript;
+
+    #[test]
+    fn message_boundaries_matter() {
+        let mut t1 = Transcript::new(b"test");
+        t1.append(b"a", b"bc");
+        let mut t2 = Transcript::new(b"test");
+        t2.append(b"a", b"b");
+        t2.append(b"", b"c");
+        assert_ne!(t1.hash(), t2.hash());
+    }
+
+    #[test]
+    fn hash_does_not_consume_transcript() {
+        let mut t1 = Transcript::new(b"test");
+        t1.append(b"a", b"b");
+        let before = t1.hash();
+        assert_eq!(before, t1.hash());
+        t1.append(b"c", b"d");
+        assert_ne!(before, t1.hash());
+    }
+
+    #[test]
+    fn challenge_has_requested_size() {
+        let t = Transcript::new(b"test");
+        for bits in [1, 9, 255, 256, 700] {
+            let e = t.challenge(b"e", bits);
+            assert!(e < BigInt::from(1) << bits);
+        }
+        assert_ne!(t.challenge(b"e", 256), t.challenge(b"f", 256));
+        assert!(t.challenge(b"e", 700) >= BigInt::from(1) << 600);
+    }
+}
@@ -0,0 +1,351 @@
+//! Compile an honest-verifier sigma protocol into one that is zero-knowledge against any
+//! verifier.
+//!
+//! The sigma protocols in this crate only hide the witness from verifiers that pick their
+//! challenge at random. A malicious verifier could instead pick the challenge as a function of
+//! the prover's initial message. This compiler stops that, by having the verifier commit to
+//! its challenge before it sees the initial message, and open the commitment afterwards:
+//!
+//! 1. The verifier commits to a challenge `e`.
+//! 2. The prover sends the initial message `a`.
+//! 3. The verifier opens the commitment to `e`.
+//! 4. If the opening is valid, the prover sends the response `z`.
+//! 5. The verifier checks `(a, e, z)`, and sends its verdict.
+//!
+//! The commitments use [`CommitmentScheme`], so the challenges must be integers. The
+//! commitment parameters must be generated so that the verifier does not know the discrete log
+//! of their `h`, e.g. by the prover, as the verifier could otherwise open to any challenge.
+//!
+//! [`ZkProver`] and [`ZkVerifier`] add the commitment and its opening around the messages of
+//! [`RemoteVerifierProtocol`] and [`RemoteProverProtocol`], so the compiled protocol binds its
+//! challenge to the channel and the session in the same way.
+
+use num::{BigInt, Signed};
+use serde::Serialize;
+
+use crate::{
+    applications::commitments::CommitmentScheme,
+    netutil::{Channel, IntKind, NetError, WireValue},
+    remote::{
+        bind_challenge, challenge_contexts, RemoteProverProtocol, RemoteProverProtocolError,
+        RemoteVerifierProtocol,
+    },
+    schnorr::SchnorrDiscreteLogInstance,
+    ChallengeSpace, SigmaProtocol,
+};
+
+#[derive(Debug)]
+pub enum ZkError<VError> {
+    /// The commitment parameters are not a valid group and generators.
+    InvalidParams,
+    /// The channel to the peer failed.
+    Net(NetError),
+    /// The verifier opened its commitment to something else than it committed to.
+    InvalidOpening,
+    /// The verifier rejected the proof.
+    Rejected,
+    /// The proof did not pass the verifier's check.
+    Verifier(VError),
+}
+
+impl<VError> From<NetError> for ZkError<VError> {
+    fn from(error: NetError) -> Self {
+        ZkError::Net(error)
+    }
+}
+
+/// The commitment scheme for `params`, which may come from the peer.
+fn scheme_for<VError>(
+    params: SchnorrDiscreteLogInstance,
+) -> Result<CommitmentScheme, ZkError<VError>> {
+    if !CommitmentScheme::check_params(&params) {
+        return Err(ZkError::InvalidParams);
+    }
+    Ok(CommitmentScheme::new(params))
+}
+
+/// The prover of a compiled protocol, against a verifier on the other end of a channel.
+pub struct ZkProver<P> {
+    remote: RemoteVerifierProtocol<P>,
+    scheme: CommitmentScheme,
+}
+
+impl<P> ZkProver<P> {
+    /// The prover of `instance` with `witness` over `channel`, with commitments under
+    /// `commitment_params`, which are checked first.
+    pub fn new<X, W, A, Z>(
+        instance: X,
+        witness: W,
+        channel: Channel,
+        commitment_params: SchnorrDiscreteLogInstance,
+    ) -> Result<Self, ZkError<()>>
+    where
+        P: SigmaProtocol<X, W, A, BigInt, Z>,
+        X: Serialize + ChallengeSpace,
+        A: WireValue,
+        Z: WireValue,
+    {
+        Ok(ZkProver {
+            scheme: scheme_for(commitment_params)?,
+            remote: RemoteVerifierProtocol::new((instance, channel), Some(witness)),
+        })
+    }
+
+    pub fn channel(&self) -> &Channel {
+        self.remote.channel()
+    }
+
+    /// Run the protocol. The prover only answers the challenge the verifier committed to.
+    pub fn run<X, W, A, Z>(&mut self) -> Result<(), ZkError<()>>
+    where
+        P: SigmaProtocol<X, W, A, BigInt, Z>,
+        X: Serialize + ChallengeSpace,
+        A: WireValue,
+        Z: WireValue,
+    {
+        let commitment: BigInt = self.remote.channel_mut().receive(IntKind::Element)?;
+        let a = self.remote.initial_message();
+
+        let (e, r): (BigInt, BigInt) = self.remote.channel_mut().receive(IntKind::Scalar)?;
+        // The scheme only binds `e` modulo `q`, and every `e + kq` would bind to a different
+        // challenge, so only the challenges the verifier could have picked are accepted.
+        if e.is_negative()
+            || e.bits() > self.remote.challenge_bits()
+            || !self.scheme.verify(&commitment, &e, &r)
+        {
+            return Err(ZkError::InvalidOpening);
+        }
+        let contexts = challenge_contexts(self.remote.channel());
+        let e = bind_challenge(e, self.remote.challenge_bits(), &contexts);
+
+        let z = self.remote.challenge_response(&e);
+        self.remote.check(a, e, z).map_err(|()| ZkError::Rejected)
+    }
+}
+
+/// The verifier of a compiled protocol, against a prover on the other end of a channel.
+pub struct ZkVerifier<P> {
+    remote: RemoteProverProtocol<P>,
+    scheme: CommitmentScheme,
+}
+
+impl<P> ZkVerifier<P> {
+    /// The verifier of `instance` over `channel`, with commitments under `commitment_params`,
+    /// which are checked first.
+    pub fn new<X, W, A, Z>(
+        instance: X,
+        channel: Channel,
+        commitment_params: SchnorrDiscreteLogInstance,
+    ) -> Result<Self, ZkError<P::VerifierError>>
+    where
+        P: SigmaProtocol<X, W, A, BigInt, Z>,
+        X: Serialize + ChallengeSpace,
+        A: WireValue,
+        Z: WireValue,
+    {
+        Ok(ZkVerifier {
+            scheme: scheme_for(commitment_params)?,
+            remote: RemoteProverProtocol::new((instance, channel), None),
+        })
+    }
+
+    pub fn channel(&self) -> &Channel {
+        self.remote.channel()
+    }
+
+    /// Run the protocol, and tell the prover whether its proof was accepted.
+    pub fn run<X, W, A, Z>(&mut self) -> Result<(), ZkError<P::VerifierError>>
+    where
+        P: SigmaProtocol<X, W, A, BigInt, Z>,
+        X: Serialize + ChallengeSpace,
+        A: WireValue,
+        Z: WireValue,
+    {
+        let e = self.remote.protocol_mut().challenge();
+        let (commitment, r) = self.scheme.commit(&e);
+        self.remote
+            .channel_mut()
+            .send(&commitment, IntKind::Element)?;
+        let a = self.remote.initial_message();
+
+        let opening = (e, r.expose().clone());
+        self.remote.channel_mut().send(&opening, IntKind::Scalar)?;
+        let contexts = challenge_contexts(self.remote.channel());
+        let e = bind_challenge(opening.0, self.remote.challenge_bits(), &contexts);
+
+        let z = self.remote.challenge_response(&e);
+        self.remote.check(a, e, z).map_err(|error| match error {
+            RemoteProverProtocolError::SubProtocolError(error) => ZkError::Verifier(error),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use num::{bigint::RandBigInt, BigInt, Zero};
+
+    use crate::{
+        applications::commitments::CommitmentScheme,
+        netutil::{Channel, Codec, IntKind, Role},
+        or::OrProtocol,
+        schnorr::{SchnorrDiscreteLogInstance, SchnorrDiscreteLogProtocol},
+    };
+
+    use super::{ZkError, ZkProver, ZkVerifier};
+
+    /// Commitment parameters in the same group as `instance`, with a fresh `h` whose discrete
+    /// log only the prover could know.
+    fn commitment_params(instance: &SchnorrDiscreteLogInstance) -> SchnorrDiscreteLogInstance {
+        let trapdoor = rand::thread_rng().gen_bigint_range(&BigInt::zero(), instance.q());
+        SchnorrDiscreteLogInstance::new(
+            instance.p().clone(),
+            instance.q().clone(),
+            instance.g().clone(),
+            instance.g().modpow(&trapdoor, instance.p()),
+        )
+    }
+
+    /// Two ends of a secure channel, the first for the prover and the second for the verifier.
+    fn channel_pair(instance: &SchnorrDiscreteLogInstance) -> (Channel, Channel) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let codec = Codec::binary_for(instance);
+        let responder_instance = instance.clone();
+        let responder = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut channel = Channel::new(stream, codec);
+            channel
+                .secure(&responder_instance, Role::Responder)
+                .unwrap();
+            channel
+        });
+        let mut channel = Channel::new(TcpStream::connect(addr).unwrap(), codec);
+        channel.secure(instance, Role::Initiator).unwrap();
+        (responder.join().unwrap(), channel)
+    }
+
+    #[test]
+    fn honest_run_is_accepted_remotely() {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(512, 64);
+        let (mut prover_channel, mut verifier_channel) = channel_pair(&instance);
+        let prover_instance = instance.clone();
+
+        let prover_handle = thread::spawn(move || {
+            // The prover picks the commitment parameters, so the verifier has no trapdoor.
+            let params = commitment_params(&prover_instance);
+            prover_channel.send(&params, IntKind::Element).unwrap();
+            let mut prover = ZkProver::<SchnorrDiscreteLogProtocol>::new(
+                prover_instance,
+                w,
+                prover_channel,
+                params,
+            )
+            .unwrap();
+            prover.run()
+        });
+
+        let params = verifier_channel.receive(IntKind::Element).unwrap();
+        let mut verifier =
+            ZkVerifier::<SchnorrDiscreteLogProtocol>::new(instance, verifier_channel, params)
+                .unwrap();
+        let verifier_res = verifier.run();
+
+        assert!(verifier_res.is_ok(), "{:?}", verifier_res);
+        assert!(prover_handle.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn compiled_or_protocol_is_accepted_remotely() {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let instances = (instance.clone(), commitment_params(&instance));
+        let params = commitment_params(&instance);
+        let (prover_channel, verifier_channel) = channel_pair(&instance);
+
+        type SchnorrOr = OrProtocol<SchnorrDiscreteLogProtocol, BigInt, BigInt>;
+        let (prover_instances, prover_params) = (instances.clone(), params.clone());
+        let prover_handle = thread::spawn(move || {
+            ZkProver::<SchnorrOr>::new(prover_instances, w, prover_channel, prover_params)
+                .unwrap()
+                .run()
+        });
+
+        let mut verifier =
+            ZkVerifier::<SchnorrOr>::new(instances, verifier_channel, params).unwrap();
+        let verifier_res = verifier.run();
+
+        assert!(verifier_res.is_ok(), "{:?}", verifier_res);
+        assert!(prover_handle.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn prover_refuses_to_answer_other_challenge() {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let params = commitment_params(&instance);
+        let scheme = CommitmentScheme::new(params.clone());
+        let (prover_channel, mut channel) = channel_pair(&instance);
+
+        let prover_handle = thread::spawn(move || {
+            ZkProver::<SchnorrDiscreteLogProtocol>::new(instance, w, prover_channel, params)
+                .unwrap()
+                .run()
+        });
+
+        let e = BigInt::from(1234);
+        let (c, r) = scheme.commit(&e);
+        channel.send(&c, IntKind::Element).unwrap();
+        let _: BigInt = channel.receive(IntKind::Element).unwrap();
+
+        // Having seen the initial message, the verifier tries to change its challenge.
+        let opening = (e + 1, r.expose().clone());
+        channel.send(&opening, IntKind::Scalar).unwrap();
+        let res = prover_handle.join().unwrap();
+        assert!(matches!(res, Err(ZkError::InvalidOpening)), "{:?}", res);
+    }
+
+    #[test]
+    fn prover_refuses_opening_to_challenge_plus_order() {
+        let (instance, w) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let q = instance.q().clone();
+        let params = commitment_params(&instance);
+        let scheme = CommitmentScheme::new(params.clone());
+        let (prover_channel, mut channel) = channel_pair(&instance);
+
+        let prover_handle = thread::spawn(move || {
+            ZkProver::<SchnorrDiscreteLogProtocol>::new(instance, w, prover_channel, params)
+                .unwrap()
+                .run()
+        });
+
+        let e = BigInt::from(1234);
+        let (c, r) = scheme.commit(&e);
+        channel.send(&c, IntKind::Element).unwrap();
+        let _: BigInt = channel.receive(IntKind::Element).unwrap();
+
+        // `e + q` opens the same commitment, but would be bound to a different challenge.
+        assert!(scheme.verify(&c, &(&e + &q), r.expose()));
+        let opening = (e + q, r.expose().clone());
+        channel.send(&opening, IntKind::Scalar).unwrap();
+        let res = prover_handle.join().unwrap();
+        assert!(matches!(res, Err(ZkError::InvalidOpening)), "{:?}", res);
+    }
+
+    #[test]
+    fn invalid_params_are_refused() {
+        let (instance, _) = SchnorrDiscreteLogInstance::generate(256, 64);
+        let (_, channel) = channel_pair(&instance);
+        // An even modulus, as a peer could send.
+        let params = SchnorrDiscreteLogInstance::new(
+            BigInt::from(1908),
+            BigInt::from(953),
+            BigInt::from(343),
+            BigInt::from(862),
+        );
+        let res = ZkVerifier::<SchnorrDiscreteLogProtocol>::new(instance, channel, params);
+        assert!(matches!(res, Err(ZkError::InvalidParams)));
+    }
+}